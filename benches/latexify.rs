@@ -0,0 +1,22 @@
+#![feature(test)]
+
+extern crate test;
+
+use citrus_cas::expression::{
+    expression_tree::Expression,
+    latex::{latexify, parse},
+};
+use test::{black_box, Bencher};
+
+// a sum of 500 terms mixing every kind of node latexify has to emit
+fn large_expression() -> Expression {
+    let term = "\\frac{x+1}{2}\\cdot\\sin(x)^{2+y}-\\left|z\\right|+f(x,<1,2>)";
+    let input = vec![term; 500].join("+");
+    parse(&input)
+}
+
+#[bench]
+fn bench_latexify_large(b: &mut Bencher) {
+    let expr = large_expression();
+    b.iter(|| latexify(black_box(&expr)));
+}
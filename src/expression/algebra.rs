@@ -0,0 +1,1003 @@
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+
+use core::cmp::Ordering;
+
+use crate::{
+    expression::{
+        eval::{evaluate, sqrt, Value, MAX_SUM_TERMS},
+        expression_tree::{
+            flatten_mul, rebuild_add, rebuild_mul, Atom, Comparison, Expression, Numeric,
+        },
+        matrix::{gcd, reduce},
+    },
+    ExpandError, SolveError,
+};
+
+// the cost of an expression used to compare equivalent forms, smaller is simpler
+pub fn cost(expr: &Expression) -> usize {
+    expr.node_count()
+}
+
+// distributes every product over the sums and differences it multiplies, and every whole power of
+// a sum over itself, then collects like terms
+// (a + b) * c -> a * c + b * c, (x + 1)^2 -> x * x + 2 * x + 1, 2 * (x + 1) - 2 -> 2 * x
+pub fn expand(expr: &Expression) -> Expression {
+    expand_bounded(expr, usize::MAX).expect("an unbounded expansion always succeeds")
+}
+
+// expand, giving up as soon as any intermediate result grows past max_nodes nodes, so input like
+// (a + b + c)^1000 fails quickly instead of exhausting memory
+pub fn expand_bounded(expr: &Expression, max_nodes: usize) -> Result<Expression, ExpandError> {
+    Ok(collect_like_terms(&distribute(expr, max_nodes)?.0))
+}
+
+// an expression along with its number of nodes, so the budget is checked without counting the
+// whole expression again at every level
+type Counted = (Expression, usize);
+
+fn distribute(expr: &Expression, max_nodes: usize) -> Result<Counted, ExpandError> {
+    let mut count = 1;
+    let distributed = expr.try_map_children(|e| {
+        let (e, n) = distribute(e, max_nodes)?;
+        count += n;
+        Ok(e)
+    })?;
+
+    match distributed {
+        Expression::Multiply(l, r) => {
+            let r_count = r.node_count();
+            multiply_out((*l, count - 1 - r_count), (*r, r_count), max_nodes)
+        }
+        // multiplied out one factor at a time with like terms collected in between, so (a + b)^n
+        // has n + 1 terms along the way rather than 2^n
+        Expression::Power(b, n) => match *n {
+            Expression::Atom(Atom::Numeric(Numeric::Integer(n)))
+                if n > 1 && matches!(*b, Expression::Add(_, _) | Expression::Subtract(_, _)) =>
+            {
+                let b_count = b.node_count();
+                let mut power = ((*b).clone(), b_count);
+                for _ in 1..n {
+                    let product = multiply_out(power, ((*b).clone(), b_count), max_nodes)?;
+                    let collected = collect_like_terms(&product.0);
+                    let collected_count = collected.node_count();
+                    power = (collected, collected_count);
+                }
+                within_budget(power, max_nodes)
+            }
+            n => within_budget((Expression::Power(b, Box::new(n)), count), max_nodes),
+        },
+        e => within_budget((e, count), max_nodes),
+    }
+}
+
+// the product of two already distributed factors, distributed over the sums of either one
+fn multiply_out(l: Counted, r: Counted, max_nodes: usize) -> Result<Counted, ExpandError> {
+    let product = |l, r| multiply_out(l, r, max_nodes);
+    // sums nest to the left, so only the right operand (a single term) is counted
+    let split = |a: Box<Expression>, b: Box<Expression>, count: usize| {
+        let b_count = b.node_count();
+        ((*a, count - 1 - b_count), (*b, b_count))
+    };
+    let join = |op: fn(Box<Expression>, Box<Expression>) -> Expression, a: Counted, b: Counted| {
+        (op(Box::new(a.0), Box::new(b.0)), 1 + a.1 + b.1)
+    };
+
+    let multiplied = match (l, r) {
+        ((Expression::Add(a, b), count), c) => {
+            let (a, b) = split(a, b, count);
+            join(Expression::Add, product(a, c.clone())?, product(b, c)?)
+        }
+        ((Expression::Subtract(a, b), count), c) => {
+            let (a, b) = split(a, b, count);
+            join(Expression::Subtract, product(a, c.clone())?, product(b, c)?)
+        }
+        (c, (Expression::Add(a, b), count)) => {
+            let (a, b) = split(a, b, count);
+            join(Expression::Add, product(c.clone(), a)?, product(c, b)?)
+        }
+        (c, (Expression::Subtract(a, b), count)) => {
+            let (a, b) = split(a, b, count);
+            join(Expression::Subtract, product(c.clone(), a)?, product(c, b)?)
+        }
+        ((l, l_count), (r, r_count)) => (
+            Expression::Multiply(Box::new(l), Box::new(r)),
+            1 + l_count + r_count,
+        ),
+    };
+
+    within_budget(multiplied, max_nodes)
+}
+
+fn within_budget((expr, count): Counted, max_nodes: usize) -> Result<Counted, ExpandError> {
+    match count > max_nodes {
+        true => Err(ExpandError::TooLarge { max_nodes }),
+        false => Ok((expr, count)),
+    }
+}
+
+// merges the terms of every sum which only differ by a numeric coefficient, dropping the ones
+// that cancel out, the remaining terms keep the order in which they first appear
+fn collect_like_terms(expr: &Expression) -> Expression {
+    let expr = expr.map_children(collect_like_terms);
+    if !matches!(expr, Expression::Add(_, _) | Expression::Subtract(_, _)) {
+        return expr;
+    }
+
+    let mut terms = Vec::new();
+    signed_terms(&expr, false, &mut terms);
+
+    let mut collected: Vec<(Numeric, Vec<Expression>)> = Vec::new();
+    for (negative, term) in terms {
+        let (coefficient, factors) = split_coefficient(term);
+        let coefficient = if negative { -coefficient } else { coefficient };
+        match collected.iter_mut().find(|(_, fs)| *fs == factors) {
+            Some((c, _)) => *c = *c + coefficient,
+            None => collected.push((coefficient, factors)),
+        }
+    }
+
+    let remaining = collected
+        .into_iter()
+        .filter(|(c, _)| *c != Numeric::Integer(0))
+        .map(|(c, fs)| {
+            let negative = f32::from(c) < 0.0;
+            let c = if negative { -c } else { c };
+            let term = match (c == Numeric::Integer(1), fs.is_empty()) {
+                (_, true) => Expression::Atom(Atom::Numeric(c)),
+                (true, false) => rebuild_mul(fs),
+                (false, false) => Expression::Multiply(
+                    Box::new(Expression::Atom(Atom::Numeric(c))),
+                    Box::new(rebuild_mul(fs)),
+                ),
+            };
+            (negative, term)
+        })
+        .collect::<Vec<_>>();
+
+    if remaining.is_empty() {
+        Expression::Atom(Atom::Numeric(Numeric::Integer(0)))
+    } else {
+        rebuild_signed_terms(remaining)
+    }
+}
+
+// splits a term into the product of its numeric factors and its other factors, in canonical order
+fn split_coefficient(term: &Expression) -> (Numeric, Vec<Expression>) {
+    let mut coefficient = Numeric::Integer(1);
+    let mut rest = Vec::new();
+    for factor in flatten_mul(term) {
+        match factor {
+            Expression::Atom(Atom::Numeric(n)) => coefficient = coefficient * n,
+            Expression::Negate(e) => {
+                let (c, fs) = split_coefficient(&e);
+                coefficient = -(coefficient * c);
+                rest.extend(fs);
+            }
+            e => rest.push(e),
+        }
+    }
+    rest.sort_by(|a, b| a.canonical_cmp(b));
+
+    (coefficient, rest)
+}
+
+// pulls the factors shared by every term of a sum or difference out in front of it
+// a * b + a * c -> a * (b + c)
+pub fn factor(expr: &Expression) -> Expression {
+    let expr = expr.map_children(factor);
+
+    let mut terms = Vec::new();
+    signed_terms(&expr, false, &mut terms);
+    if terms.len() < 2 {
+        return expr;
+    }
+
+    let mut factors: Vec<Vec<Expression>> =
+        terms.iter().map(|(_, term)| flatten_mul(term)).collect();
+
+    let mut common = Vec::new();
+    for candidate in factors[0].clone() {
+        if matches!(candidate, Expression::Atom(Atom::Numeric(_))) {
+            continue;
+        }
+        let positions: Option<Vec<usize>> = factors
+            .iter()
+            .map(|fs| fs.iter().position(|f| *f == candidate))
+            .collect();
+        if let Some(positions) = positions {
+            for (fs, i) in factors.iter_mut().zip(positions) {
+                fs.remove(i);
+            }
+            common.push(candidate);
+        }
+    }
+
+    if common.is_empty() {
+        return expr;
+    }
+
+    let remaining = terms
+        .iter()
+        .zip(factors)
+        .map(|((negative, _), fs)| (*negative, rebuild_mul(fs)))
+        .collect();
+
+    common.push(rebuild_signed_terms(remaining));
+    rebuild_mul(common)
+}
+
+// pulls the greatest common divisor of the integer coefficients of a sum out in front of it
+// 6 * x + 9 -> 3 * (2 * x + 3), sums with a non-integer coefficient are left as they are
+pub fn factor_common(expr: &Expression) -> Expression {
+    let mut terms = Vec::new();
+    signed_terms(expr, false, &mut terms);
+    if terms.len() < 2 {
+        return expr.clone();
+    }
+
+    let mut split = Vec::new();
+    for (negative, term) in terms {
+        match split_coefficient(term) {
+            (Numeric::Integer(c), factors) => split.push((negative, c, factors)),
+            _ => return expr.clone(),
+        }
+    }
+
+    let divisor = split
+        .iter()
+        .fold(0, |acc, (_, c, _)| gcd(acc, c.unsigned_abs()));
+    if divisor <= 1 {
+        return expr.clone();
+    }
+
+    let remaining = split
+        .into_iter()
+        .map(|(negative, c, factors)| {
+            let c = Numeric::Integer(c.abs() / divisor as i32);
+            let term = match (c == Numeric::Integer(1), factors.is_empty()) {
+                (_, true) => Expression::Atom(Atom::Numeric(c)),
+                (true, false) => rebuild_mul(factors),
+                (false, false) => Expression::Multiply(
+                    Box::new(Expression::Atom(Atom::Numeric(c))),
+                    Box::new(rebuild_mul(factors)),
+                ),
+            };
+            (negative != (c < Numeric::Integer(0)), term)
+        })
+        .collect();
+
+    Expression::Multiply(
+        Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(
+            divisor as i32,
+        )))),
+        Box::new(rebuild_signed_terms(remaining)),
+    )
+}
+
+// collects the terms of a chain of additions and subtractions, with whether each is subtracted
+fn signed_terms<'a>(expr: &'a Expression, negative: bool, terms: &mut Vec<(bool, &'a Expression)>) {
+    match expr {
+        Expression::Add(l, r) => {
+            signed_terms(l, negative, terms);
+            signed_terms(r, negative, terms);
+        }
+        Expression::Subtract(l, r) => {
+            signed_terms(l, negative, terms);
+            signed_terms(r, !negative, terms);
+        }
+        e => terms.push((negative, e)),
+    }
+}
+
+// rebuilds a chain of additions and subtractions from its terms, negating a subtracted first term
+fn rebuild_signed_terms(terms: Vec<(bool, Expression)>) -> Expression {
+    let mut terms = terms.into_iter();
+    let (negative, first) = terms.next().expect("a sum has at least one term");
+    let first = if negative {
+        Expression::Negate(Box::new(first))
+    } else {
+        first
+    };
+    terms.fold(first, |acc, (negative, term)| match negative {
+        true => Expression::Subtract(Box::new(acc), Box::new(term)),
+        false => Expression::Add(Box::new(acc), Box::new(term)),
+    })
+}
+
+// the degree of a polynomial in var once it is expanded and its like terms are collected
+// 3 * x^2 + x -> 2, constants (and zero) have degree 0
+// expressions which are not polynomials in var, sin(x), 1 / x or x^n, have no degree, and neither
+// do ones whose expansion grows past MAX_POLYNOMIAL_NODES nodes
+pub fn degree(expr: &Expression, var: char) -> Option<i32> {
+    polynomial_terms(expr, var).map(|terms| terms.last().map_or(0, |(d, _)| *d))
+}
+
+// the coefficient of the highest power of var in a polynomial, 3 * x^2 + x -> 3, the coefficient
+// may itself contain other variables, a * x + b * x -> a + b
+pub fn leading_coefficient(expr: &Expression, var: char) -> Option<Expression> {
+    polynomial_terms(expr, var).map(|terms| match terms.into_iter().last() {
+        Some((_, coefficient)) => coefficient,
+        None => Expression::int(0),
+    })
+}
+
+// the most nodes the expansion of a polynomial may grow to before its terms are given up on
+const MAX_POLYNOMIAL_NODES: usize = 10_000;
+
+// the nonzero coefficients of a polynomial in var, by increasing degree
+fn polynomial_terms(expr: &Expression, var: char) -> Option<Vec<(i32, Expression)>> {
+    let expanded = expand_bounded(&normalize_division(expr), MAX_POLYNOMIAL_NODES).ok()?;
+    let mut terms = Vec::new();
+    signed_terms(&expanded, false, &mut terms);
+
+    let mut by_degree: BTreeMap<i32, Vec<(bool, Expression)>> = BTreeMap::new();
+    for (negative, term) in terms {
+        let (c, factors) = split_coefficient(term);
+        let c = if negative { -c } else { c };
+
+        let mut degree = 0;
+        let mut rest = Vec::new();
+        for factor in factors {
+            match monomial_degree(&factor, var)? {
+                0 => rest.push(factor),
+                d => degree += d,
+            }
+        }
+
+        let coefficient = match (c, rest.is_empty()) {
+            (_, true) => Expression::Atom(Atom::Numeric(c)),
+            (Numeric::Integer(1), false) => rebuild_mul(rest),
+            (Numeric::Integer(-1), false) => Expression::Negate(Box::new(rebuild_mul(rest))),
+            (_, false) => Expression::Multiply(
+                Box::new(Expression::Atom(Atom::Numeric(c))),
+                Box::new(rebuild_mul(rest)),
+            ),
+        };
+        by_degree
+            .entry(degree)
+            .or_default()
+            .push((false, coefficient));
+    }
+
+    Some(
+        by_degree
+            .into_iter()
+            .map(|(d, cs)| (d, collect_like_terms(&rebuild_signed_terms(cs))))
+            .filter(|(_, c)| *c != Expression::int(0))
+            .collect(),
+    )
+}
+
+// the power of var in a factor of an expanded term, factors without var have degree 0
+fn monomial_degree(factor: &Expression, var: char) -> Option<i32> {
+    match factor {
+        e if !e.contains_variable(var) => Some(0),
+        Expression::Atom(Atom::Variable(_)) => Some(1),
+        Expression::Power(b, n) => match (&**b, &**n) {
+            (
+                Expression::Atom(Atom::Variable(_)),
+                Expression::Atom(Atom::Numeric(Numeric::Integer(n))),
+            ) if *n > 0 => Some(*n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// the two roots of a quadratic equation in var, l = r or an expression equal to zero, by the
+// quadratic formula, ordered by their real and then imaginary parts
+// the roots are exact when the coefficients are rational and the discriminant is a square, a
+// negative discriminant gives a pair of complex conjugates and a repeated root is listed twice
+pub fn solve_quadratic(eq: &Expression, var: char) -> Result<Vec<Expression>, SolveError> {
+    let expr = match eq {
+        Expression::Relation(Comparison::Equal, l, r) => Expression::Subtract(l.clone(), r.clone()),
+        Expression::Relation(_, _, _) | Expression::And(_) => return Err(SolveError::NotQuadratic),
+        e => e.clone(),
+    };
+    let terms = polynomial_terms(&expr, var).ok_or(SolveError::NotQuadratic)?;
+    if terms.last().map(|(d, _)| *d) != Some(2) {
+        return Err(SolveError::NotQuadratic);
+    }
+
+    let coefficient = |degree| match terms.iter().find(|(d, _)| *d == degree) {
+        Some((_, c)) => evaluate(c)
+            .and_then(Value::scalar)
+            .map_err(|_| SolveError::NotNumeric),
+        None => Ok(Numeric::Integer(0)),
+    };
+    let (a, b, c) = (coefficient(2)?, coefficient(1)?, coefficient(0)?);
+
+    let discriminant = b * b - Numeric::Integer(4) * a * c;
+    let root = exact_sqrt(discriminant).unwrap_or_else(|| sqrt(discriminant));
+    let mut roots = [-b - root, -b + root].map(|n| reduce(n / (Numeric::Integer(2) * a)));
+    roots.sort_by(|x, y| x.parts().partial_cmp(&y.parts()).unwrap_or(Ordering::Equal));
+
+    Ok(roots
+        .into_iter()
+        .map(|n| Expression::Atom(Atom::Numeric(n)))
+        .collect())
+}
+
+// the square root of a rational square, 9/4 -> 3/2
+fn exact_sqrt(n: Numeric) -> Option<Numeric> {
+    match reduce(n) {
+        Numeric::Integer(i) => integer_sqrt(i).map(Numeric::Integer),
+        Numeric::Fraction(p, q) => Some(Numeric::Fraction(integer_sqrt(p)?, integer_sqrt(q)?)),
+        _ => None,
+    }
+}
+
+fn integer_sqrt(i: i32) -> Option<i32> {
+    if i < 0 {
+        return None;
+    }
+    let root = libm::sqrtf(i as f32) as i64;
+    (root - 1..=root + 1)
+        .find(|r| *r >= 0 && r * r == i as i64)
+        .map(|r| r as i32)
+}
+
+// rewrites every division by a nonzero numeric constant as a multiplication by its reciprocal, so
+// the quotient takes part in coefficient collection, x / 2 -> (1 / 2) * x
+// integer divisors give exact fractions, x / 2 + x / 2 expands to x
+pub fn normalize_division(expr: &Expression) -> Expression {
+    match expr.map_children(normalize_division) {
+        Expression::Divide(l, r) => match *r {
+            Expression::Atom(Atom::Numeric(c)) if c != Numeric::Integer(0) => Expression::Multiply(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(1) / c))),
+                l,
+            ),
+            r => Expression::Divide(l, Box::new(r)),
+        },
+        e => e,
+    }
+}
+
+// rewrites every subtraction as the addition of a negation, a - b -> a + (-b), so sums only have
+// one form, denormalize turns them back into subtractions
+pub fn normalize_sub(expr: &Expression) -> Expression {
+    match expr.map_children(normalize_sub) {
+        Expression::Subtract(l, r) => Expression::Add(l, Box::new(Expression::Negate(r))),
+        e => e,
+    }
+}
+
+// rewrites every addition of a negation as a subtraction, a + (-b) -> a - b, for display
+pub fn denormalize(expr: &Expression) -> Expression {
+    match expr.map_children(denormalize) {
+        Expression::Add(l, r) => match *r {
+            Expression::Negate(r) => Expression::Subtract(l, r),
+            r => Expression::Add(l, Box::new(r)),
+        },
+        e => e,
+    }
+}
+
+// multiplies the numeric factors of every product into one leading coefficient, keeping the other
+// factors in order, 2 * x * 3 -> 6 * x, a coefficient of 1 is dropped and one of 0 leaves only 0
+pub fn fold_numeric_factors(expr: &Expression) -> Expression {
+    let expr = expr.map_children(fold_numeric_factors);
+    if !matches!(expr, Expression::Multiply(_, _)) {
+        return expr;
+    }
+
+    let mut coefficient = Numeric::Integer(1);
+    let mut rest = Vec::new();
+    for factor in flatten_mul(&expr) {
+        match factor {
+            Expression::Atom(Atom::Numeric(n)) => coefficient = coefficient * n,
+            e => rest.push(e),
+        }
+    }
+
+    if coefficient == Numeric::Integer(0) {
+        return Expression::Atom(Atom::Numeric(Numeric::Integer(0)));
+    }
+    if coefficient != Numeric::Integer(1) {
+        rest.insert(0, Expression::Atom(Atom::Numeric(coefficient)));
+    }
+    rebuild_mul(rest)
+}
+
+// combines the terms of every sum of fractions over one denominator, the product of the distinct
+// factors of their denominators with the least common multiple of their integer coefficients
+// 1 / x + 1 / y -> (y + x) / (x * y), 1 / 2 + 1 / 3 -> (3 + 2) / 6
+// sums without a fraction, or with a denominator whose coefficient is not a whole number, are left
+// as they are
+pub fn common_denominator(expr: &Expression) -> Expression {
+    let expr = expr.map_children(common_denominator);
+
+    let mut terms = Vec::new();
+    signed_terms(&expr, false, &mut terms);
+    if terms.len() < 2 {
+        return expr;
+    }
+
+    let mut fractions = Vec::new();
+    for (negative, term) in terms {
+        let (negative, term) = match term {
+            Expression::Negate(e) => (!negative, &**e),
+            term => (negative, term),
+        };
+        let (numerator, denominator) = match term {
+            Expression::Divide(n, d) => ((**n).clone(), split_coefficient(d)),
+            Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))) => (
+                Expression::Atom(Atom::Numeric(Numeric::Integer(*n))),
+                (Numeric::Integer(*d), Vec::new()),
+            ),
+            term => (term.clone(), (Numeric::Integer(1), Vec::new())),
+        };
+        match denominator {
+            (Numeric::Integer(c), factors) if c != 0 => {
+                fractions.push((negative, numerator, c, factors))
+            }
+            _ => return expr,
+        }
+    }
+    if fractions
+        .iter()
+        .all(|(_, _, c, factors)| *c == 1 && factors.is_empty())
+    {
+        return expr;
+    }
+
+    // every factor appears as often as it does in the denominator which has it the most times
+    let mut coefficient = 1;
+    let mut common: Vec<Expression> = Vec::new();
+    for (_, _, c, factors) in &fractions {
+        coefficient = match lcm(coefficient, *c) {
+            Some(l) => l,
+            None => return expr,
+        };
+        let mut unmatched = common.clone();
+        for f in factors {
+            match unmatched.iter().position(|u| u == f) {
+                Some(i) => {
+                    unmatched.remove(i);
+                }
+                None => common.push(f.clone()),
+            }
+        }
+    }
+
+    // each numerator is scaled by the factors of the common denominator missing from its own
+    let numerators = fractions
+        .into_iter()
+        .map(|(negative, numerator, c, factors)| {
+            let mut missing = common.clone();
+            for f in &factors {
+                let i = missing.iter().position(|m| m == f).unwrap();
+                missing.remove(i);
+            }
+            missing.insert(
+                0,
+                Expression::Atom(Atom::Numeric(Numeric::Integer(coefficient / c))),
+            );
+            missing.push(numerator);
+            (negative, fold_numeric_factors(&rebuild_mul(missing)))
+        })
+        .collect();
+
+    if coefficient != 1 {
+        common.insert(
+            0,
+            Expression::Atom(Atom::Numeric(Numeric::Integer(coefficient))),
+        );
+    }
+    Expression::Divide(
+        Box::new(rebuild_signed_terms(numerators)),
+        Box::new(rebuild_mul(common)),
+    )
+}
+
+// unrolls every sum with whole number bounds into the additions it stands for, with each value of
+// the index in turn put in its body, \\sum_{i=1}^{3} i -> 1 + 2 + 3, an empty range is 0
+// the bodies are unrolled again, so sums inside them bounded by the index are unrolled too
+// sums with any other bounds, or with more than MAX_SUM_TERMS terms, are left as they are
+pub fn expand_sum(expr: &Expression) -> Expression {
+    match expr.map_children(expand_sum) {
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => match (*lower, *upper) {
+            (
+                Expression::Atom(Atom::Numeric(Numeric::Integer(l))),
+                Expression::Atom(Atom::Numeric(Numeric::Integer(u))),
+            ) if (u as i64 - l as i64) < MAX_SUM_TERMS => rebuild_add(
+                (l..=u)
+                    .map(|k| {
+                        let value = Expression::Atom(Atom::Numeric(Numeric::Integer(k)));
+                        expand_sum(&body.substitute_many(&BTreeMap::from([(index, value)])))
+                    })
+                    .collect(),
+            ),
+            (lower, upper) => Expression::Sum {
+                index,
+                lower: Box::new(lower),
+                upper: Box::new(upper),
+                body,
+            },
+        },
+        e => e,
+    }
+}
+
+// the least common multiple of two nonzero integers, if it fits
+fn lcm(a: i32, b: i32) -> Option<i32> {
+    (a / gcd(a.unsigned_abs(), b.unsigned_abs()) as i32).checked_mul(b)
+}
+
+// keeps whichever of the expression, its expansion, its factorization and the form with its
+// numeric factors folded costs the least
+// children are chosen first, so the choice is made separately for every subtree, and an expansion
+// that grows past EXPANSION_GROWTH times the subtree is given up on, (a + b + c)^30 is kept as is
+pub fn simplify_distributive(expr: &Expression) -> Expression {
+    let expr = expr.map_children(simplify_distributive);
+    let candidates = [
+        expand_bounded(&expr, EXPANSION_GROWTH * cost(&expr)).ok(),
+        Some(factor(&expr)),
+        Some(fold_numeric_factors(&expr)),
+    ];
+
+    candidates
+        .into_iter()
+        .flatten()
+        .fold(expr, |best, candidate| {
+            if cost(&candidate) < cost(&best) {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+// how many times its size an expansion may grow to while simplifying, one that grows further is
+// rarely cheaper once its like terms are collected
+const EXPANSION_GROWTH: usize = 4;
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use core::str::FromStr;
+
+    use super::{
+        common_denominator, cost, degree, denormalize, expand, expand_bounded, expand_sum, factor,
+        factor_common, fold_numeric_factors, leading_coefficient, normalize_division,
+        normalize_sub, simplify_distributive, solve_quadratic,
+    };
+    use crate::{
+        expression::{
+            expression_tree::{flatten_add, Atom, Expression, Numeric},
+            latex,
+        },
+        ExpandError, SolveError,
+    };
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand(&parse("a * (b + c)")), parse("a * b + a * c"));
+        assert_eq!(
+            expand(&parse("(a - b) * (c + d)")),
+            parse("a * c + a * d - b * c - b * d")
+        );
+        assert_eq!(expand(&parse("f((a + b) * c)")), parse("f(a * c + b * c)"));
+    }
+
+    #[test]
+    fn test_expand_like_terms() {
+        assert_eq!(expand(&parse("2 * (x + 1) - 2")), parse("2 * x"));
+        assert_eq!(expand(&parse("(x + y) * (x - y)")), parse("x * x - y * y"));
+        assert_eq!(expand(&parse("x * (y - 1) + x")), parse("x * y"));
+        assert_eq!(expand(&parse("a - a")), parse("0"));
+    }
+
+    #[test]
+    fn test_expand_powers() {
+        assert_eq!(expand(&parse("(x + 1)^2")), parse("x * x + 2 * x + 1"));
+        assert_eq!(
+            expand(&parse("(a - b)^2 * c")),
+            expand(&parse("(a - b) * (a - b) * c"))
+        );
+        assert_eq!(expand(&parse("(a + b)^n")), parse("(a + b)^n"));
+    }
+
+    #[test]
+    fn test_expand_bounded() {
+        let expr = parse("(a + b)^30");
+
+        assert_eq!(
+            expand_bounded(&expr, 500),
+            Err(ExpandError::TooLarge { max_nodes: 500 })
+        );
+        assert_eq!(
+            expand_bounded(&parse("(a + b + c + d + e)^1000"), 10_000),
+            Err(ExpandError::TooLarge { max_nodes: 10_000 })
+        );
+
+        let expanded = expand_bounded(&expr, 100_000).unwrap();
+        assert_eq!(flatten_add(&expanded).len(), 31);
+    }
+
+    #[test]
+    fn test_factor() {
+        assert_eq!(factor(&parse("a * b + a * c")), parse("a * (b + c)"));
+        assert_eq!(
+            factor(&parse("x * y - x * y * z")),
+            parse("x * y * (1 - z)")
+        );
+        assert_eq!(factor(&parse("a * b + c")), parse("a * b + c"));
+    }
+
+    #[test]
+    fn test_factor_common() {
+        assert_eq!(factor_common(&parse("6 * x + 9")), parse("3 * (2 * x + 3)"));
+        assert_eq!(
+            factor_common(&parse("4 * x^2 + 8 * x")),
+            parse("4 * (x^2 + 2 * x)")
+        );
+        assert_eq!(
+            factor_common(&parse("10 * x - 15")),
+            parse("5 * (2 * x - 3)")
+        );
+        assert_eq!(factor_common(&parse("2 * x + 3")), parse("2 * x + 3"));
+        assert_eq!(factor_common(&parse("0.5 * x + 2")), parse("0.5 * x + 2"));
+    }
+
+    #[test]
+    fn test_fold_numeric_factors() {
+        assert_eq!(fold_numeric_factors(&parse("2 * x * 3")), parse("6 * x"));
+        assert_eq!(fold_numeric_factors(&parse("0 * x * y")), parse("0"));
+        assert_eq!(
+            fold_numeric_factors(&parse("x * 2 * y * 0.5")),
+            parse("x * y")
+        );
+        assert_eq!(
+            fold_numeric_factors(&parse("f(2 * 3 * x) * 0.5")),
+            parse("0.5 * f(6 * x)")
+        );
+        assert_eq!(fold_numeric_factors(&parse("x * y")), parse("x * y"));
+    }
+
+    #[test]
+    fn test_normalize_sub() {
+        assert_eq!(normalize_sub(&parse("a - b")), parse("a + (-b)"));
+        assert_eq!(
+            normalize_sub(&parse("a - (b - c) * 2")),
+            parse("a + (-((b + (-c)) * 2))")
+        );
+        assert_eq!(denormalize(&parse("x + (-y) + z")), parse("x - y + z"));
+
+        for s in [
+            "a - b - c",
+            "x^2 - 2 * x + 1",
+            "sin(x - 1) - <a - b, c>",
+            "-a - (b - c)",
+        ] {
+            let expr = parse(s);
+            assert_eq!(denormalize(&normalize_sub(&expr)), expr);
+            assert!(!normalize_sub(&expr)
+                .descendants()
+                .any(|e| matches!(e, Expression::Subtract(_, _))));
+        }
+        for s in ["a + (-b)", "x + (-(y + (-z)))"] {
+            let expr = parse(s);
+            assert_eq!(normalize_sub(&denormalize(&expr)), expr);
+        }
+
+        assert_eq!(expand(&normalize_sub(&parse("2 * x - x"))), parse("x"));
+    }
+
+    #[test]
+    fn test_simplify_distributive_factor() {
+        let expr = parse("a * b + a * c");
+        let simplified = simplify_distributive(&expr);
+
+        assert_eq!(simplified, parse("a * (b + c)"));
+        assert!(cost(&simplified) < cost(&expr));
+    }
+
+    #[test]
+    fn test_simplify_distributive_expand() {
+        let expr = parse("2 * (x + 1) - 2");
+        let simplified = simplify_distributive(&expr);
+
+        assert_eq!(simplified, parse("2 * x"));
+        assert!(cost(&simplified) < cost(&expr));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_simplify_distributive_large_power() {
+        let expr = parse("(a + b + c)^30");
+        let start = std::time::Instant::now();
+
+        assert_eq!(simplify_distributive(&expr), expr);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_common_denominator() {
+        assert_eq!(
+            common_denominator(&parse("1 / x + 1 / y")),
+            parse("(y + x) / (x * y)")
+        );
+        assert_eq!(
+            common_denominator(&parse("1/2 + 1/3")),
+            parse("(3 + 2) / 6")
+        );
+        assert_eq!(
+            common_denominator(&parse("a / (2 * x) - b / (3 * x)")),
+            parse("(3 * a - 2 * b) / (6 * x)")
+        );
+        assert_eq!(
+            common_denominator(&parse("x + 1 / x")),
+            parse("(x * x + 1) / x")
+        );
+
+        for s in ["x + y", "1 / x", "x / 0.5 + 1 / y"] {
+            assert_eq!(common_denominator(&parse(s)), parse(s));
+        }
+    }
+
+    #[test]
+    fn test_expand_sum() {
+        assert_eq!(
+            expand_sum(&latex::parse("\\sum_{i=1}^{3} i")),
+            parse("1 + 2 + 3")
+        );
+        assert_eq!(
+            expand_sum(&latex::parse("\\sum_{k=0}^{2} k^2 \\cdot x + 1")),
+            parse("(0^2 * x + 1^2 * x + 2^2 * x) + 1")
+        );
+        assert_eq!(expand_sum(&latex::parse("\\sum_{k=1}^{0} k")), parse("0"));
+
+        let open = latex::parse("\\sum_{k=1}^{n} k");
+        assert_eq!(expand_sum(&open), open);
+
+        assert_eq!(
+            expand_sum(&latex::parse("\\sum_{j=1}^{2}\\sum_{k=1}^{j} k")),
+            parse("1 + (1 + 2)")
+        );
+
+        let huge = latex::parse("\\sum_{k=1}^{1000000000} k");
+        assert_eq!(expand_sum(&huge), huge);
+    }
+
+    #[test]
+    fn test_simplify_distributive_fold() {
+        assert_eq!(simplify_distributive(&parse("2 * x * 3")), parse("6 * x"));
+        assert_eq!(
+            simplify_distributive(&parse("f(x * 4 * 0.25)")),
+            parse("f(x)")
+        );
+    }
+
+    #[test]
+    fn test_degree() {
+        assert_eq!(degree(&parse("3 * x^2 + x"), 'x'), Some(2));
+        assert_eq!(
+            leading_coefficient(&parse("3 * x^2 + x"), 'x'),
+            Some(parse("3"))
+        );
+
+        assert_eq!(degree(&parse("(x + 1)^2 * (x - 2)"), 'x'), Some(3));
+        assert_eq!(degree(&parse("(x + 1) * (x - 1) - x^2"), 'x'), Some(0));
+        assert_eq!(
+            leading_coefficient(&parse("(x + 1) * (x - 1) - x^2"), 'x'),
+            Some(Expression::int(-1))
+        );
+        assert_eq!(
+            leading_coefficient(&parse("a * x^3 + b * x^3 - x"), 'x'),
+            Some(parse("a + b"))
+        );
+        assert_eq!(
+            leading_coefficient(&parse("x - 2 * x^2"), 'x'),
+            Some(Expression::int(-2))
+        );
+        assert_eq!(degree(&parse("y^2 + 1"), 'x'), Some(0));
+        assert_eq!(degree(&parse("x / 2"), 'x'), Some(1));
+    }
+
+    #[test]
+    fn test_degree_not_polynomial() {
+        for s in ["sin(x) * x", "x^n", "1 / x", "x^(1 / 2)", "2^x"] {
+            assert_eq!(degree(&parse(s), 'x'), None, "{}", s);
+            assert_eq!(leading_coefficient(&parse(s), 'x'), None, "{}", s);
+        }
+
+        // too large to expand
+        let huge = parse("(x + y + z + w + v)^1000");
+        assert_eq!(degree(&huge, 'x'), None);
+        assert_eq!(leading_coefficient(&huge, 'x'), None);
+    }
+
+    #[test]
+    fn test_solve_quadratic() {
+        let roots = solve_quadratic(&latex::parse("x^2 - 5x + 6 = 0"), 'x').unwrap();
+        assert_eq!(roots, [Expression::int(2), Expression::int(3)]);
+        assert!(roots
+            .iter()
+            .all(|r| matches!(r, Expression::Atom(Atom::Numeric(Numeric::Integer(_))))));
+
+        assert_eq!(
+            solve_quadratic(&parse("4 * x^2 - 1"), 'x').unwrap(),
+            [
+                Expression::Atom(Atom::Numeric(Numeric::Fraction(-1, 2))),
+                Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2)))
+            ]
+        );
+        assert_eq!(
+            solve_quadratic(&latex::parse("(x - 1)^2 = 0"), 'x').unwrap(),
+            [Expression::int(1), Expression::int(1)]
+        );
+
+        let irrational = solve_quadratic(&parse("x^2 - 2"), 'x').unwrap();
+        match irrational[..] {
+            [Expression::Atom(Atom::Numeric(Numeric::Decimal(a))), Expression::Atom(Atom::Numeric(Numeric::Decimal(b)))] =>
+            {
+                assert!((a + core::f32::consts::SQRT_2).abs() < 1e-6);
+                assert!((b - core::f32::consts::SQRT_2).abs() < 1e-6);
+            }
+            _ => panic!("expected two decimal roots, found {:?}", irrational),
+        }
+    }
+
+    #[test]
+    fn test_solve_quadratic_complex() {
+        assert_eq!(
+            solve_quadratic(&latex::parse("x^2 + 2x + 5 = 0"), 'x').unwrap(),
+            [
+                Expression::Atom(Atom::Numeric(Numeric::complex(-1.0, -2.0))),
+                Expression::Atom(Atom::Numeric(Numeric::complex(-1.0, 2.0)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solve_quadratic_errors() {
+        assert_eq!(
+            solve_quadratic(&parse("x^3 - 1"), 'x'),
+            Err(SolveError::NotQuadratic)
+        );
+        assert_eq!(
+            solve_quadratic(&parse("sin(x)^2 + x"), 'x'),
+            Err(SolveError::NotQuadratic)
+        );
+        assert_eq!(
+            solve_quadratic(&latex::parse("x^2 < 1"), 'x'),
+            Err(SolveError::NotQuadratic)
+        );
+        assert_eq!(
+            solve_quadratic(&parse("a * x^2 + 1"), 'x'),
+            Err(SolveError::NotNumeric)
+        );
+    }
+
+    #[test]
+    fn test_normalize_division() {
+        let scaled = |n, d, e: &str| {
+            Expression::Multiply(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d)))),
+                Box::new(parse(e)),
+            )
+        };
+
+        assert_eq!(normalize_division(&parse("x / 2")), scaled(1, 2, "x"));
+        assert_eq!(normalize_division(&parse("x / y")), parse("x / y"));
+        assert_eq!(normalize_division(&parse("x / 0")), parse("x / 0"));
+        assert_eq!(
+            expand(&normalize_division(&parse("x / 2 + x / 2"))),
+            parse("x")
+        );
+        assert_eq!(
+            expand(&normalize_division(&parse("x / 2 + x / 3"))),
+            scaled(5, 6, "x")
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::hash::BuildHasher;
+
+use indexmap::IndexSet;
+
+use crate::{
+    expression::expression_tree::{Atom, Escape, Expression},
+    modifier::ModifierImmutable,
+};
+
+// Id: a handle to a node of an ExprArena, only meaningful for the arena which returned it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id(usize);
+
+// a node with its children replaced by placeholders, along with the ids of those children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Node {
+    shape: Expression,
+    children: Vec<Id>,
+}
+
+// ExprArena: interned expressions, where every distinct subexpression is stored exactly once
+// an expression is interned with intern, or built from the bottom up with insert:
+//     let x = arena.intern(&Expression::from_str("x")?);
+//     let double = arena.insert(&Expression::from_str("_*1 + _*2")?, &[x, x]);
+// inserting an equal node again returns the same id, so a tree where every node repeats the one
+// below it takes one node per level no matter how large it would be as an Expression
+pub struct ExprArena<S>
+where
+    S: Default + BuildHasher,
+{
+    nodes: IndexSet<Node, S>,
+}
+
+impl<S> ExprArena<S>
+where
+    S: Default + BuildHasher,
+{
+    pub fn new() -> Self {
+        Self {
+            nodes: IndexSet::with_hasher(S::default()),
+        }
+    }
+
+    // the number of distinct nodes stored
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // stores expr and all of its subexpressions, returning the id of its root
+    pub fn intern(&mut self, expr: &Expression) -> Id {
+        let children = expr
+            .children()
+            .into_iter()
+            .map(|child| self.intern(child))
+            .collect::<Vec<_>>();
+        self.insert(expr, &children)
+    }
+
+    // stores a node shaped like shape, with its direct subexpressions replaced by the given
+    // children in the order of Expression::children, and returns its id
+    // panics if shape does not have exactly as many direct subexpressions as there are children
+    pub fn insert(&mut self, shape: &Expression, children: &[Id]) -> Id {
+        assert_eq!(
+            shape.children().len(),
+            children.len(),
+            "a node needs one child for each of its subexpressions"
+        );
+
+        let node = Node {
+            shape: shape.map_children(|_| Expression::Atom(Atom::Escape(Escape::Everything, 0))),
+            children: children.to_vec(),
+        };
+        Id(self.nodes.insert_full(node).0)
+    }
+
+    // the node behind id, with placeholders in place of its direct subexpressions
+    pub fn shape(&self, id: Id) -> &Expression {
+        &self.node(id).shape
+    }
+
+    // the ids of the direct subexpressions of the node behind id
+    pub fn children(&self, id: Id) -> &[Id] {
+        &self.node(id).children
+    }
+
+    // rebuilds the owned expression tree rooted at id, shared nodes are copied for every use
+    pub fn to_expression(&self, id: Id) -> Expression {
+        let node = self.node(id);
+        let mut children = node.children.iter();
+        node.shape
+            .map_children(|_| self.to_expression(*children.next().unwrap()))
+    }
+
+    // applies f to every node under root from the bottom up, each node is rebuilt from the
+    // results for its children before f is given its id, and f returns the id of its replacement
+    // results are memoized by id, so every distinct node is visited once however often it is shared
+    pub fn transform(&mut self, root: Id, mut f: impl FnMut(&mut Self, Id) -> Id) -> Id {
+        self.transform_memoized(root, &mut f, &mut BTreeMap::new())
+    }
+
+    // simplifies every node under root with the modifier, at most L times each, from the bottom up
+    // every node is rebuilt from its simplified children before it is simplified itself, so as long
+    // as the simplified nodes stay small a tree sharing its subexpressions takes time in its number
+    // of distinct nodes rather than in its size as an Expression
+    pub fn simplify<M: ModifierImmutable, const L: usize>(&mut self, root: Id, modifier: &M) -> Id {
+        self.transform(root, |arena, id| {
+            let mut expr = arena.to_expression(id);
+            expr.simplify_im::<M, L>(modifier);
+            arena.intern(&expr)
+        })
+    }
+
+    fn transform_memoized(
+        &mut self,
+        id: Id,
+        f: &mut impl FnMut(&mut Self, Id) -> Id,
+        memo: &mut BTreeMap<Id, Id>,
+    ) -> Id {
+        if let Some(done) = memo.get(&id) {
+            return *done;
+        }
+
+        let node = self.node(id).clone();
+        let children = node
+            .children
+            .iter()
+            .map(|child| self.transform_memoized(*child, f, memo))
+            .collect::<Vec<_>>();
+        let rebuilt = self.insert(&node.shape, &children);
+
+        let done = f(self, rebuilt);
+        memo.insert(id, done);
+        done
+    }
+
+    fn node(&self, id: Id) -> &Node {
+        self.nodes
+            .get_index(id.0)
+            .expect("an id is only used with the arena which returned it")
+    }
+}
+
+impl<S> Default for ExprArena<S>
+where
+    S: Default + BuildHasher,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::boxed::Box;
+    use core::str::FromStr;
+    use std::collections::hash_map::RandomState;
+
+    use super::ExprArena;
+    use crate::{expression::expression_tree::Expression, modifier::default::simplifier};
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_intern_roundtrip() {
+        let mut arena = ExprArena::<RandomState>::new();
+
+        for s in [
+            "x",
+            "(a + b) * (a + b) - f(a + b, 2)",
+            "[1, 2; 3, x] * <a, b>",
+        ] {
+            let id = arena.intern(&parse(s));
+            assert_eq!(arena.to_expression(id), parse(s));
+        }
+    }
+
+    #[test]
+    fn test_intern_shares_subexpressions() {
+        let mut arena = ExprArena::<RandomState>::new();
+
+        let sum = arena.intern(&parse("a + b"));
+        let product = arena.intern(&parse("(a + b) * (a + b)"));
+
+        // a, b, a + b and the product
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.children(product), [sum, sum]);
+        assert_eq!(arena.shape(product), &parse("_*0 * _*0"));
+    }
+
+    #[test]
+    fn test_transform_shared() {
+        let mut arena = ExprArena::<RandomState>::new();
+        let add = parse("_*1 + _*2");
+        let mul = parse("_*1 * _*2");
+
+        // x + x, (x + x) + (x + x), ... 2^64 leaves as an owned tree
+        let mut root = arena.intern(&parse("x"));
+        for _ in 0..64 {
+            root = arena.insert(&add, &[root, root]);
+        }
+        assert_eq!(arena.len(), 65);
+
+        let two = arena.intern(&parse("2"));
+        let doubled = arena.transform(root, |arena, id| match arena.children(id) {
+            &[l, r] if l == r && matches!(arena.shape(id), Expression::Add(_, _)) => {
+                arena.insert(&mul, &[two, l])
+            }
+            _ => id,
+        });
+
+        let mut expected = parse("x");
+        for _ in 0..64 {
+            expected = Expression::Multiply(Box::new(parse("2")), Box::new(expected));
+        }
+        assert_eq!(arena.to_expression(doubled), expected);
+    }
+
+    #[test]
+    fn test_simplify_shared() {
+        let mut arena = ExprArena::<RandomState>::new();
+        let add = parse("_*1 + _*2");
+
+        // 2^64 leaves as an owned tree, each level simplifies to a multiple of x
+        let mut root = arena.intern(&parse("x"));
+        for _ in 0..64 {
+            root = arena.insert(&add, &[root, root]);
+        }
+
+        let simplified = arena.simplify::<_, 100>(root, &simplifier());
+        assert_eq!(
+            arena.to_expression(simplified),
+            parse("18446744073709551616.0 * x")
+        );
+    }
+}
@@ -0,0 +1,802 @@
+use alloc::{boxed::Box, collections::BTreeMap, string::ToString, vec, vec::Vec};
+
+use crate::{
+    expression::{
+        eval::{try_eval, EvalContext, Value},
+        expression_tree::{Atom, Expression, Numeric},
+    },
+    DiffError, EvalError, IntegrateError, LimitError, SolveError,
+};
+
+// the antiderivative of expr with respect to var, without a constant of integration
+// handles linearity over sums and constant multiples, the power rule, 1/x and the basic
+// trig and exponential forms of var itself, anything else is unsupported
+pub fn integrate(expr: &Expression, var: char) -> Result<Expression, IntegrateError> {
+    let x = Expression::Atom(Atom::Variable(var));
+
+    if !expr.contains_variable(var) {
+        return Ok(Expression::Multiply(Box::new(expr.clone()), Box::new(x)));
+    }
+
+    match expr {
+        // an atom containing var is var itself
+        Expression::Atom(_) => Ok(power_rule(&x, &numeric(Numeric::Integer(1)))),
+
+        Expression::Negate(e) => Ok(Expression::Negate(Box::new(integrate(e, var)?))),
+
+        Expression::Add(l, r) => Ok(Expression::Add(
+            Box::new(integrate(l, var)?),
+            Box::new(integrate(r, var)?),
+        )),
+        Expression::Subtract(l, r) => Ok(Expression::Subtract(
+            Box::new(integrate(l, var)?),
+            Box::new(integrate(r, var)?),
+        )),
+
+        Expression::Multiply(c, e) | Expression::Multiply(e, c) if !c.contains_variable(var) => Ok(
+            Expression::Multiply(c.clone(), Box::new(integrate(e, var)?)),
+        ),
+        Expression::Divide(e, c) if !c.contains_variable(var) => {
+            Ok(Expression::Divide(Box::new(integrate(e, var)?), c.clone()))
+        }
+        Expression::Divide(c, d) if !c.contains_variable(var) && **d == x => {
+            if numeric_value(c) == Some(Numeric::Integer(1)) {
+                Ok(ln(x))
+            } else {
+                Ok(Expression::Multiply(c.clone(), Box::new(ln(x))))
+            }
+        }
+
+        Expression::Power(b, n) if **b == x && !n.contains_variable(var) => {
+            if numeric_value(n) == Some(Numeric::Integer(-1)) {
+                Ok(ln(x))
+            } else {
+                Ok(power_rule(&x, n))
+            }
+        }
+        Expression::Power(b, e) if **b == Expression::Atom(Atom::Variable('e')) && **e == x => {
+            Ok(expr.clone())
+        }
+
+        Expression::Function { name, args } if args.len() == 1 && *args[0] == x => {
+            match name.as_str() {
+                "sin" => Ok(Expression::Negate(Box::new(function("cos", x)))),
+                "cos" => Ok(function("sin", x)),
+                "exp" => Ok(function("exp", x)),
+                "sinh" => Ok(function("cosh", x)),
+                "cosh" => Ok(function("sinh", x)),
+                _ => Err(IntegrateError::Unsupported),
+            }
+        }
+
+        _ => Err(IntegrateError::Unsupported),
+    }
+}
+
+// the derivative of expr with respect to var, every other variable is held constant
+// handles sums, products, quotients, powers and the elementary functions of one argument through
+// the chain rule, vectors and matrices are differentiated entry by entry
+// anything without a rule (unknown functions, factorials) is left as diff(expr, var), while modulus
+// and percentages of var have no derivative at all
+pub fn differentiate(expr: &Expression, var: char) -> Result<Expression, DiffError> {
+    let x = Expression::Atom(Atom::Variable(var));
+
+    if !expr.contains_variable(var) {
+        return Ok(numeric(Numeric::Integer(0)));
+    }
+
+    Ok(match expr {
+        // an atom containing var is var itself
+        Expression::Atom(_) => numeric(Numeric::Integer(1)),
+
+        Expression::Negate(e) => Expression::Negate(Box::new(differentiate(e, var)?)),
+
+        Expression::Add(l, r) => Expression::Add(
+            Box::new(differentiate(l, var)?),
+            Box::new(differentiate(r, var)?),
+        ),
+        Expression::Subtract(l, r) => Expression::Subtract(
+            Box::new(differentiate(l, var)?),
+            Box::new(differentiate(r, var)?),
+        ),
+
+        Expression::Multiply(c, e) if !c.contains_variable(var) => {
+            Expression::Multiply(c.clone(), Box::new(differentiate(e, var)?))
+        }
+        Expression::Multiply(e, c) if !c.contains_variable(var) => {
+            Expression::Multiply(Box::new(differentiate(e, var)?), c.clone())
+        }
+        Expression::Multiply(l, r) => Expression::Add(
+            Box::new(Expression::Multiply(
+                Box::new(differentiate(l, var)?),
+                r.clone(),
+            )),
+            Box::new(Expression::Multiply(
+                l.clone(),
+                Box::new(differentiate(r, var)?),
+            )),
+        ),
+
+        Expression::Divide(e, c) if !c.contains_variable(var) => {
+            Expression::Divide(Box::new(differentiate(e, var)?), c.clone())
+        }
+        // (f/g)' = (f'g - fg')/g^2
+        Expression::Divide(l, r) => Expression::Divide(
+            Box::new(Expression::Subtract(
+                Box::new(Expression::Multiply(
+                    Box::new(differentiate(l, var)?),
+                    r.clone(),
+                )),
+                Box::new(Expression::Multiply(
+                    l.clone(),
+                    Box::new(differentiate(r, var)?),
+                )),
+            )),
+            Box::new(Expression::Power(
+                r.clone(),
+                Box::new(numeric(Numeric::Integer(2))),
+            )),
+        ),
+
+        // (f^n)' = n f^(n-1) f'
+        Expression::Power(b, n) if !n.contains_variable(var) => {
+            let lowered = match numeric_value(n) {
+                Some(n) => match n - Numeric::Integer(1) {
+                    Numeric::Integer(1) => (**b).clone(),
+                    m => Expression::Power(b.clone(), Box::new(numeric(m))),
+                },
+                None => Expression::Power(
+                    b.clone(),
+                    Box::new(Expression::Subtract(
+                        n.clone(),
+                        Box::new(numeric(Numeric::Integer(1))),
+                    )),
+                ),
+            };
+            chain(
+                Expression::Multiply(n.clone(), Box::new(lowered)),
+                differentiate(b, var)?,
+            )
+        }
+        // (a^g)' = a^g ln(a) g'
+        Expression::Power(a, g) if !a.contains_variable(var) => {
+            let ln_a = match **a {
+                Expression::Atom(Atom::Variable('e')) => None,
+                _ => Some(ln((**a).clone())),
+            };
+            let outer = match ln_a {
+                Some(ln_a) => Expression::Multiply(Box::new(expr.clone()), Box::new(ln_a)),
+                None => expr.clone(),
+            };
+            chain(outer, differentiate(g, var)?)
+        }
+        // (f^g)' = f^g (g' ln(f) + g f'/f)
+        Expression::Power(f, g) => Expression::Multiply(
+            Box::new(expr.clone()),
+            Box::new(Expression::Add(
+                Box::new(Expression::Multiply(
+                    Box::new(differentiate(g, var)?),
+                    Box::new(ln((**f).clone())),
+                )),
+                Box::new(Expression::Divide(
+                    Box::new(Expression::Multiply(
+                        g.clone(),
+                        Box::new(differentiate(f, var)?),
+                    )),
+                    f.clone(),
+                )),
+            )),
+        ),
+
+        // f'(u)' = f''(u) u', for known functions f
+        Expression::Function { name, args } if args.len() == 1 && name.ends_with('\'') => {
+            match prime(&(name.clone() + "'"), &args[0]) {
+                Some(outer) => chain(outer, differentiate(&args[0], var)?),
+                None => unevaluated(expr, x),
+            }
+        }
+        Expression::Function { name, args } if args.len() == 1 => {
+            let u = (*args[0]).clone();
+            let outer = match name.as_str() {
+                "sin" => function("cos", u),
+                "cos" => Expression::Negate(Box::new(function("sin", u))),
+                "tan" => Expression::Divide(
+                    Box::new(numeric(Numeric::Integer(1))),
+                    Box::new(Expression::Power(
+                        Box::new(function("cos", u)),
+                        Box::new(numeric(Numeric::Integer(2))),
+                    )),
+                ),
+                "sinh" => function("cosh", u),
+                "cosh" => function("sinh", u),
+                "exp" => function("exp", u),
+                "ln" | "log" => {
+                    Expression::Divide(Box::new(numeric(Numeric::Integer(1))), Box::new(u))
+                }
+                "sqrt" => Expression::Divide(
+                    Box::new(numeric(Numeric::Integer(1))),
+                    Box::new(Expression::Multiply(
+                        Box::new(numeric(Numeric::Integer(2))),
+                        Box::new(function("sqrt", u)),
+                    )),
+                ),
+                _ => return Ok(unevaluated(expr, x)),
+            };
+            chain(outer, differentiate(&args[0], var)?)
+        }
+
+        Expression::Vector { backing, size } => Expression::Vector {
+            size: *size,
+            backing: entries(backing, var)?,
+        },
+        Expression::Matrix { backing, shape } => Expression::Matrix {
+            shape: *shape,
+            backing: entries(backing, var)?,
+        },
+
+        Expression::Modulus(_, _) | Expression::Percent(_) => {
+            return Err(DiffError::NotDifferentiable)
+        }
+
+        _ => unevaluated(expr, x),
+    })
+}
+
+// the vector of partial derivatives of expr with respect to each of vars, in order
+pub fn gradient(expr: &Expression, vars: &[char]) -> Result<Expression, DiffError> {
+    Ok(Expression::Vector {
+        size: vars.len() as u8,
+        backing: vars
+            .iter()
+            .map(|var| differentiate(expr, *var).map(Box::new))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+// a root of expr as a function of var found by newton's method from the initial guess, accepted
+// once expr is within tol of zero or a step moves less than tol
+pub fn find_root(
+    expr: &Expression,
+    var: char,
+    guess: f32,
+    tol: f32,
+    max_iter: usize,
+) -> Result<f32, SolveError> {
+    let derivative = differentiate(expr, var).map_err(|_| SolveError::NotDifferentiable)?;
+    let at = |e: &Expression, x: f32| {
+        let mut ctx = EvalContext::default();
+        ctx.variables.insert(var, Numeric::Decimal(x));
+        try_eval(e, &ctx)
+            .and_then(Value::scalar)
+            .map(f32::from)
+            .map_err(|_| SolveError::NotNumeric)
+    };
+
+    let mut x = guess;
+    for _ in 0..max_iter {
+        let fx = at(expr, x)?;
+        if libm::fabsf(fx) <= tol {
+            return Ok(x);
+        }
+
+        let dfx = at(&derivative, x)?;
+        if dfx == 0.0 {
+            return Err(SolveError::ZeroDerivative);
+        }
+
+        let next = x - fx / dfx;
+        if !next.is_finite() {
+            return Err(SolveError::NoConvergence);
+        }
+        if libm::fabsf(next - x) <= tol {
+            return Ok(next);
+        }
+        x = next;
+    }
+
+    Err(SolveError::NoConvergence)
+}
+
+// the definite integral of integrand over var from a to b by the composite simpson's rule, with
+// steps rounded up to an even number of intervals, the integrand has to evaluate to a number at
+// every sample point
+pub fn integrate_numeric(
+    integrand: &Expression,
+    var: char,
+    a: f32,
+    b: f32,
+    steps: usize,
+) -> Result<f32, EvalError> {
+    let steps = (steps.max(2) + 1) & !1;
+    let h = (b - a) / steps as f32;
+    let at = |x: f32| {
+        let mut ctx = EvalContext::default();
+        ctx.variables.insert(var, Numeric::Decimal(x));
+        try_eval(integrand, &ctx)
+            .and_then(Value::scalar)
+            .map(f32::from)
+    };
+
+    let mut sum = at(a)? + at(b)?;
+    for i in 1..steps {
+        let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * at(a + i as f32 * h)?;
+    }
+    Ok(sum * h / 3.0)
+}
+
+// the taylor polynomial of expr in var about the given point, up to and including the term of the
+// given order, as a sum of c * (var - about)^k / k! terms with terms of zero coefficient dropped
+// the coefficients are the derivatives evaluated at the point, or substituted symbolically when
+// they do not evaluate to a number
+pub fn taylor(
+    expr: &Expression,
+    var: char,
+    about: Numeric,
+    order: usize,
+) -> Result<Expression, DiffError> {
+    let x = Expression::Atom(Atom::Variable(var));
+    let shifted = if about == Numeric::Integer(0) {
+        x
+    } else {
+        Expression::Subtract(Box::new(x), Box::new(numeric(about)))
+    };
+
+    let mut ctx = EvalContext::default();
+    ctx.variables.insert(var, about);
+    let point = BTreeMap::from([(var, numeric(about))]);
+
+    let mut derivative = expr.clone();
+    let mut factorial = Numeric::Integer(1);
+    let mut terms = Vec::new();
+    for k in 0..=order {
+        if k > 0 {
+            derivative = differentiate(&derivative, var)?;
+            factorial = factorial * Numeric::Integer(k as i32);
+        }
+
+        let coefficient = match try_eval(&derivative, &ctx).and_then(Value::scalar) {
+            Ok(c) if f32::from(c) == 0.0 => continue,
+            Ok(c) => numeric(c),
+            Err(_) => derivative.substitute_many(&point),
+        };
+        let term = match k {
+            0 => coefficient,
+            1 => Expression::Multiply(Box::new(coefficient), Box::new(shifted.clone())),
+            _ => Expression::Multiply(
+                Box::new(coefficient),
+                Box::new(Expression::Power(
+                    Box::new(shifted.clone()),
+                    Box::new(numeric(Numeric::Integer(k as i32))),
+                )),
+            ),
+        };
+        terms.push(if k > 1 {
+            Expression::Divide(Box::new(term), Box::new(numeric(factorial)))
+        } else {
+            term
+        });
+    }
+
+    Ok(terms
+        .into_iter()
+        .reduce(|acc, t| Expression::Add(Box::new(acc), Box::new(t)))
+        .unwrap_or(numeric(Numeric::Integer(0))))
+}
+
+// the limit of expr as var approaches to, found by substituting the point when expr is defined there,
+// or by one application of l'hopital's rule to a quotient of the form 0/0, sin(x)/x -> 1 at 0
+// anything else, including quotients which are still 0/0 after one application, is unsupported
+pub fn limit(expr: &Expression, var: char, to: Numeric) -> Result<Expression, LimitError> {
+    let mut ctx = EvalContext::default();
+    ctx.variables.insert(var, to);
+    let at = |e: &Expression| match try_eval(e, &ctx).and_then(Value::scalar) {
+        Ok(n) if n.parts().0.is_finite() && n.parts().1.is_finite() => Some(n),
+        _ => None,
+    };
+    let vanishes = |n: Numeric| {
+        let (re, im) = n.parts();
+        libm::fabsf(re) < 1e-6 && libm::fabsf(im) < 1e-6
+    };
+
+    if let Some(n) = at(expr) {
+        return Ok(numeric(n));
+    }
+
+    let (num, den) = match expr {
+        Expression::Divide(num, den) => (num, den),
+        _ => return Err(LimitError::Unsupported),
+    };
+    match (at(num), at(den)) {
+        (Some(n), Some(d)) if vanishes(n) && vanishes(d) => (),
+        _ => return Err(LimitError::Unsupported),
+    }
+
+    let derivative = |e: &Expression| differentiate(e, var).map_err(|_| LimitError::Unsupported);
+    match (at(&derivative(num)?), at(&derivative(den)?)) {
+        (Some(n), Some(d)) if !vanishes(d) => Ok(numeric(n / d)),
+        _ => Err(LimitError::Unsupported),
+    }
+}
+
+// the closed form of a derivative written with primes, sin''(u) is -sin(u), for the known functions
+// of one argument, None for functions without a rule
+pub(crate) fn prime(name: &str, u: &Expression) -> Option<Expression> {
+    let base = name.trim_end_matches('\'');
+    let t = Expression::Atom(Atom::Variable('t'));
+
+    let mut derivative = function(base, t);
+    for _ in base.len()..name.len() {
+        derivative = differentiate(&derivative, 't').ok()?;
+        if matches!(&derivative, Expression::Function { name, args: _ } if name == "diff") {
+            return None;
+        }
+    }
+    Some(derivative.substitute_many(&BTreeMap::from([('t', u.clone())])))
+}
+
+// the derivative of an outer function multiplied by the derivative of its argument, leaving out the
+// multiplication when the argument is var itself
+fn chain(outer: Expression, inner: Expression) -> Expression {
+    if numeric_value(&inner) == Some(Numeric::Integer(1)) {
+        outer
+    } else {
+        Expression::Multiply(Box::new(outer), Box::new(inner))
+    }
+}
+
+fn unevaluated(expr: &Expression, x: Expression) -> Expression {
+    Expression::Function {
+        name: "diff".to_string(),
+        args: vec![Box::new(expr.clone()), Box::new(x)],
+    }
+}
+
+// the derivatives of the entries of a vector or matrix, in order
+fn entries<C: FromIterator<Box<Expression>>>(
+    backing: &[Box<Expression>],
+    var: char,
+) -> Result<C, DiffError> {
+    backing
+        .iter()
+        .map(|e| differentiate(e, var).map(Box::new))
+        .collect()
+}
+
+// x^n -> x^(n+1)/(n+1), adding numerically when n is a number
+fn power_rule(x: &Expression, n: &Expression) -> Expression {
+    let n = match numeric_value(n) {
+        Some(n) => numeric(n + Numeric::Integer(1)),
+        None => Expression::Add(Box::new(n.clone()), Box::new(numeric(Numeric::Integer(1)))),
+    };
+    Expression::Divide(
+        Box::new(Expression::Power(Box::new(x.clone()), Box::new(n.clone()))),
+        Box::new(n),
+    )
+}
+
+// the value of a number or a negated number
+fn numeric_value(expr: &Expression) -> Option<Numeric> {
+    match expr {
+        Expression::Atom(Atom::Numeric(n)) => Some(*n),
+        Expression::Negate(e) => numeric_value(e).map(|n| -n),
+        _ => None,
+    }
+}
+
+fn numeric(n: Numeric) -> Expression {
+    Expression::Atom(Atom::Numeric(n))
+}
+
+fn ln(x: Expression) -> Expression {
+    function("ln", x)
+}
+
+fn function(name: &str, arg: Expression) -> Expression {
+    Expression::Function {
+        name: name.to_string(),
+        args: vec![Box::new(arg)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use alloc::boxed::Box;
+
+    use super::{differentiate, find_root, gradient, integrate, integrate_numeric, limit, taylor};
+    use crate::{
+        expression::expression_tree::{Atom, Expression, Numeric},
+        modifier::{adaptable_modifier::AdaptableModifier, default::simplifier},
+        DiffError, EvalError, IntegrateError, LimitError, SolveError,
+    };
+
+    fn integral(s: &str) -> Result<Expression, IntegrateError> {
+        integrate(&Expression::from_str(s).unwrap(), 'x')
+    }
+
+    fn derivative(s: &str) -> Expression {
+        let mut d = differentiate(&Expression::from_str(s).unwrap(), 'x').unwrap();
+        d.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        d
+    }
+
+    #[test]
+    fn test_differentiate() {
+        assert_eq!(derivative("x^3"), Expression::from_str("3 * x^2").unwrap());
+        assert_eq!(derivative("5 * x + y"), Expression::from_str("5").unwrap());
+        assert_eq!(
+            derivative("x * sin(x)"),
+            Expression::from_str("sin(x) + x * cos(x)").unwrap()
+        );
+        assert_eq!(
+            derivative("exp(2 * x)"),
+            Expression::from_str("2 * exp(2 * x)").unwrap()
+        );
+        assert_eq!(derivative("ln(x)"), Expression::from_str("1 / x").unwrap());
+    }
+
+    #[test]
+    fn test_differentiate_unevaluated() {
+        assert_eq!(
+            differentiate(&Expression::from_str("f(x)").unwrap(), 'x'),
+            Ok(Expression::from_str("diff(f(x), x)").unwrap())
+        );
+        assert_eq!(
+            differentiate(&Expression::from_str("f(y)").unwrap(), 'x'),
+            Ok(Expression::from_str("0").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_differentiate_primes() {
+        assert_eq!(
+            derivative("sin'(x)"),
+            Expression::from_str("-sin(x)").unwrap()
+        );
+        assert_eq!(
+            derivative("exp''(2 * x)"),
+            Expression::from_str("2 * exp(2 * x)").unwrap()
+        );
+        assert_eq!(
+            derivative("f'(x)"),
+            Expression::from_str("diff(f'(x), x)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_differentiate_not_differentiable() {
+        for s in ["x % 2", "sin(2 % x)", "x%", "<1, x % 3>", "(x % 2) ^ 2"] {
+            assert_eq!(
+                differentiate(&Expression::from_str(s).unwrap(), 'x'),
+                Err(DiffError::NotDifferentiable),
+                "{}",
+                s
+            );
+        }
+        assert_eq!(
+            differentiate(&Expression::from_str("y % 2 + x").unwrap(), 'x'),
+            Ok(Expression::from_str("0 + 1").unwrap())
+        );
+        assert_eq!(
+            gradient(&Expression::from_str("x % y").unwrap(), &['x', 'y']),
+            Err(DiffError::NotDifferentiable)
+        );
+        assert_eq!(
+            find_root(&Expression::from_str("x % 2").unwrap(), 'x', 1.0, 1e-6, 50),
+            Err(SolveError::NotDifferentiable)
+        );
+    }
+
+    #[test]
+    fn test_gradient() {
+        let mut grad = gradient(&Expression::from_str("x^2 + y^2").unwrap(), &['x', 'y']).unwrap();
+        grad.simplify_im::<AdaptableModifier, 100>(&simplifier());
+
+        assert_eq!(grad, Expression::from_str("<2 * x, 2 * y>").unwrap());
+
+        let mut grad = gradient(&Expression::from_str("x * y * z").unwrap(), &['z', 'x']).unwrap();
+        grad.simplify_im::<AdaptableModifier, 100>(&simplifier());
+
+        assert_eq!(grad, Expression::from_str("<x * y, y * z>").unwrap());
+    }
+
+    fn taylor_simplified(s: &str, about: Numeric, order: usize) -> Expression {
+        let mut t = taylor(&Expression::from_str(s).unwrap(), 'x', about, order).unwrap();
+        t.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        t
+    }
+
+    #[test]
+    fn test_taylor() {
+        assert_eq!(
+            taylor_simplified("exp(x)", Numeric::Integer(0), 3),
+            Expression::from_str("1 + x + x^2/2 + x^3/6").unwrap()
+        );
+        assert_eq!(
+            taylor_simplified("sin(x)", Numeric::Integer(0), 4),
+            Expression::from_str("x - x^3/6").unwrap()
+        );
+        assert_eq!(
+            taylor_simplified("x^2", Numeric::Integer(1), 5),
+            Expression::from_str("1 + 2 * (x - 1) + (x - 1)^2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_taylor_symbolic() {
+        assert_eq!(
+            taylor_simplified("a * x^2", Numeric::Integer(0), 2),
+            Expression::from_str("a * x^2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_root() {
+        let expr = Expression::from_str("x^2 - 2").unwrap();
+
+        let root = find_root(&expr, 'x', 1.0, 1e-6, 50).unwrap();
+        assert!((root - core::f32::consts::SQRT_2).abs() < 1e-5);
+
+        let root = find_root(&expr, 'x', -3.0, 1e-6, 50).unwrap();
+        assert!((root + core::f32::consts::SQRT_2).abs() < 1e-5);
+
+        let root = find_root(
+            &Expression::from_str("cos(x) - x").unwrap(),
+            'x',
+            1.0,
+            1e-6,
+            50,
+        );
+        assert!((root.unwrap() - 0.7390851).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_limit() {
+        let lim = |s: &str, to| limit(&Expression::from_str(s).unwrap(), 'x', Numeric::Integer(to));
+        let value = |s: &str| Ok(Expression::from_str(s).unwrap());
+
+        // direct substitution
+        assert_eq!(lim("x^2 + 1", 2), value("5"));
+        assert_eq!(
+            lim("x / (x + 1)", 1),
+            Ok(Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2))))
+        );
+
+        // 0/0, after one application of l'hopital's rule
+        assert_eq!(lim("sin(x) / x", 0), value("1"));
+        assert_eq!(lim("(x^2 - 1) / (x - 1)", 1), value("2"));
+        assert_eq!(lim("(exp(x) - 1) / (2 * x)", 0), value("0.5"));
+    }
+
+    #[test]
+    fn test_limit_unsupported() {
+        let lim = |s: &str, to| limit(&Expression::from_str(s).unwrap(), 'x', Numeric::Integer(to));
+
+        assert_eq!(lim("1 / x", 0), Err(LimitError::Unsupported));
+        // still 0/0 after one application
+        assert_eq!(lim("(1 - cos(x)) / x^2", 0), Err(LimitError::Unsupported));
+        assert_eq!(lim("x * y", 1), Err(LimitError::Unsupported));
+        assert_eq!(lim("ln(x) * x", 0), Err(LimitError::Unsupported));
+    }
+
+    #[test]
+    fn test_integrate_numeric() {
+        let parse = |s: &str| Expression::from_str(s).unwrap();
+
+        let area = integrate_numeric(&parse("x^2"), 'x', 0.0, 1.0, 10).unwrap();
+        assert!((area - 1.0 / 3.0).abs() < 1e-5);
+
+        let area = integrate_numeric(&parse("sin(t)"), 't', 0.0, core::f32::consts::PI, 51);
+        assert!((area.unwrap() - 2.0).abs() < 1e-4);
+
+        let area = integrate_numeric(&parse("x^3"), 'x', 1.0, -1.0, 4).unwrap();
+        assert!(area.abs() < 1e-6);
+
+        assert_eq!(
+            integrate_numeric(&parse("x * y"), 'x', 0.0, 1.0, 10),
+            Err(EvalError::UndefinedVariable('y'))
+        );
+        assert_eq!(
+            integrate_numeric(&parse("1 / x"), 'x', 0.0, 1.0, 10),
+            Err(EvalError::DivByZero)
+        );
+    }
+
+    #[test]
+    fn test_find_root_errors() {
+        let expr = Expression::from_str("x^2 - 2").unwrap();
+
+        assert_eq!(
+            find_root(&expr, 'x', 0.0, 1e-6, 50),
+            Err(SolveError::ZeroDerivative)
+        );
+        assert_eq!(
+            find_root(&expr, 'x', 100.0, 1e-6, 2),
+            Err(SolveError::NoConvergence)
+        );
+        assert_eq!(
+            find_root(
+                &Expression::from_str("x^2 + 1").unwrap(),
+                'x',
+                0.5,
+                1e-6,
+                50
+            ),
+            Err(SolveError::NoConvergence)
+        );
+        assert_eq!(
+            find_root(&Expression::from_str("x - y").unwrap(), 'x', 1.0, 1e-6, 50),
+            Err(SolveError::NotNumeric)
+        );
+    }
+
+    #[test]
+    fn test_integrate_power() {
+        assert_eq!(integral("x^2"), Ok(Expression::from_str("x^3/3").unwrap()));
+        assert_eq!(integral("x"), Ok(Expression::from_str("x^2/2").unwrap()));
+        assert_eq!(
+            integral("x^(-2)"),
+            Ok(Expression::Divide(
+                Box::new(Expression::Power(
+                    Box::new(Expression::Atom(Atom::Variable('x'))),
+                    Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(-1)))),
+                )),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(-1)))),
+            ))
+        );
+        assert_eq!(
+            integral("x^n"),
+            Ok(Expression::from_str("x^(n+1)/(n+1)").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_integrate_reciprocal() {
+        assert_eq!(integral("1/x"), Ok(Expression::from_str("ln(x)").unwrap()));
+        assert_eq!(
+            integral("x^(-1)"),
+            Ok(Expression::from_str("ln(x)").unwrap())
+        );
+        assert_eq!(
+            integral("3/x"),
+            Ok(Expression::from_str("3*ln(x)").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_integrate_trig_exp() {
+        assert_eq!(
+            integral("sin(x)"),
+            Ok(Expression::from_str("-cos(x)").unwrap())
+        );
+        assert_eq!(
+            integral("cos(x)"),
+            Ok(Expression::from_str("sin(x)").unwrap())
+        );
+        assert_eq!(
+            integral("exp(x)"),
+            Ok(Expression::from_str("exp(x)").unwrap())
+        );
+        assert_eq!(integral("e^x"), Ok(Expression::from_str("e^x").unwrap()));
+    }
+
+    #[test]
+    fn test_integrate_linearity() {
+        assert_eq!(
+            integral("2*x + 3 - y"),
+            Ok(Expression::from_str("2*(x^2/2) + 3*x - y*x").unwrap())
+        );
+        assert_eq!(
+            integral("x^2/4"),
+            Ok(Expression::from_str("(x^3/3)/4").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_integrate_unsupported() {
+        assert_eq!(integral("x*sin(x)"), Err(IntegrateError::Unsupported));
+        assert_eq!(integral("sin(x^2)"), Err(IntegrateError::Unsupported));
+    }
+}
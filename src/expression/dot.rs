@@ -0,0 +1,181 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, iter};
+
+use crate::expression::expression_tree::Expression;
+
+// renders an expression as a graphviz dot graph, with one node per subexpression and edges to its
+// children labeled by their role (the operands, function arguments and vector or matrix entries)
+// equal subtrees are drawn separately, so the graph is always a tree
+pub fn to_dot(expr: &Expression) -> String {
+    let mut out = String::from("digraph expression {\n");
+    let mut next_id = 0;
+    to_dot_into(expr, &mut out, &mut next_id);
+    out.push_str("}\n");
+    out
+}
+
+// writes the node of an expression and everything under it, returning the node's id
+fn to_dot_into(expr: &Expression, out: &mut String, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    writeln!(out, "    n{} [label=\"{}\"];", id, escape(&label(expr))).unwrap();
+
+    for (edge, child) in edge_labels(expr).into_iter().zip(expr.children()) {
+        let child_id = to_dot_into(child, out, next_id);
+        writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, child_id, edge).unwrap();
+    }
+
+    id
+}
+
+fn label(expr: &Expression) -> String {
+    match expr {
+        Expression::Atom(a) => a.to_string(),
+        Expression::Negate(_) => "-".to_string(),
+        Expression::Factorial(_) => "!".to_string(),
+        Expression::Percent(_) => "%".to_string(),
+        Expression::Add(_, _) => "+".to_string(),
+        Expression::Subtract(_, _) => "-".to_string(),
+        Expression::Multiply(_, _) => "*".to_string(),
+        Expression::Divide(_, _) => "/".to_string(),
+        Expression::Power(_, _) => "^".to_string(),
+        Expression::Modulus(_, _) => "mod".to_string(),
+        Expression::Function { name, args: _ } => name.clone(),
+        Expression::Vector {
+            backing: _,
+            size: _,
+        } => "vector".to_string(),
+        Expression::Matrix {
+            backing: _,
+            shape: (rs, cs),
+        } => format!("matrix {}x{}", rs, cs),
+        Expression::Integral {
+            integrand: _,
+            var,
+            lower: _,
+            upper: _,
+        } => format!("integral d{}", var),
+        Expression::Sum {
+            index,
+            lower: _,
+            upper: _,
+            body: _,
+        } => format!("sum over {}", index),
+        Expression::Relation(op, _, _) => op.to_string(),
+        Expression::And(_) => "and".to_string(),
+        Expression::Or(_) => "or".to_string(),
+        Expression::Not(_) => "not".to_string(),
+        Expression::Piecewise(_) => "piecewise".to_string(),
+    }
+}
+
+// the labels of the edges to the children of an expression, in the order of Expression::children
+fn edge_labels(expr: &Expression) -> Vec<String> {
+    match expr {
+        Expression::Atom(_) => Vec::new(),
+        Expression::Negate(_)
+        | Expression::Factorial(_)
+        | Expression::Percent(_)
+        | Expression::Not(_) => Vec::from(["operand".to_string()]),
+        Expression::Add(_, _)
+        | Expression::Subtract(_, _)
+        | Expression::Multiply(_, _)
+        | Expression::Divide(_, _)
+        | Expression::Power(_, _)
+        | Expression::Modulus(_, _)
+        | Expression::Relation(_, _, _) => Vec::from(["left".to_string(), "right".to_string()]),
+        Expression::Function { name: _, args } => {
+            (0..args.len()).map(|i| format!("arg {}", i)).collect()
+        }
+        Expression::And(es) | Expression::Or(es) => {
+            (0..es.len()).map(|i| format!("{}", i)).collect()
+        }
+        Expression::Piecewise(branches) => (0..branches.len())
+            .flat_map(|i| [format!("value {}", i), format!("condition {}", i)])
+            .collect(),
+        Expression::Vector {
+            backing: vec,
+            size: _,
+        } => (0..vec.len()).map(|i| format!("{}", i)).collect(),
+        Expression::Matrix {
+            backing: _,
+            shape: (rs, cs),
+        } => (0..*rs)
+            .flat_map(|r| (0..*cs).map(move |c| format!("{},{}", r, c)))
+            .collect(),
+        Expression::Integral {
+            integrand: _,
+            var: _,
+            lower,
+            upper,
+        } => iter::once("integrand")
+            .chain(lower.as_ref().map(|_| "lower"))
+            .chain(upper.as_ref().map(|_| "upper"))
+            .map(String::from)
+            .collect(),
+        Expression::Sum {
+            index: _,
+            lower: _,
+            upper: _,
+            body: _,
+        } => Vec::from(["lower".to_string(), "upper".to_string(), "body".to_string()]),
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::to_dot;
+    use crate::expression::expression_tree::Expression;
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = to_dot(&parse("2 + 3 * x"));
+
+        assert!(dot.starts_with("digraph expression {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    n0 [label=\"+\"];\n"));
+        assert!(dot.contains("    n0 -> n1 [label=\"left\"];\n"));
+        assert!(dot.contains("    n2 -> n4 [label=\"right\"];\n"));
+    }
+
+    #[test]
+    fn test_to_dot_node_count() {
+        for s in [
+            "x",
+            "-(a + b) ^ 2",
+            "f(x, y, z!)",
+            "[1, 2; 3, sin(x)] * <a, b>",
+        ] {
+            let expr = parse(s);
+            let dot = to_dot(&expr);
+
+            let nodes = dot.lines().filter(|l| !l.contains("->")).count() - 2;
+            let edges = dot.lines().filter(|l| l.contains("->")).count();
+            assert_eq!(nodes, expr.node_count());
+            assert_eq!(edges, expr.node_count() - 1);
+        }
+    }
+
+    #[test]
+    fn test_to_dot_edge_labels() {
+        let dot = to_dot(&parse("f(x, [1, 2; 3, 4])"));
+
+        assert!(dot.contains("[label=\"arg 1\"]"));
+        assert!(dot.contains("[label=\"matrix 2x2\"]"));
+        assert!(dot.contains("[label=\"1,0\"]"));
+    }
+}
@@ -0,0 +1,1273 @@
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::Cell;
+
+use crate::{
+    expression::{
+        calculus::prime,
+        expression_tree::{Atom, Comparison, Expression, Numeric},
+    },
+    EvalError, ShapeError,
+};
+
+// EvalContext: the values of the variables an expression is evaluated with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalContext {
+    pub variables: BTreeMap<char, Numeric>,
+}
+
+// AngleMode: the unit the arguments of trigonometric functions are in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+// EvalOptions: how an expression is evaluated
+#[derive(Debug, Clone, Default)]
+pub struct EvalOptions {
+    pub angle_mode: AngleMode,
+    pub functions: FnRegistry,
+    // evaluates the factorial of real numbers which are not whole through the gamma function,
+    // x! = gamma(x + 1), instead of failing with a domain error, negative integers still fail
+    pub gamma: bool,
+    // the most nodes evaluation visits before failing with StepLimitExceeded, so that evaluating
+    // untrusted input has a hard ceiling, None visits as many as the expression has
+    pub max_steps: Option<usize>,
+}
+
+// FnRegistry: the functions an expression can call, by name and number of arguments
+// the built in functions are available by default, looked up in a static table so that a default
+// registry costs nothing to build
+#[derive(Debug, Clone)]
+pub struct FnRegistry {
+    functions: Vec<(String, usize, Function)>,
+    builtin: bool,
+}
+
+impl FnRegistry {
+    // a registry without any functions, not even the built in ones
+    pub fn empty() -> Self {
+        FnRegistry {
+            functions: Vec::new(),
+            builtin: false,
+        }
+    }
+
+    // registers a function taking the given number of arguments, replacing the one registered
+    // under the same name and number of arguments, a name may be registered once per arity
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: usize,
+        f: fn(&[Numeric]) -> Result<Numeric, EvalError>,
+    ) {
+        match self
+            .functions
+            .iter_mut()
+            .find(|(n, a, _)| n == name && *a == arity)
+        {
+            Some(entry) => entry.2 = f,
+            None => self.functions.push((name.to_string(), arity, f)),
+        }
+    }
+
+    // calls the function registered under name with the arity of args, a name registered only
+    // with other arities is an arity mismatch (reporting the first one registered)
+    // the built in functions count as registered before any other
+    pub fn call(&self, name: &str, args: &[Numeric]) -> Result<Numeric, EvalError> {
+        let named = |(n, _, _): &(&str, usize, Function)| *n == name;
+        let builtin = FUNCTIONS.iter().copied().filter(|_| self.builtin);
+        let registered = self.functions.iter().map(|(n, a, f)| (n.as_str(), *a, *f));
+
+        // a registered function replaces the built in one with the same name and arity
+        match registered
+            .clone()
+            .chain(builtin.clone())
+            .filter(named)
+            .find(|(_, arity, _)| *arity == args.len())
+        {
+            Some((_, _, f)) => f(args),
+            None => match builtin.chain(registered).find(named) {
+                Some((_, expected, _)) => Err(EvalError::ArityMismatch {
+                    expected,
+                    found: args.len(),
+                }),
+                None => Err(EvalError::UnknownFunction(name.to_string())),
+            },
+        }
+    }
+}
+
+impl Default for FnRegistry {
+    fn default() -> Self {
+        FnRegistry {
+            functions: Vec::new(),
+            builtin: true,
+        }
+    }
+}
+
+// Value: what an expression evaluates to, a number or a vector or matrix of numbers, or whether
+// a relation holds
+// matrix entries are stored row by row, like the backing of Expression::Matrix
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(Numeric),
+    Vector(Vec<Numeric>),
+    Matrix {
+        backing: Vec<Numeric>,
+        shape: (u8, u8),
+    },
+    Boolean(bool),
+}
+
+impl Value {
+    // the number this value is, vectors and matrices are not numbers
+    pub fn scalar(self) -> Result<Numeric, EvalError> {
+        match self {
+            Value::Scalar(n) => Ok(n),
+            _ => Err(EvalError::Unsupported),
+        }
+    }
+
+    fn map(self, f: impl Fn(Numeric) -> Result<Numeric, EvalError>) -> Result<Value, EvalError> {
+        Ok(match self {
+            Value::Scalar(n) => Value::Scalar(f(n)?),
+            Value::Vector(v) => Value::Vector(v.into_iter().map(f).collect::<Result<_, _>>()?),
+            Value::Matrix { backing, shape } => Value::Matrix {
+                backing: backing.into_iter().map(f).collect::<Result<_, _>>()?,
+                shape,
+            },
+            Value::Boolean(_) => return Err(EvalError::Unsupported),
+        })
+    }
+}
+
+// evaluates an expression which has no variables, with angles in radians
+pub fn evaluate(expr: &Expression) -> Result<Value, EvalError> {
+    try_eval(expr, &EvalContext::default())
+}
+
+// evaluates an expression which has no variables with the given options
+pub fn evaluate_with(expr: &Expression, options: &EvalOptions) -> Result<Value, EvalError> {
+    let steps = Cell::new(options.max_steps.unwrap_or(usize::MAX));
+    eval_value(expr, &EvalContext::default(), options, &steps)
+}
+
+// evaluates an expression directly to a value, looking its variables up in the context
+// integer arithmetic stays exact as long as Numeric can represent the result
+// vectors and matrices are added, subtracted, negated and scaled entry by entry, matrices multiply
+// vectors and other matrices, everything else (sin(<1, 2>), <1, 2> * <3, 4>) is unsupported
+pub fn try_eval(expr: &Expression, ctx: &EvalContext) -> Result<Value, EvalError> {
+    eval_value(expr, ctx, &EvalOptions::default(), &Cell::new(usize::MAX))
+}
+
+// the most terms a sum is evaluated or expanded over, larger ranges are refused
+pub(crate) const MAX_SUM_TERMS: i64 = 100_000;
+
+// takes one of the steps left, every node evaluated takes one
+fn step(steps: &Cell<usize>) -> Result<(), EvalError> {
+    match steps.get().checked_sub(1) {
+        Some(left) => {
+            steps.set(left);
+            Ok(())
+        }
+        None => Err(EvalError::StepLimitExceeded),
+    }
+}
+
+fn eval_value(
+    expr: &Expression,
+    ctx: &EvalContext,
+    options: &EvalOptions,
+    steps: &Cell<usize>,
+) -> Result<Value, EvalError> {
+    step(steps)?;
+    let eval = |e: &Expression| eval_value(e, ctx, options, steps);
+    let entries = |backing: &[Box<Expression>]| {
+        backing
+            .iter()
+            .map(|e| eval(e)?.scalar())
+            .collect::<Result<Vec<_>, _>>()
+    };
+    match expr {
+        Expression::Vector { backing, size: _ } => Ok(Value::Vector(entries(backing)?)),
+        Expression::Matrix { backing, shape } => Ok(Value::Matrix {
+            backing: entries(backing)?,
+            shape: *shape,
+        }),
+
+        Expression::Negate(e) => eval(e)?.map(|n| Ok(-n)),
+        Expression::Add(l, r) => entrywise(eval(l)?, eval(r)?, |a, b| Ok(a + b)),
+        Expression::Subtract(l, r) => entrywise(eval(l)?, eval(r)?, |a, b| Ok(a - b)),
+        Expression::Multiply(l, r) => multiply(eval(l)?, eval(r)?),
+        Expression::Divide(l, r) => {
+            let (l, r) = (eval(l)?, nonzero(eval(r)?.scalar()?)?);
+            l.map(|n| Ok(n / r))
+        }
+
+        // relations and the connectives between them evaluate to whether they hold
+        e @ (Expression::Atom(Atom::Boolean(_))
+        | Expression::Relation(_, _, _)
+        | Expression::And(_)
+        | Expression::Or(_)
+        | Expression::Not(_)) => holds(e, &mut |e| {
+            step(steps)?;
+            eval_recording(e, ctx, options, steps, &mut |_, _| {})
+        })
+        .map(Value::Boolean),
+
+        // the step for this node is already taken
+        e => eval_recording(e, ctx, options, steps, &mut |_, _| {}).map(Value::Scalar),
+    }
+}
+
+// combines two numbers, or two vectors or matrices of the same shape entry by entry
+fn entrywise(
+    l: Value,
+    r: Value,
+    f: fn(Numeric, Numeric) -> Result<Numeric, EvalError>,
+) -> Result<Value, EvalError> {
+    let zip = |a: Vec<Numeric>, b: Vec<Numeric>| {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| f(a, b))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    match (l, r) {
+        (Value::Scalar(a), Value::Scalar(b)) => f(a, b).map(Value::Scalar),
+        (Value::Vector(a), Value::Vector(b)) if a.len() == b.len() => Ok(Value::Vector(zip(a, b)?)),
+        (Value::Vector(a), Value::Vector(b)) => Err(EvalError::Shape(ShapeError::Mismatch {
+            expected: a.len(),
+            found: b.len(),
+        })),
+        (
+            Value::Matrix {
+                backing: a,
+                shape: (rs, cs),
+            },
+            Value::Matrix {
+                backing: b,
+                shape: (other_rs, other_cs),
+            },
+        ) => match (rs == other_rs, cs == other_cs) {
+            (true, true) => Ok(Value::Matrix {
+                backing: zip(a, b)?,
+                shape: (rs, cs),
+            }),
+            (false, _) => Err(EvalError::Shape(ShapeError::Mismatch {
+                expected: rs as usize,
+                found: other_rs as usize,
+            })),
+            (true, false) => Err(EvalError::Shape(ShapeError::Mismatch {
+                expected: cs as usize,
+                found: other_cs as usize,
+            })),
+        },
+        _ => Err(EvalError::Unsupported),
+    }
+}
+
+// scales vectors and matrices by numbers, multiplies matrices with vectors and other matrices
+fn multiply(l: Value, r: Value) -> Result<Value, EvalError> {
+    match (l, r) {
+        (Value::Scalar(a), r) => r.map(|b| Ok(a * b)),
+        (l, Value::Scalar(b)) => l.map(|a| Ok(a * b)),
+        (Value::Matrix { backing, shape }, Value::Vector(v)) => Ok(Value::Vector(matrix_product(
+            &backing,
+            shape,
+            &v,
+            (v.len() as u8, 1),
+        )?)),
+        (
+            Value::Matrix {
+                backing: a,
+                shape: left,
+            },
+            Value::Matrix {
+                backing: b,
+                shape: right,
+            },
+        ) => Ok(Value::Matrix {
+            backing: matrix_product(&a, left, &b, right)?,
+            shape: (left.0, right.1),
+        }),
+        _ => Err(EvalError::Unsupported),
+    }
+}
+
+// the row by row entries of the product of an r x n and an n x c matrix
+fn matrix_product(
+    a: &[Numeric],
+    (rs, n): (u8, u8),
+    b: &[Numeric],
+    (m, cs): (u8, u8),
+) -> Result<Vec<Numeric>, EvalError> {
+    if n != m {
+        return Err(EvalError::Shape(ShapeError::Mismatch {
+            expected: n as usize,
+            found: m as usize,
+        }));
+    }
+    let (n, cs) = (n as usize, cs as usize);
+    Ok((0..rs as usize)
+        .flat_map(|r| {
+            (0..cs).map(move |c| {
+                (0..n)
+                    .map(|k| a[r * n + k] * b[k * cs + c])
+                    .fold(Numeric::Integer(0), |acc, x| acc + x)
+            })
+        })
+        .collect())
+}
+
+// every subexpression other than a number with the value it evaluates to, in the order they are
+// evaluated (operands before their operation), so the work can be shown step by step
+// the steps stop at the first subexpression that fails to evaluate
+pub fn evaluation_steps(expr: &Expression, ctx: &EvalContext) -> Vec<(Expression, Numeric)> {
+    let mut steps = Vec::new();
+    let unlimited = Cell::new(usize::MAX);
+    let _ = eval_recording(
+        expr,
+        ctx,
+        &EvalOptions::default(),
+        &unlimited,
+        &mut |e, n| steps.push((e.clone(), n)),
+    );
+    steps
+}
+
+// the caller takes the step for expr itself, one is taken here for each subexpression
+fn eval_recording(
+    expr: &Expression,
+    ctx: &EvalContext,
+    options: &EvalOptions,
+    steps: &Cell<usize>,
+    record: &mut dyn FnMut(&Expression, Numeric),
+) -> Result<Numeric, EvalError> {
+    let mut eval = |e: &Expression| {
+        step(steps)?;
+        eval_recording(e, ctx, options, steps, record)
+    };
+    let value = match expr {
+        Expression::Atom(Atom::Numeric(n)) => Ok(*n),
+        Expression::Atom(Atom::Variable(v)) => ctx
+            .variables
+            .get(v)
+            .copied()
+            .ok_or(EvalError::UndefinedVariable(*v)),
+        Expression::Atom(_) => Err(EvalError::Unsupported),
+
+        Expression::Negate(e) => Ok(-eval(e)?),
+        Expression::Factorial(e) => factorial(eval(e)?, options),
+        Expression::Percent(e) => Ok(eval(e)? / Numeric::Integer(100)),
+
+        Expression::Add(l, r) => Ok(eval(l)? + eval(r)?),
+        Expression::Subtract(l, r) => Ok(eval(l)? - eval(r)?),
+        Expression::Multiply(l, r) => Ok(eval(l)? * eval(r)?),
+        Expression::Divide(l, r) => {
+            let (l, r) = (eval(l)?, nonzero(eval(r)?)?);
+            Ok(l / r)
+        }
+        Expression::Power(l, r) => Ok(power(eval(l)?, eval(r)?)),
+        Expression::Modulus(l, r) => {
+            let (l, r) = (eval(l)?, nonzero(eval(r)?)?);
+            modulus(l, r)
+        }
+
+        // the derivative of a known function, written with primes, is evaluated in closed form
+        Expression::Function { name, args } if name.ends_with('\'') => {
+            match (args.len(), args.first().and_then(|u| prime(name, u))) {
+                (1, Some(derivative)) => eval(&derivative),
+                _ => Err(EvalError::UnknownFunction(name.clone())),
+            }
+        }
+        Expression::Function { name, args } => {
+            let args = args
+                .iter()
+                .map(|e| eval(e))
+                .collect::<Result<Vec<_>, _>>()?;
+            call_function(name, &args, options)
+        }
+
+        Expression::Vector {
+            backing: _,
+            size: _,
+        }
+        | Expression::Matrix {
+            backing: _,
+            shape: _,
+        }
+        | Expression::Integral {
+            integrand: _,
+            var: _,
+            lower: _,
+            upper: _,
+        }
+        | Expression::Relation(_, _, _)
+        | Expression::And(_)
+        | Expression::Or(_)
+        | Expression::Not(_) => Err(EvalError::Unsupported),
+
+        // the body with the index bound to every integer from lower to upper, an empty range sums
+        // to 0
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => match (eval(lower)?, eval(upper)?) {
+            (Numeric::Integer(lower), Numeric::Integer(upper))
+                if upper as i64 - lower as i64 >= MAX_SUM_TERMS =>
+            {
+                Err(EvalError::StepLimitExceeded)
+            }
+            (Numeric::Integer(lower), Numeric::Integer(upper)) => {
+                let mut ctx = ctx.clone();
+                let mut total = Numeric::Integer(0);
+                for k in lower..=upper {
+                    ctx.variables.insert(*index, Numeric::Integer(k));
+                    step(steps)?;
+                    total = total + eval_recording(body, &ctx, options, steps, record)?;
+                }
+                Ok(total)
+            }
+            _ => Err(EvalError::DomainError),
+        },
+
+        // the value of the first branch whose condition holds, undefined where none of them do
+        Expression::Piecewise(branches) => {
+            let mut value = Err(EvalError::DomainError);
+            for (branch, condition) in branches {
+                if holds(condition, &mut eval)? {
+                    value = eval(branch);
+                    break;
+                }
+            }
+            value
+        }
+    }?;
+
+    if !matches!(expr, Expression::Atom(Atom::Numeric(_))) {
+        record(expr, value);
+    }
+    Ok(value)
+}
+
+// whether a relation, or a logical combination of relations and booleans, holds with the sides of
+// its relations evaluated by eval
+// complex numbers can only be compared for equality
+fn holds(
+    condition: &Expression,
+    eval: &mut dyn FnMut(&Expression) -> Result<Numeric, EvalError>,
+) -> Result<bool, EvalError> {
+    match condition {
+        Expression::Relation(op, l, r) => {
+            let (l, r) = (eval(l)?, eval(r)?);
+            match op {
+                Comparison::Equal => Ok(l == r),
+                Comparison::NotEqual => Ok(l != r),
+                _ if l.parts().1 != 0.0 || r.parts().1 != 0.0 => Err(EvalError::DomainError),
+                Comparison::Less => Ok(f32::from(l) < f32::from(r)),
+                Comparison::LessEqual => Ok(f32::from(l) <= f32::from(r)),
+                Comparison::Greater => Ok(f32::from(l) > f32::from(r)),
+                Comparison::GreaterEqual => Ok(f32::from(l) >= f32::from(r)),
+            }
+        }
+        Expression::And(es) => {
+            for e in es {
+                if !holds(e, eval)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        Expression::Or(es) => {
+            for e in es {
+                if holds(e, eval)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Expression::Not(e) => Ok(!holds(e, eval)?),
+        Expression::Atom(Atom::Boolean(b)) => Ok(*b),
+        _ => Err(EvalError::Unsupported),
+    }
+}
+
+// replaces the entries of every vector and matrix which evaluate to a number by that number,
+// symbolic entries are left as they are (apart from the containers inside of them)
+pub fn evaluate_containers(expr: &Expression) -> Expression {
+    match expr {
+        Expression::Vector {
+            backing: _,
+            size: _,
+        }
+        | Expression::Matrix {
+            backing: _,
+            shape: _,
+        } => expr.map_children(|e| match evaluate(e).and_then(Value::scalar) {
+            Ok(n) => Expression::Atom(Atom::Numeric(n)),
+            Err(_) => evaluate_containers(e),
+        }),
+        e => e.map_children(evaluate_containers),
+    }
+}
+
+// drops the branches of every piecewise whose condition folds to false, and collapses a piecewise
+// to the value of its first branch when that condition folds to true, conditions with free
+// variables are kept symbolic (along with every branch after them)
+pub fn prune_piecewise(expr: &Expression) -> Expression {
+    match expr.map_children(prune_piecewise) {
+        Expression::Piecewise(branches) => {
+            let mut kept = Vec::new();
+            for (value, condition) in branches {
+                match holds(&condition, &mut |e| evaluate(e).and_then(Value::scalar)) {
+                    Ok(false) => (),
+                    Ok(true) if kept.is_empty() => return value,
+                    // later branches can no longer be reached
+                    Ok(true) => {
+                        kept.push((value, condition));
+                        break;
+                    }
+                    Err(_) => kept.push((value, condition)),
+                }
+            }
+            Expression::Piecewise(kept)
+        }
+        e => e,
+    }
+}
+
+// built in functions: (name, number of arguments, implementation)
+// a function may be listed once per number of arguments it takes
+type Function = fn(&[Numeric]) -> Result<Numeric, EvalError>;
+const FUNCTIONS: &[(&str, usize, Function)] = &[
+    ("sgn", 1, |args| Ok(sign(args[0]))),
+    ("clamp", 3, |args| clamp(args[0], args[1], args[2])),
+    ("mod1", 1, |args| Ok(fractional_part(args[0]))),
+    ("sin", 1, |args| decimal(libm::sinf, args[0])),
+    ("cos", 1, |args| decimal(libm::cosf, args[0])),
+    ("tan", 1, |args| decimal(libm::tanf, args[0])),
+    ("exp", 1, |args| decimal(libm::expf, args[0])),
+    ("ln", 1, |args| match f32::from(args[0]) > 0.0 {
+        true => decimal(libm::logf, args[0]),
+        false => Err(EvalError::DomainError),
+    }),
+    // log(x) is the natural logarithm, as it is when simplifying and differentiating
+    ("log", 1, |args| {
+        log(Numeric::Decimal(core::f32::consts::E), args[0])
+    }),
+    ("log", 2, |args| log(args[0], args[1])),
+    ("sqrt", 1, |args| Ok(sqrt(args[0]))),
+    ("floor", 1, |args| round(args[0], false)),
+    ("ceil", 1, |args| round(args[0], true)),
+    ("binom", 2, |args| binomial(args[0], args[1])),
+    ("abs", 1, |args| {
+        let (re, im) = args[0].parts();
+        Ok(Numeric::Decimal(libm::hypotf(re, im)))
+    }),
+];
+
+// the functions whose argument is an angle, converted to radians before they are applied
+const TRIGONOMETRIC: &[&str] = &["sin", "cos", "tan"];
+
+fn call_function(
+    name: &str,
+    args: &[Numeric],
+    options: &EvalOptions,
+) -> Result<Numeric, EvalError> {
+    let converted;
+    let args = match options.angle_mode {
+        AngleMode::Degrees if TRIGONOMETRIC.contains(&name) => {
+            let radians = Numeric::Decimal(core::f32::consts::PI / 180.0);
+            converted = args.iter().map(|&a| a * radians).collect::<Vec<_>>();
+            &converted[..]
+        }
+        _ => args,
+    };
+
+    options.functions.call(name, args)
+}
+
+// the logarithm of x to the base b, ln(x) / ln(b)
+fn log(b: Numeric, x: Numeric) -> Result<Numeric, EvalError> {
+    let (base, value) = (f32::from(b), f32::from(x));
+    if b.parts().1 != 0.0 || x.parts().1 != 0.0 || base <= 0.0 || base == 1.0 || value <= 0.0 {
+        return Err(EvalError::DomainError);
+    }
+    Ok(Numeric::Decimal(libm::logf(value) / libm::logf(base)))
+}
+
+// applies a real function, complex arguments are outside of its domain
+fn decimal(f: fn(f32) -> f32, x: Numeric) -> Result<Numeric, EvalError> {
+    match x {
+        Numeric::Complex { re: _, im: _ } => Err(EvalError::DomainError),
+        x => Ok(Numeric::Decimal(f(x.into()))),
+    }
+}
+
+// rounds a real number down (floor) or up (ceil) to a whole number, exactly for fractions, the
+// result is an integer unless it is too large for one
+fn round(x: Numeric, up: bool) -> Result<Numeric, EvalError> {
+    let rounded = match x {
+        Numeric::Integer(i) => return Ok(Numeric::Integer(i)),
+        Numeric::Fraction(n, d) => {
+            let (n, d) = if d < 0 { (-n, -d) } else { (n, d) };
+            let floor = n.div_euclid(d);
+            return Ok(Numeric::Integer(match up && n.rem_euclid(d) != 0 {
+                true => floor + 1,
+                false => floor,
+            }));
+        }
+        Numeric::Complex { re: _, im: _ } => return Err(EvalError::DomainError),
+        Numeric::Decimal(d) if up => libm::ceilf(d),
+        Numeric::Decimal(d) => libm::floorf(d),
+    };
+    Ok(match libm::fabsf(rounded) < i32::MAX as f32 {
+        true => Numeric::Integer(rounded as i32),
+        false => Numeric::Decimal(rounded),
+    })
+}
+
+// the principal square root, negative and complex numbers have a complex root
+pub(crate) fn sqrt(x: Numeric) -> Numeric {
+    let (re, im) = x.parts();
+    let r = libm::hypotf(re, im);
+    let root_im = libm::sqrtf((r - re) / 2.0);
+    Numeric::complex(
+        libm::sqrtf((r + re) / 2.0),
+        if im < 0.0 { -root_im } else { root_im },
+    )
+}
+
+fn sign(x: Numeric) -> Numeric {
+    let value = f32::from(x);
+    Numeric::Integer(if value > 0.0 {
+        1
+    } else if value < 0.0 {
+        -1
+    } else {
+        0
+    })
+}
+
+fn clamp(x: Numeric, lo: Numeric, hi: Numeric) -> Result<Numeric, EvalError> {
+    if lo > hi {
+        Err(EvalError::DomainError)
+    } else if x < lo {
+        Ok(lo)
+    } else if x > hi {
+        Ok(hi)
+    } else {
+        Ok(x)
+    }
+}
+
+// x - floor(x), so the result is always in [0, 1)
+fn fractional_part(x: Numeric) -> Numeric {
+    match x {
+        Numeric::Integer(_) => Numeric::Integer(0),
+        x => {
+            let value = f32::from(x);
+            Numeric::Decimal(value - libm::floorf(value))
+        }
+    }
+}
+
+// n! for whole, non negative n, exact until it overflows an integer and then promoted to a decimal
+fn factorial(n: Numeric, options: &EvalOptions) -> Result<Numeric, EvalError> {
+    let value = f32::from(n);
+    let whole = libm::truncf(value) == value;
+    if n.parts().1 != 0.0 || (whole && value < 0.0) {
+        return Err(EvalError::DomainError);
+    }
+    if !whole {
+        return match options.gamma {
+            true => Ok(Numeric::Decimal(libm::tgammaf(value + 1.0))),
+            false => Err(EvalError::DomainError),
+        };
+    }
+
+    let mut result = Numeric::Integer(1);
+    for i in 2..=i32::from(n) {
+        result = result * Numeric::Integer(i);
+        // once the decimal overflows the result can no longer change
+        if matches!(result, Numeric::Decimal(d) if d.is_infinite()) {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+// n choose k = n! / (k! (n - k)!) for whole, non negative n, zero when k is outside of 0..=n
+// built up one factor at a time, each partial product is itself a binomial coefficient so the
+// integer division is exact, promoted to a decimal once it overflows an integer
+fn binomial(n: Numeric, k: Numeric) -> Result<Numeric, EvalError> {
+    let whole = |x: Numeric| {
+        let value = f32::from(x);
+        x.parts().1 == 0.0 && libm::truncf(value) == value
+    };
+    if !whole(n) || !whole(k) || f32::from(n) < 0.0 {
+        return Err(EvalError::DomainError);
+    }
+
+    let (n, k) = (i32::from(n), i32::from(k));
+    if k < 0 || k > n {
+        return Ok(Numeric::Integer(0));
+    }
+
+    let k = k.min(n - k);
+    let mut result = Numeric::Integer(1);
+    for i in 1..=k {
+        result = match result {
+            Numeric::Integer(r) => match i32::try_from(r as i64 * (n - k + i) as i64 / i as i64) {
+                Ok(r) => Numeric::Integer(r),
+                Err(_) => Numeric::Decimal(r as f32 * (n - k + i) as f32 / i as f32),
+            },
+            r => Numeric::Decimal(f32::from(r) * (n - k + i) as f32 / i as f32),
+        };
+    }
+    Ok(result)
+}
+
+fn power(base: Numeric, exponent: Numeric) -> Numeric {
+    match (base, exponent) {
+        (Numeric::Integer(b), Numeric::Integer(e)) if e >= 0 => b.checked_pow(e as u32).map_or(
+            Numeric::Decimal(libm::powf(b as f32, e as f32)),
+            Numeric::Integer,
+        ),
+        // b^e = exp(e ln b), with the principal logarithm of b
+        (b @ Numeric::Complex { re: _, im: _ }, e) | (b, e @ Numeric::Complex { re: _, im: _ }) => {
+            let (re, im) = b.parts();
+            if re == 0.0 && im == 0.0 {
+                return Numeric::Integer(0);
+            }
+            let ln_b = Numeric::complex(libm::logf(libm::hypotf(re, im)), libm::atan2f(im, re));
+            let (x, y) = (e * ln_b).parts();
+            let magnitude = libm::expf(x);
+            Numeric::complex(magnitude * libm::cosf(y), magnitude * libm::sinf(y))
+        }
+        (b, e) => Numeric::Decimal(libm::powf(b.into(), e.into())),
+    }
+}
+
+// divisors have to be nonzero, the quotient would otherwise be an infinite decimal or a fraction
+// over zero
+fn nonzero(n: Numeric) -> Result<Numeric, EvalError> {
+    match n.parts() == (0.0, 0.0) {
+        true => Err(EvalError::DivByZero),
+        false => Ok(n),
+    }
+}
+
+fn modulus(l: Numeric, r: Numeric) -> Result<Numeric, EvalError> {
+    Ok(match (l, r) {
+        (Numeric::Integer(a), Numeric::Integer(b)) => a.checked_rem(b).map_or(
+            Numeric::Decimal(libm::fmodf(a as f32, b as f32)),
+            Numeric::Integer,
+        ),
+        (Numeric::Complex { re: _, im: _ }, _) | (_, Numeric::Complex { re: _, im: _ }) => {
+            return Err(EvalError::DomainError)
+        }
+        (a, b) => Numeric::Decimal(libm::fmodf(a.into(), b.into())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, collections::BTreeMap, string::ToString, vec};
+    use core::str::FromStr;
+
+    use super::{
+        evaluate, evaluate_containers, evaluate_with, evaluation_steps, prune_piecewise, try_eval,
+        AngleMode, EvalContext, EvalOptions, FnRegistry, Value,
+    };
+    use crate::{
+        expression::expression_tree::{Atom, Comparison, Expression, Numeric},
+        EvalError, ShapeError,
+    };
+
+    fn eval(s: &str) -> Result<Numeric, EvalError> {
+        evaluate(&Expression::from_str(s).unwrap()).and_then(Value::scalar)
+    }
+
+    #[test]
+    fn test_evaluate_arithmetic() {
+        assert!(matches!(eval("2 + 3 * 4"), Ok(Numeric::Integer(14))));
+        assert!(matches!(eval("2 ^ 10 - 24"), Ok(Numeric::Integer(1000))));
+        assert!(matches!(eval("7 / 2"), Ok(Numeric::Fraction(7, 2))));
+        assert!(matches!(eval("7 % 4"), Ok(Numeric::Integer(3))));
+        assert!(matches!(eval("1.5 * 2"), Ok(Numeric::Decimal(d)) if d == 3.0));
+        assert!(matches!(eval("2 ^ 3 ^ 2"), Ok(Numeric::Integer(512))));
+        assert!(matches!(eval("(2 ^ 3) ^ 2"), Ok(Numeric::Integer(64))));
+        assert!(matches!(
+            eval("(1/46341)*(1/46341)"),
+            Ok(Numeric::Decimal(_))
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_variables() {
+        let ctx = EvalContext {
+            variables: BTreeMap::from([('x', Numeric::Integer(3))]),
+        };
+        let expr = Expression::from_str("x ^ 2 + y").unwrap();
+
+        assert_eq!(
+            try_eval(&expr, &ctx),
+            Err(EvalError::UndefinedVariable('y'))
+        );
+        assert!(matches!(
+            try_eval(&Expression::from_str("x ^ 2 + 1").unwrap(), &ctx),
+            Ok(Value::Scalar(Numeric::Integer(10)))
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_utility_functions() {
+        assert!(matches!(eval("sgn(-2)"), Ok(Numeric::Integer(-1))));
+        assert!(matches!(eval("sgn(0)"), Ok(Numeric::Integer(0))));
+        assert!(matches!(eval("sgn(0.5)"), Ok(Numeric::Integer(1))));
+        assert!(matches!(eval("clamp(5, 0, 3)"), Ok(Numeric::Integer(3))));
+        assert!(matches!(eval("clamp(-5, 0, 3)"), Ok(Numeric::Integer(0))));
+        assert!(matches!(eval("clamp(2, 0, 3)"), Ok(Numeric::Integer(2))));
+        assert!(matches!(eval("mod1(2.25)"), Ok(Numeric::Decimal(d)) if d == 0.25));
+        assert!(matches!(eval("mod1(-0.25)"), Ok(Numeric::Decimal(d)) if d == 0.75));
+        assert!(matches!(eval("mod1(7)"), Ok(Numeric::Integer(0))));
+    }
+
+    #[test]
+    fn test_evaluate_math_functions() {
+        assert!(matches!(eval("sin(0)"), Ok(Numeric::Decimal(d)) if d == 0.0));
+        assert!(matches!(eval("cos(0)"), Ok(Numeric::Decimal(d)) if d == 1.0));
+        assert!(matches!(eval("sqrt(4)"), Ok(Numeric::Decimal(d)) if d == 2.0));
+        assert!(matches!(eval("exp(0) + ln(1)"), Ok(Numeric::Decimal(d)) if d == 1.0));
+        assert!(matches!(eval("abs(0 - 2.5)"), Ok(Numeric::Decimal(d)) if d == 2.5));
+        assert_eq!(eval("ln(0)"), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn test_evaluate_angle_mode() {
+        let sin = Expression::from_str("sin(90)").unwrap();
+        let degrees = EvalOptions {
+            angle_mode: AngleMode::Degrees,
+            ..EvalOptions::default()
+        };
+        let value = |v: Result<Value, EvalError>| f32::from(v.unwrap().scalar().unwrap());
+
+        assert!((value(evaluate_with(&sin, &degrees)) - 1.0).abs() < 1e-6);
+        assert!((value(evaluate(&sin)) - 0.894).abs() < 1e-3);
+        assert!(
+            (value(evaluate_with(&sin, &EvalOptions::default())) - value(evaluate(&sin))).abs()
+                < 1e-6
+        );
+
+        let cos = Expression::from_str("cos(180) + sqrt(4)").unwrap();
+        assert!((value(evaluate_with(&cos, &degrees)) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_evaluate_registry() {
+        let eval_with = |s: &str, options: &EvalOptions| {
+            evaluate_with(&Expression::from_str(s).unwrap(), options).and_then(Value::scalar)
+        };
+        let mut options = EvalOptions::default();
+        options.functions.register("avg", 2, |args| {
+            Ok((args[0] + args[1]) / Numeric::Integer(2))
+        });
+
+        assert!(matches!(
+            eval_with("avg(2, 4)", &options),
+            Ok(Numeric::Integer(3))
+        ));
+        assert!(matches!(
+            eval_with("avg(1, 2) + sgn(-5)", &options),
+            Ok(Numeric::Fraction(1, 2))
+        ));
+        assert_eq!(
+            eval_with("avg(1, 2, 3)", &options),
+            Err(EvalError::ArityMismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            eval("avg(2, 4)"),
+            Err(EvalError::UnknownFunction("avg".to_string()))
+        );
+
+        // registering a built in function replaces it
+        options
+            .functions
+            .register("sgn", 1, |_| Ok(Numeric::Integer(0)));
+        assert!(matches!(
+            eval_with("sgn(-5)", &options),
+            Ok(Numeric::Integer(0))
+        ));
+
+        let empty = EvalOptions {
+            functions: FnRegistry::empty(),
+            ..EvalOptions::default()
+        };
+        assert_eq!(
+            eval_with("sin(0)", &empty),
+            Err(EvalError::UnknownFunction("sin".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_floor_ceil() {
+        assert!(matches!(eval("floor(3.7)"), Ok(Numeric::Integer(3))));
+        assert!(matches!(eval("ceil(3.2)"), Ok(Numeric::Integer(4))));
+        assert!(matches!(eval("floor(-3.2)"), Ok(Numeric::Integer(-4))));
+        assert!(matches!(eval("ceil(-3.7)"), Ok(Numeric::Integer(-3))));
+        assert!(matches!(eval("floor(7 / 2)"), Ok(Numeric::Integer(3))));
+        assert!(matches!(eval("ceil(7 / 2)"), Ok(Numeric::Integer(4))));
+        assert!(matches!(eval("floor(-7 / 2)"), Ok(Numeric::Integer(-4))));
+        assert!(matches!(eval("ceil(5)"), Ok(Numeric::Integer(5))));
+        assert!(matches!(eval("floor(1e20)"), Ok(Numeric::Decimal(_))));
+        assert_eq!(eval("floor(sqrt(-1))"), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn test_evaluate_log() {
+        let close = |s: &str, expected: f32| (f32::from(eval(s).unwrap()) - expected).abs() < 1e-5;
+
+        assert!(close("log(2, 8)", 3.0));
+        assert!(close("log(10, 0.01)", -2.0));
+        assert!(close("log(exp(2))", 2.0));
+        assert_eq!(eval("log(1, 5)"), Err(EvalError::DomainError));
+        assert_eq!(eval("log(2, 0)"), Err(EvalError::DomainError));
+        assert_eq!(
+            eval("log(2, 8, 1)"),
+            Err(EvalError::ArityMismatch {
+                expected: 1,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_evaluate_complex() {
+        assert!(matches!(eval("i * i"), Ok(Numeric::Decimal(d)) if d == -1.0));
+        assert_eq!(
+            eval("sqrt(0 - 4)"),
+            Ok(Numeric::Complex { re: 0.0, im: 2.0 })
+        );
+        assert_eq!(
+            eval("(1 + 2 * i) * (3 - i)"),
+            Ok(Numeric::Complex { re: 5.0, im: 5.0 })
+        );
+        assert_eq!(
+            eval("(1 + i) / (1 - i) + 2"),
+            Ok(Numeric::Complex { re: 2.0, im: 1.0 })
+        );
+        assert!(matches!(eval("abs(3 + 4 * i)"), Ok(Numeric::Decimal(d)) if d == 5.0));
+
+        let square = eval("i ^ 2").unwrap().parts();
+        assert!((square.0 + 1.0).abs() < 1e-6 && square.1.abs() < 1e-6);
+
+        assert_eq!(eval("sin(i)"), Err(EvalError::DomainError));
+        assert_eq!(eval("i % 2"), Err(EvalError::DomainError));
+        assert_eq!(eval("1 / (i - i)"), Err(EvalError::DivByZero));
+    }
+
+    #[test]
+    fn test_evaluate_div_by_zero() {
+        assert_eq!(eval("5/0"), Err(EvalError::DivByZero));
+        assert_eq!(eval("5%0"), Err(EvalError::DivByZero));
+        assert_eq!(eval("1.5 / (2 - 2)"), Err(EvalError::DivByZero));
+        assert_eq!(eval("x / 0"), Err(EvalError::UndefinedVariable('x')));
+        assert!(matches!(eval("0 / 5"), Ok(Numeric::Integer(0))));
+    }
+
+    #[test]
+    fn test_evaluate_function_errors() {
+        assert_eq!(
+            eval("clamp(5, 3)"),
+            Err(EvalError::ArityMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(eval("clamp(1, 3, 0)"), Err(EvalError::DomainError));
+        assert_eq!(eval("sgn(y)"), Err(EvalError::UndefinedVariable('y')));
+        assert_eq!(
+            eval("sin(1, 2)"),
+            Err(EvalError::ArityMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            eval("foo(1)"),
+            Err(EvalError::UnknownFunction("foo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_vector() {
+        let value = |s: &str| evaluate(&Expression::from_str(s).unwrap());
+
+        assert_eq!(
+            value("<1+1, 2*3>"),
+            Ok(Value::Vector(vec![
+                Numeric::Integer(2),
+                Numeric::Integer(6)
+            ]))
+        );
+        assert_eq!(
+            value("2 * <1, 2> - <1, 1> / 2"),
+            Ok(Value::Vector(vec![
+                Numeric::Fraction(3, 2),
+                Numeric::Fraction(7, 2)
+            ]))
+        );
+        assert_eq!(
+            value("-<1, 2.5>"),
+            Ok(Value::Vector(vec![
+                Numeric::Integer(-1),
+                Numeric::Decimal(-2.5)
+            ]))
+        );
+        assert_eq!(
+            value("<1, 2> + <1, 2, 3>"),
+            Err(EvalError::Shape(ShapeError::Mismatch {
+                expected: 2,
+                found: 3
+            }))
+        );
+        assert_eq!(value("<1, 2> * <3, 4>"), Err(EvalError::Unsupported));
+        assert_eq!(value("sin(<1, 2>)"), Err(EvalError::Unsupported));
+        assert_eq!(value("<1, 2> / 0"), Err(EvalError::DivByZero));
+    }
+
+    #[test]
+    fn test_evaluate_matrix() {
+        let value = |s: &str| evaluate(&Expression::from_str(s).unwrap());
+        let matrix = |entries: &[i32], shape| Value::Matrix {
+            backing: entries.iter().map(|&i| Numeric::Integer(i)).collect(),
+            shape,
+        };
+
+        assert_eq!(value("[1+1, 2; 3, 4*2]"), Ok(matrix(&[2, 2, 3, 8], (2, 2))));
+        assert_eq!(
+            value("[1, 2; 3, 4] * [0, 1; 1, 0] + 2 * [1, 0; 0, 1]"),
+            Ok(matrix(&[4, 1, 4, 5], (2, 2)))
+        );
+        assert_eq!(
+            value("[1, 2, 3; 4, 5, 6] * <1, 0, 2>"),
+            Ok(Value::Vector(vec![
+                Numeric::Integer(7),
+                Numeric::Integer(16)
+            ]))
+        );
+        assert_eq!(
+            value("[1, 2; 3, 4] * [1, 2, 3]"),
+            Err(EvalError::Shape(ShapeError::Mismatch {
+                expected: 2,
+                found: 1
+            }))
+        );
+        assert_eq!(
+            value("[1, 2; 3, 4] + [1, 2]"),
+            Err(EvalError::Shape(ShapeError::Mismatch {
+                expected: 2,
+                found: 1
+            }))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_containers() {
+        let expr = Expression::from_str("[1+1, 2; 3, 4*2]").unwrap();
+        assert_eq!(
+            evaluate_containers(&expr),
+            Expression::from_str("[2, 2; 3, 8]").unwrap()
+        );
+
+        let expr = Expression::from_str("f(<2^3, x+1, <1+2>>) + <1-1>").unwrap();
+        assert_eq!(
+            evaluate_containers(&expr),
+            Expression::from_str("f(<8, x+1, <3>>) + <0>").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prune_piecewise() {
+        let e = |s| Expression::from_str(s).unwrap();
+        let relation = |op, l, r| Expression::Relation(op, Box::new(e(l)), Box::new(e(r)));
+
+        // 1 < 0 never holds, so its branch is dropped and x > 0 stays symbolic
+        let expr = Expression::Piecewise(vec![
+            (e("1"), relation(Comparison::Less, "1", "0")),
+            (e("x"), relation(Comparison::Greater, "x", "0")),
+            (e("-x"), relation(Comparison::LessEqual, "x", "0")),
+        ]);
+        assert_eq!(
+            prune_piecewise(&expr),
+            Expression::Piecewise(vec![
+                (e("x"), relation(Comparison::Greater, "x", "0")),
+                (e("-x"), relation(Comparison::LessEqual, "x", "0")),
+            ])
+        );
+
+        // the first branch which holds is the value
+        let expr = Expression::Piecewise(vec![
+            (e("1"), relation(Comparison::Less, "1", "0")),
+            (e("2 * y"), relation(Comparison::Equal, "1 + 1", "2")),
+            (e("3"), Expression::Atom(Atom::Boolean(true))),
+        ]);
+        assert_eq!(prune_piecewise(&expr), e("2 * y"));
+
+        // a branch which holds after a symbolic one ends the piecewise
+        let expr = Expression::Piecewise(vec![
+            (e("x"), relation(Comparison::Greater, "x", "0")),
+            (e("0"), Expression::Atom(Atom::Boolean(true))),
+            (e("1"), relation(Comparison::Less, "x", "0")),
+        ]);
+        assert_eq!(
+            prune_piecewise(&expr),
+            Expression::Piecewise(vec![
+                (e("x"), relation(Comparison::Greater, "x", "0")),
+                (e("0"), Expression::Atom(Atom::Boolean(true))),
+            ])
+        );
+
+        // nested piecewise are pruned too
+        let expr = e("x")
+            + Expression::Piecewise(vec![
+                (
+                    e("1"),
+                    Expression::Not(Box::new(Expression::Atom(Atom::Boolean(true)))),
+                ),
+                (e("2"), relation(Comparison::GreaterEqual, "3", "3")),
+            ]);
+        assert_eq!(prune_piecewise(&expr), e("x + 2"));
+    }
+
+    #[test]
+    fn test_evaluate_factorial() {
+        assert!(matches!(eval("5!"), Ok(Numeric::Integer(120))));
+        assert!(matches!(eval("0!"), Ok(Numeric::Integer(1))));
+        assert!(matches!(eval("13!"), Ok(Numeric::Decimal(d)) if d == 6227020800.0));
+        assert_eq!(eval("(-1)!"), Err(EvalError::DomainError));
+        assert_eq!(eval("(1/2)!"), Err(EvalError::DomainError));
+        assert_eq!(eval("2.5!"), Err(EvalError::DomainError));
+        assert_eq!(eval("(-2.5)!"), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn test_evaluate_factorial_gamma() {
+        let gamma = EvalOptions {
+            gamma: true,
+            ..EvalOptions::default()
+        };
+        let eval = |s: &str| evaluate_with(&Expression::from_str(s).unwrap(), &gamma);
+
+        assert!(matches!(
+            eval("2.5!"),
+            Ok(Value::Scalar(Numeric::Decimal(d))) if (d - 3.32335).abs() < 1e-4
+        ));
+        assert!(matches!(
+            eval("(-0.5)!"),
+            Ok(Value::Scalar(Numeric::Decimal(d))) if (d - 1.77245).abs() < 1e-4
+        ));
+        assert_eq!(eval("5!"), Ok(Value::Scalar(Numeric::Integer(120))));
+        assert_eq!(eval("(-3)!"), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn test_evaluate_factorial_overflow() {
+        // stops multiplying once the result is infinite rather than looping a million times
+        assert!(matches!(eval("1000000!"), Ok(Numeric::Decimal(d)) if d == f32::INFINITY));
+        assert!(matches!(eval("40!"), Ok(Numeric::Decimal(d)) if d == f32::INFINITY));
+    }
+
+    #[test]
+    fn test_evaluate_step_limit() {
+        // a balanced sum of 2^16 ones, with 2^17 - 1 nodes
+        let sum = (0..16).fold(Expression::from_str("1").unwrap(), |acc, _| {
+            Expression::Add(Box::new(acc.clone()), Box::new(acc))
+        });
+        let limited = |max_steps| EvalOptions {
+            max_steps: Some(max_steps),
+            ..EvalOptions::default()
+        };
+
+        assert_eq!(
+            evaluate_with(&sum, &limited(1000)),
+            Err(EvalError::StepLimitExceeded)
+        );
+        assert_eq!(
+            evaluate_with(&sum, &limited(131_071)),
+            Ok(Value::Scalar(Numeric::Integer(65_536)))
+        );
+        assert_eq!(
+            evaluate_with(&sum, &limited(131_070)),
+            Err(EvalError::StepLimitExceeded)
+        );
+
+        // the nodes evaluated through functions and relations count as well
+        let eval = |s: &str, max_steps| {
+            evaluate_with(&Expression::from_str(s).unwrap(), &limited(max_steps))
+        };
+        assert_eq!(
+            eval("sin(1 + 2) * 3", 6),
+            Ok(Value::Scalar(Numeric::Decimal(libm::sinf(3.0) * 3.0)))
+        );
+        assert_eq!(eval("sin(1 + 2) * 3", 5), Err(EvalError::StepLimitExceeded));
+    }
+
+    #[test]
+    fn test_evaluate_binom() {
+        assert!(matches!(eval("binom(5, 2)"), Ok(Numeric::Integer(10))));
+        assert!(matches!(eval("binom(10, 0)"), Ok(Numeric::Integer(1))));
+        assert!(matches!(eval("binom(10, 10)"), Ok(Numeric::Integer(1))));
+        assert!(matches!(eval("binom(3, 5)"), Ok(Numeric::Integer(0))));
+        assert!(matches!(eval("binom(3, -1)"), Ok(Numeric::Integer(0))));
+        assert!(matches!(
+            eval("binom(33, 16)"),
+            Ok(Numeric::Integer(1166803110))
+        ));
+        assert!(
+            matches!(eval("binom(40, 20)"), Ok(Numeric::Decimal(d)) if (d - 137846528820.0).abs() < 1e5)
+        );
+        assert_eq!(eval("binom(-2, 1)"), Err(EvalError::DomainError));
+        assert_eq!(eval("binom(5 / 2, 1)"), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn test_evaluation_steps() {
+        let parse = |s| Expression::from_str(s).unwrap();
+
+        let steps = evaluation_steps(&parse("2 + 3 * 4"), &EvalContext::default());
+        assert_eq!(
+            steps,
+            [
+                (parse("3 * 4"), Numeric::Integer(12)),
+                (parse("2 + 3 * 4"), Numeric::Integer(14)),
+            ]
+        );
+
+        let ctx = EvalContext {
+            variables: BTreeMap::from([('x', Numeric::Integer(3))]),
+        };
+        let steps = evaluation_steps(&parse("x ^ 2 - y"), &ctx);
+        assert_eq!(
+            steps,
+            [
+                (parse("x"), Numeric::Integer(3)),
+                (parse("x ^ 2"), Numeric::Integer(9)),
+            ]
+        );
+    }
+}
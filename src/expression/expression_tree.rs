@@ -1,26 +1,57 @@
-use alloc::{boxed::Box, string::String, vec::Vec};
+use alloc::{boxed::Box, collections::BTreeMap, format, string::String, vec, vec::Vec};
 use core::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
-    ops::{Add, Div, Mul, Neg, Sub},
+    iter,
+    ops::{Add, Deref, Div, Mul, Neg, Sub},
     str::FromStr,
 };
 
 use heapless::LinearMap;
 
 use crate::{
-    expression::parser::parse,
+    expression::{
+        latex::{nesting_depth, DEFAULT_MAX_DEPTH},
+        parser::try_parse,
+    },
     modifier::{adaptable_modifier::ModifierFunction, ModifierImmutable, ModifierMutable},
     Error,
 };
 
 // Numeric: representation of any numeric value
+// arithmetic between integers stays exact (integer results, or fractions for uneven division) and
+// is promoted to a decimal when mixed with a decimal or when the integer result would overflow
+// any arithmetic involving a complex number is done in complex decimals
+// serialized externally tagged (`{"Integer": 5}`, `{"Decimal": 5.0}`) so variants stay distinguishable
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Numeric {
     Integer(i32),
     Decimal(f32),
     Fraction(i32, i32), // might be unnecessary?
+    Complex { re: f32, im: f32 },
+}
+
+impl Numeric {
+    // the imaginary unit i
+    pub const I: Numeric = Numeric::Complex { re: 0.0, im: 1.0 };
+
+    // the real and imaginary parts, real numbers have no imaginary part
+    pub fn parts(self) -> (f32, f32) {
+        match self {
+            Numeric::Complex { re, im } => (re, im),
+            n => (n.into(), 0.0),
+        }
+    }
+
+    // a complex number, or a decimal when it has no imaginary part
+    pub fn complex(re: f32, im: f32) -> Numeric {
+        match im == 0.0 {
+            true => Numeric::Decimal(re),
+            false => Numeric::Complex { re, im },
+        }
+    }
 }
 
 impl From<Numeric> for f32 {
@@ -29,6 +60,8 @@ impl From<Numeric> for f32 {
             Numeric::Integer(i) => i as f32,
             Numeric::Decimal(d) => d,
             Numeric::Fraction(n, d) => n as f32 / d as f32,
+            // only the real part of a complex number
+            Numeric::Complex { re, im: _ } => re,
         }
     }
 }
@@ -45,6 +78,7 @@ impl From<Numeric> for i32 {
             Numeric::Integer(i) => i,
             Numeric::Decimal(d) => d as i32,
             Numeric::Fraction(n, d) => n / d,
+            Numeric::Complex { re, im: _ } => re as i32,
         }
     }
 }
@@ -77,6 +111,12 @@ impl PartialOrd for Numeric {
             (Numeric::Fraction(n1, d1), Numeric::Fraction(n2, d2)) => {
                 (*n1 as f32 / *d1 as f32).partial_cmp(&(*n2 as f32 / *d2 as f32))
             }
+            // complex numbers are only ordered when they are real
+            (a, b) => match (a.parts(), b.parts()) {
+                ((re1, 0.0), (re2, 0.0)) => re1.partial_cmp(&re2),
+                _ if a == b => Some(Ordering::Equal),
+                _ => None,
+            },
         }
     }
 }
@@ -93,6 +133,7 @@ impl PartialEq for Numeric {
             (Numeric::Fraction(i, j), Numeric::Integer(k)) => *i == *k * *j,
             (Numeric::Decimal(i), Numeric::Fraction(j, k)) => *i == *j as f32 / *k as f32,
             (Numeric::Fraction(i, j), Numeric::Decimal(k)) => *i as f32 / *j as f32 == *k,
+            (a, b) => a.parts() == b.parts(),
         }
     }
 }
@@ -108,30 +149,78 @@ impl Hash for Numeric {
                 n.hash(state);
                 d.hash(state);
             }
+            Numeric::Complex { re, im } => {
+                re.to_bits().hash(state);
+                im.to_bits().hash(state);
+            }
         }
     }
 }
 
+// the numerator and denominator of a / b + c / d, a / b - c / d and (a / b) * (c / d), or none
+// when either one overflows
+fn fraction_sum(a: i32, b: i32, c: i32, d: i32) -> Option<(i32, i32)> {
+    Some((
+        a.checked_mul(d)?.checked_add(b.checked_mul(c)?)?,
+        b.checked_mul(d)?,
+    ))
+}
+
+fn fraction_difference(a: i32, b: i32, c: i32, d: i32) -> Option<(i32, i32)> {
+    Some((
+        a.checked_mul(d)?.checked_sub(b.checked_mul(c)?)?,
+        b.checked_mul(d)?,
+    ))
+}
+
+fn fraction_product(a: i32, b: i32, c: i32, d: i32) -> Option<(i32, i32)> {
+    Some((a.checked_mul(c)?, b.checked_mul(d)?))
+}
+
+// the fraction when it did not overflow, otherwise op on the decimal values of the operands, the
+// same promotion integers get
+fn fraction_or(
+    parts: Option<(i32, i32)>,
+    l: Numeric,
+    r: Numeric,
+    op: fn(f32, f32) -> f32,
+) -> Numeric {
+    match parts {
+        Some((n, d)) => Numeric::Fraction(n, d),
+        None => Numeric::Decimal(op(f32::from(l), f32::from(r))),
+    }
+}
+
 impl Add for Numeric {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Numeric::Integer(a), Numeric::Integer(b)) => Numeric::Integer(a + b),
+            (Numeric::Integer(a), Numeric::Integer(b)) => a
+                .checked_add(b)
+                .map_or(Numeric::Decimal(a as f32 + b as f32), Numeric::Integer),
             (Numeric::Decimal(a), Numeric::Decimal(b)) => Numeric::Decimal(a + b),
             (Numeric::Integer(a), Numeric::Decimal(b)) => Numeric::Decimal(a as f32 + b),
             (Numeric::Decimal(a), Numeric::Integer(b)) => Numeric::Decimal(a + b as f32),
             (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => {
-                Numeric::Fraction(a * d + b * c, b * d)
+                fraction_or(fraction_sum(a, b, c, d), self, rhs, |x, y| x + y)
+            }
+            (Numeric::Integer(a), Numeric::Fraction(b, c)) => {
+                fraction_or(fraction_sum(a, 1, b, c), self, rhs, |x, y| x + y)
+            }
+            (Numeric::Fraction(a, b), Numeric::Integer(c)) => {
+                fraction_or(fraction_sum(a, b, c, 1), self, rhs, |x, y| x + y)
             }
-            (Numeric::Integer(a), Numeric::Fraction(b, c)) => Numeric::Fraction(a * c + b, c),
-            (Numeric::Fraction(a, b), Numeric::Integer(c)) => Numeric::Fraction(a + b * c, b),
             (Numeric::Decimal(a), Numeric::Fraction(b, c)) => {
                 Numeric::Decimal(a + (b as f32 / c as f32))
             }
             (Numeric::Fraction(a, b), Numeric::Decimal(c)) => {
                 Numeric::Decimal((a as f32 / b as f32) + c)
             }
+            (x, y) => {
+                let ((a, b), (c, d)) = (x.parts(), y.parts());
+                Numeric::complex(a + c, b + d)
+            }
         }
     }
 }
@@ -141,21 +230,31 @@ impl Sub for Numeric {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Numeric::Integer(a), Numeric::Integer(b)) => Numeric::Integer(a - b),
+            (Numeric::Integer(a), Numeric::Integer(b)) => a
+                .checked_sub(b)
+                .map_or(Numeric::Decimal(a as f32 - b as f32), Numeric::Integer),
             (Numeric::Decimal(a), Numeric::Decimal(b)) => Numeric::Decimal(a - b),
             (Numeric::Integer(a), Numeric::Decimal(b)) => Numeric::Decimal(a as f32 - b),
             (Numeric::Decimal(a), Numeric::Integer(b)) => Numeric::Decimal(a - b as f32),
             (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => {
-                Numeric::Fraction(a * d - b * c, b * d)
+                fraction_or(fraction_difference(a, b, c, d), self, rhs, |x, y| x - y)
+            }
+            (Numeric::Integer(a), Numeric::Fraction(b, c)) => {
+                fraction_or(fraction_difference(a, 1, b, c), self, rhs, |x, y| x - y)
+            }
+            (Numeric::Fraction(a, b), Numeric::Integer(c)) => {
+                fraction_or(fraction_difference(a, b, c, 1), self, rhs, |x, y| x - y)
             }
-            (Numeric::Integer(a), Numeric::Fraction(b, c)) => Numeric::Fraction(a * c - b, c),
-            (Numeric::Fraction(a, b), Numeric::Integer(c)) => Numeric::Fraction(a - b * c, b),
             (Numeric::Decimal(a), Numeric::Fraction(b, c)) => {
                 Numeric::Decimal(a - (b as f32 / c as f32))
             }
             (Numeric::Fraction(a, b), Numeric::Decimal(c)) => {
                 Numeric::Decimal((a as f32 / b as f32) - c)
             }
+            (x, y) => {
+                let ((a, b), (c, d)) = (x.parts(), y.parts());
+                Numeric::complex(a - c, b - d)
+            }
         }
     }
 }
@@ -165,19 +264,31 @@ impl Mul for Numeric {
 
     fn mul(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Numeric::Integer(a), Numeric::Integer(b)) => Numeric::Integer(a * b),
+            (Numeric::Integer(a), Numeric::Integer(b)) => a
+                .checked_mul(b)
+                .map_or(Numeric::Decimal(a as f32 * b as f32), Numeric::Integer),
             (Numeric::Decimal(a), Numeric::Decimal(b)) => Numeric::Decimal(a * b),
             (Numeric::Integer(a), Numeric::Decimal(b)) => Numeric::Decimal(a as f32 * b),
             (Numeric::Decimal(a), Numeric::Integer(b)) => Numeric::Decimal(a * b as f32),
-            (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => Numeric::Fraction(a * c, b * d),
-            (Numeric::Integer(a), Numeric::Fraction(b, c)) => Numeric::Fraction(a * b, c),
-            (Numeric::Fraction(a, b), Numeric::Integer(c)) => Numeric::Fraction(a * c, b),
+            (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => {
+                fraction_or(fraction_product(a, b, c, d), self, rhs, |x, y| x * y)
+            }
+            (Numeric::Integer(a), Numeric::Fraction(b, c)) => {
+                fraction_or(fraction_product(a, 1, b, c), self, rhs, |x, y| x * y)
+            }
+            (Numeric::Fraction(a, b), Numeric::Integer(c)) => {
+                fraction_or(fraction_product(a, b, c, 1), self, rhs, |x, y| x * y)
+            }
             (Numeric::Decimal(a), Numeric::Fraction(b, c)) => {
                 Numeric::Decimal(a * (b as f32 / c as f32))
             }
             (Numeric::Fraction(a, b), Numeric::Decimal(c)) => {
                 Numeric::Decimal((a as f32 / b as f32) * c)
             }
+            (x, y) => {
+                let ((a, b), (c, d)) = (x.parts(), y.parts());
+                Numeric::complex(a * c - b * d, a * d + b * c)
+            }
         }
     }
 }
@@ -187,19 +298,36 @@ impl Div for Numeric {
 
     fn div(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Numeric::Integer(a), Numeric::Integer(b)) => Numeric::Fraction(a, b),
+            // exact quotients stay integers, anything else is kept exact as a fraction
+            (Numeric::Integer(a), Numeric::Integer(b)) => {
+                match (a.checked_rem(b), a.checked_div(b)) {
+                    (Some(0), Some(q)) => Numeric::Integer(q),
+                    _ => Numeric::Fraction(a, b),
+                }
+            }
             (Numeric::Decimal(a), Numeric::Decimal(b)) => Numeric::Decimal(a / b),
             (Numeric::Integer(a), Numeric::Decimal(b)) => Numeric::Decimal(a as f32 / b),
             (Numeric::Decimal(a), Numeric::Integer(b)) => Numeric::Decimal(a / b as f32),
-            (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => Numeric::Fraction(a * d, b * c),
-            (Numeric::Integer(a), Numeric::Fraction(b, c)) => Numeric::Fraction(a * c, b),
-            (Numeric::Fraction(a, b), Numeric::Integer(c)) => Numeric::Fraction(a, b * c),
+            (Numeric::Fraction(a, b), Numeric::Fraction(c, d)) => {
+                fraction_or(fraction_product(a, b, d, c), self, rhs, |x, y| x / y)
+            }
+            (Numeric::Integer(a), Numeric::Fraction(b, c)) => {
+                fraction_or(fraction_product(a, 1, c, b), self, rhs, |x, y| x / y)
+            }
+            (Numeric::Fraction(a, b), Numeric::Integer(c)) => {
+                fraction_or(fraction_product(a, b, 1, c), self, rhs, |x, y| x / y)
+            }
             (Numeric::Decimal(a), Numeric::Fraction(b, c)) => {
                 Numeric::Decimal(a / (b as f32 / c as f32))
             }
             (Numeric::Fraction(a, b), Numeric::Decimal(c)) => {
                 Numeric::Decimal((a as f32 / b as f32) / c)
             }
+            (x, y) => {
+                let ((a, b), (c, d)) = (x.parts(), y.parts());
+                let norm = c * c + d * d;
+                Numeric::complex((a * c + b * d) / norm, (b * c - a * d) / norm)
+            }
         }
     }
 }
@@ -209,9 +337,16 @@ impl Neg for Numeric {
 
     fn neg(self) -> Self::Output {
         match self {
-            Numeric::Integer(a) => Numeric::Integer(-a),
+            Numeric::Integer(a) => a
+                .checked_neg()
+                .map_or(Numeric::Decimal(-(a as f32)), Numeric::Integer),
             Numeric::Decimal(a) => Numeric::Decimal(-a),
-            Numeric::Fraction(a, b) => Numeric::Fraction(-a, b),
+            Numeric::Fraction(a, b) => a
+                .checked_neg()
+                .map_or(Numeric::Decimal(-(a as f32 / b as f32)), |a| {
+                    Numeric::Fraction(a, b)
+                }),
+            Numeric::Complex { re, im } => Numeric::Complex { re: -re, im: -im },
         }
     }
 }
@@ -220,14 +355,25 @@ impl fmt::Display for Numeric {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Numeric::Integer(i) => write!(f, "{}", i),
-            Numeric::Decimal(d) => write!(f, "{}", d),
+            Numeric::Decimal(d) => {
+                write!(f, "{}", format_decimal_point(*d, Some(DISPLAY_PRECISION)))
+            }
             Numeric::Fraction(r1, r2) => write!(f, "({} / {})", r1, r2),
+            Numeric::Complex { re, im } => {
+                let re = format_decimal(*re, Some(DISPLAY_PRECISION));
+                match (re == "0", imaginary(*im, Some(DISPLAY_PRECISION))) {
+                    (true, im) => write!(f, "{}", im),
+                    (false, im) if im.starts_with('-') => write!(f, "({} - {})", re, &im[1..]),
+                    (false, im) => write!(f, "({} + {})", re, im),
+                }
+            }
         }
     }
 }
 
 // Escape: indicates where something in an expression should be replaced
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Escape {
     Atom,
     Function,
@@ -236,6 +382,86 @@ pub enum Escape {
     Everything,
 }
 
+// Link: how a member of a system is written after the ones before it
+pub(crate) enum Link<'a> {
+    // a relation continuing from the right side of the one before it, only its comparison and
+    // right side are written, 0 < x and x < 1 is written 0 < x < 1
+    Chained(Comparison, &'a Expression),
+    // anything else, written in full after a conjunction
+    Joined(&'a Expression),
+}
+
+// how each member of a system is written
+pub(crate) fn chain_links(members: &[Box<Expression>]) -> Vec<Link<'_>> {
+    let mut previous: Option<&Expression> = None;
+    members
+        .iter()
+        .map(|e| {
+            let link = match (previous, &**e) {
+                (Some(p), Expression::Relation(op, l, r)) if **l == *p => Link::Chained(*op, r),
+                (_, e) => Link::Joined(e),
+            };
+            previous = match &**e {
+                Expression::Relation(_, _, r) => Some(&**r),
+                _ => None,
+            };
+            link
+        })
+        .collect()
+}
+
+// the number of significant digits decimals are displayed with
+pub(crate) const DISPLAY_PRECISION: usize = 6;
+
+// a decimal rounded to the given number of significant digits without trailing zeros, 0.30000001
+// is 0.3 to 6 digits, one with more digits before the decimal point than that is written in
+// scientific notation, 123456789 is 1.23457e8
+// without a precision it is written with as many digits as it takes to read it back exactly
+pub(crate) fn format_decimal(d: f32, precision: Option<usize>) -> String {
+    let significant = match precision {
+        Some(significant) if d.is_finite() && d != 0.0 => significant.max(1) as i32,
+        _ => return format!("{}", d),
+    };
+
+    let magnitude = libm::floorf(libm::log10f(libm::fabsf(d))) as i32;
+    match significant - 1 - magnitude {
+        decimals if decimals > 0 => {
+            let rounded = format!("{:.*}", decimals as usize, d);
+            String::from(rounded.trim_end_matches('0').trim_end_matches('.'))
+        }
+        0 => format!("{:.0}", d),
+        _ => {
+            let scientific = format!("{:.*e}", significant as usize - 1, d);
+            match scientific.split_once('e') {
+                Some((mantissa, exponent)) if mantissa.contains('.') => format!(
+                    "{}e{}",
+                    mantissa.trim_end_matches('0').trim_end_matches('.'),
+                    exponent
+                ),
+                _ => scientific,
+            }
+        }
+    }
+}
+
+// format_decimal, keeping a decimal point on whole numbers so they still read as decimals, 2.0
+pub(crate) fn format_decimal_point(d: f32, precision: Option<usize>) -> String {
+    let mut digits = format_decimal(d, precision);
+    if d.is_finite() && !digits.contains(['.', 'e']) {
+        digits.push_str(".0");
+    }
+    digits
+}
+
+// the imaginary part of a complex number with its unit, i, -i or 2.5i
+pub(crate) fn imaginary(im: f32, precision: Option<usize>) -> String {
+    match im {
+        1.0 => String::from("i"),
+        -1.0 => String::from("-i"),
+        im => format!("{}i", format_decimal(im, precision)),
+    }
+}
+
 impl fmt::Display for Escape {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -248,13 +474,40 @@ impl fmt::Display for Escape {
     }
 }
 
+// Comparison: how the two sides of a relation compare
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Comparison::Equal => write!(f, "="),
+            Comparison::NotEqual => write!(f, "!="),
+            Comparison::Less => write!(f, "<"),
+            Comparison::LessEqual => write!(f, "<="),
+            Comparison::Greater => write!(f, ">"),
+            Comparison::GreaterEqual => write!(f, ">="),
+        }
+    }
+}
+
 // Atom: the smallest unit of an expression
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Atom {
     Numeric(Numeric),
     Variable(char),
     Escape(Escape, u8),
     Error(crate::Error),
+    Boolean(bool),
 }
 
 impl fmt::Display for Atom {
@@ -264,12 +517,15 @@ impl fmt::Display for Atom {
             Atom::Variable(v) => write!(f, "{}", v),
             Atom::Escape(e, n) => write!(f, "_{}{}", e, n),
             Atom::Error(e) => write!(f, "{}", e),
+            Atom::Boolean(b) => write!(f, "{}", b),
         }
     }
 }
 
 // Expression: a tree representing a mathematical expression
+// serialized externally tagged like Numeric, `{"Add":[{"Atom":{"Variable":"x"}},...]}`
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     // atoms
     Atom(Atom),
@@ -300,6 +556,33 @@ pub enum Expression {
         backing: Vec<Box<Self>>,
         shape: (u8, u8),
     },
+
+    // the integral of the integrand with respect to var, definite when it has both bounds
+    Integral {
+        integrand: Box<Self>,
+        var: char,
+        lower: Option<Box<Self>>,
+        upper: Option<Box<Self>>,
+    },
+    // the sum of the body for every integer value of index from lower to upper inclusive
+    Sum {
+        index: char,
+        lower: Box<Self>,
+        upper: Box<Self>,
+        body: Box<Self>,
+    },
+
+    // relations: lhs compared to rhs, and a system of relations which all hold, a chain such as
+    // 0 < x < 1 is the system of 0 < x and x < 1
+    Relation(Comparison, Box<Self>, Box<Self>),
+    And(Vec<Box<Self>>),
+    // logical connectives over relations and booleans, a disjunction holds when any member does
+    Or(Vec<Box<Self>>),
+    Not(Box<Self>),
+
+    // a piecewise definition, (value, condition) branches where the first branch whose condition
+    // holds gives the value
+    Piecewise(Vec<(Self, Self)>),
 }
 
 impl Expression {
@@ -534,10 +817,7 @@ impl Expression {
                     let mut level = 0;
 
                     for (arg1, arg2) in a1.iter().zip(a2.iter()) {
-                        match arg1.level_eq(arg2, map) {
-                            Some(l) => level += l,
-                            None => return None,
-                        };
+                        level += arg1.level_eq(arg2, map)?;
                     }
 
                     Some(level)
@@ -559,10 +839,7 @@ impl Expression {
                     let mut level = 0;
 
                     for (expr1, expr2) in b1.iter().zip(b2.iter()) {
-                        match expr1.level_eq(expr2, map) {
-                            Some(l) => level += l,
-                            None => return None,
-                        };
+                        level += expr1.level_eq(expr2, map)?;
                     }
 
                     Some(level)
@@ -584,10 +861,7 @@ impl Expression {
                     let mut level = 0;
 
                     for (expr1, expr2) in b1.iter().zip(b2.iter()) {
-                        match expr1.level_eq(expr2, map) {
-                            Some(l) => level += l,
-                            None => return None,
-                        };
+                        level += expr1.level_eq(expr2, map)?;
                     }
 
                     Some(level)
@@ -598,6 +872,20 @@ impl Expression {
             (Expression::Negate(e1), Expression::Negate(e2))
             | (Expression::Factorial(e1), Expression::Factorial(e2))
             | (Expression::Percent(e1), Expression::Percent(e2)) => e1.level_eq(e2, map),
+            (Expression::Piecewise(b1), Expression::Piecewise(b2)) => {
+                if b1.len() == b2.len() {
+                    let mut level = 0;
+
+                    for ((v1, c1), (v2, c2)) in b1.iter().zip(b2.iter()) {
+                        level += v1.level_eq(v2, map)?;
+                        level += c1.level_eq(c2, map)?;
+                    }
+
+                    Some(level)
+                } else {
+                    None
+                }
+            }
             (Expression::Add(e11, e12), Expression::Add(e21, e22))
             | (Expression::Subtract(e11, e12), Expression::Subtract(e21, e22))
             | (Expression::Multiply(e11, e12), Expression::Multiply(e21, e22))
@@ -694,6 +982,25 @@ impl Expression {
                         Box::new(e1.clone().conversion()(map).0),
                         Box::new(e2.clone().conversion()(map).0),
                     ),
+                    e @ Expression::Integral {
+                        integrand: _,
+                        var: _,
+                        lower: _,
+                        upper: _,
+                    } => e.map_children(|c| c.clone().conversion()(map).0),
+                    e @ Expression::Sum {
+                        index: _,
+                        lower: _,
+                        upper: _,
+                        body: _,
+                    } => e.map_children(|c| c.clone().conversion()(map).0),
+                    e @ (Expression::Relation(_, _, _)
+                    | Expression::And(_)
+                    | Expression::Or(_)
+                    | Expression::Not(_)
+                    | Expression::Piecewise(_)) => {
+                        e.map_children(|c| c.clone().conversion()(map).0)
+                    }
                 },
                 true,
             )
@@ -775,6 +1082,18 @@ impl Expression {
             (Expression::Negate(e1), Expression::Negate(e2))
             | (Expression::Factorial(e1), Expression::Factorial(e2))
             | (Expression::Percent(e1), Expression::Percent(e2)) => e1.extract_arguments(e2, map),
+            (Expression::Piecewise(b1), Expression::Piecewise(b2)) => {
+                if b1.len() == b2.len() {
+                    let mut map_n = map;
+                    for ((v1, c1), (v2, c2)) in b1.iter().zip(b2.iter()) {
+                        map_n = v1.extract_arguments(v2, map_n);
+                        map_n = c1.extract_arguments(c2, map_n);
+                    }
+                    map_n
+                } else {
+                    map
+                }
+            }
             (Expression::Add(e11, e12), Expression::Add(e21, e22))
             | (Expression::Subtract(e11, e12), Expression::Subtract(e21, e22))
             | (Expression::Multiply(e11, e12), Expression::Multiply(e21, e22))
@@ -787,6 +1106,733 @@ impl Expression {
             _ => map,
         }
     }
+
+    // the direct sub-expressions of this node, from left to right
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Atom(_) => Vec::new(),
+
+            Expression::Negate(e)
+            | Expression::Factorial(e)
+            | Expression::Percent(e)
+            | Expression::Not(e) => vec![e],
+
+            Expression::Add(e1, e2)
+            | Expression::Subtract(e1, e2)
+            | Expression::Multiply(e1, e2)
+            | Expression::Divide(e1, e2)
+            | Expression::Power(e1, e2)
+            | Expression::Modulus(e1, e2)
+            | Expression::Relation(_, e1, e2) => vec![e1, e2],
+
+            Expression::Function { name: _, args: es }
+            | Expression::And(es)
+            | Expression::Or(es)
+            | Expression::Vector {
+                backing: es,
+                size: _,
+            }
+            | Expression::Matrix {
+                backing: es,
+                shape: _,
+            } => es.iter().map(|e| &**e).collect(),
+
+            // the integrand, then the bounds it has
+            Expression::Integral {
+                integrand,
+                var: _,
+                lower,
+                upper,
+            } => iter::once(&**integrand)
+                .chain(lower.as_deref())
+                .chain(upper.as_deref())
+                .collect(),
+            // the bounds, then the body
+            Expression::Sum {
+                index: _,
+                lower,
+                upper,
+                body,
+            } => vec![lower, upper, body],
+
+            // the value of every branch followed by its condition
+            Expression::Piecewise(branches) => branches
+                .iter()
+                .flat_map(|(value, condition)| [value, condition])
+                .collect(),
+        }
+    }
+
+    // rebuilds this node with f applied to each of its direct sub-expressions
+    pub(crate) fn map_children(&self, mut f: impl FnMut(&Expression) -> Expression) -> Expression {
+        let mut f = |e: &Expression| Box::new(f(e));
+        match self {
+            Expression::Atom(a) => Expression::Atom(*a),
+
+            Expression::Negate(e) => Expression::Negate(f(e)),
+            Expression::Factorial(e) => Expression::Factorial(f(e)),
+            Expression::Percent(e) => Expression::Percent(f(e)),
+
+            Expression::Add(e1, e2) => Expression::Add(f(e1), f(e2)),
+            Expression::Subtract(e1, e2) => Expression::Subtract(f(e1), f(e2)),
+            Expression::Multiply(e1, e2) => Expression::Multiply(f(e1), f(e2)),
+            Expression::Divide(e1, e2) => Expression::Divide(f(e1), f(e2)),
+            Expression::Power(e1, e2) => Expression::Power(f(e1), f(e2)),
+            Expression::Modulus(e1, e2) => Expression::Modulus(f(e1), f(e2)),
+
+            Expression::Function { name, args } => Expression::Function {
+                name: name.clone(),
+                args: args.iter().map(|e| f(e)).collect(),
+            },
+            Expression::Vector { backing, size } => Expression::Vector {
+                backing: backing.iter().map(|e| f(e)).collect(),
+                size: *size,
+            },
+            Expression::Matrix { backing, shape } => Expression::Matrix {
+                backing: backing.iter().map(|e| f(e)).collect(),
+                shape: *shape,
+            },
+            Expression::Integral {
+                integrand,
+                var,
+                lower,
+                upper,
+            } => Expression::Integral {
+                integrand: f(integrand),
+                var: *var,
+                lower: lower.as_deref().map(&mut f),
+                upper: upper.as_deref().map(&mut f),
+            },
+            Expression::Sum {
+                index,
+                lower,
+                upper,
+                body,
+            } => Expression::Sum {
+                index: *index,
+                lower: f(lower),
+                upper: f(upper),
+                body: f(body),
+            },
+
+            Expression::Relation(op, e1, e2) => Expression::Relation(*op, f(e1), f(e2)),
+            Expression::And(es) => Expression::And(es.iter().map(|e| f(e)).collect()),
+            Expression::Or(es) => Expression::Or(es.iter().map(|e| f(e)).collect()),
+            Expression::Not(e) => Expression::Not(f(e)),
+
+            Expression::Piecewise(branches) => Expression::Piecewise(
+                branches
+                    .iter()
+                    .map(|(value, condition)| (*f(value), *f(condition)))
+                    .collect(),
+            ),
+        }
+    }
+
+    // map_children with a fallible f, the first error stops any further calls and is returned
+    pub(crate) fn try_map_children<E>(
+        &self,
+        mut f: impl FnMut(&Expression) -> Result<Expression, E>,
+    ) -> Result<Expression, E> {
+        let mut error = None;
+        let mapped = self.map_children(|e| match error {
+            Some(_) => e.clone(),
+            None => f(e).unwrap_or_else(|err| {
+                error = Some(err);
+                e.clone()
+            }),
+        });
+        match error {
+            Some(err) => Err(err),
+            None => Ok(mapped),
+        }
+    }
+
+    // whether var occurs anywhere in the expression
+    pub fn contains_variable(&self, var: char) -> bool {
+        self.fold(&mut |e, children: Vec<bool>| {
+            matches!(e, Expression::Atom(Atom::Variable(v)) if *v == var)
+                || children.contains(&true)
+        })
+    }
+
+    // replaces every variable found in the map by its value in a single pass, so the
+    // substitutions happen simultaneously and never see each other's results (x -> y, y -> x swaps)
+    pub fn substitute_many(&self, values: &BTreeMap<char, Expression>) -> Expression {
+        match self {
+            Expression::Atom(Atom::Variable(v)) => match values.get(v) {
+                Some(value) => value.clone(),
+                None => self.clone(),
+            },
+            // the variable of an integral and the index of a sum are bound inside the integrand and
+            // the body, only the bounds see the outer value
+            Expression::Integral {
+                integrand,
+                var,
+                lower,
+                upper,
+            } if values.contains_key(var) => {
+                let mut inner = values.clone();
+                inner.remove(var);
+                Expression::Integral {
+                    integrand: Box::new(integrand.substitute_many(&inner)),
+                    var: *var,
+                    lower: lower.as_ref().map(|e| Box::new(e.substitute_many(values))),
+                    upper: upper.as_ref().map(|e| Box::new(e.substitute_many(values))),
+                }
+            }
+            Expression::Sum {
+                index,
+                lower,
+                upper,
+                body,
+            } if values.contains_key(index) => {
+                let mut inner = values.clone();
+                inner.remove(index);
+                Expression::Sum {
+                    index: *index,
+                    lower: Box::new(lower.substitute_many(values)),
+                    upper: Box::new(upper.substitute_many(values)),
+                    body: Box::new(body.substitute_many(&inner)),
+                }
+            }
+            e => e.map_children(|c| c.substitute_many(values)),
+        }
+    }
+
+    // folds the expression tree in post-order: the children of a node are folded from left to
+    // right, then f is called on the node itself with the results of its children
+    pub fn fold<T>(&self, f: &mut impl FnMut(&Expression, Vec<T>) -> T) -> T {
+        let children = self.children().into_iter().map(|e| e.fold(f)).collect();
+        f(self, children)
+    }
+
+    // every node of the tree in pre-order, starting with the expression itself: a node comes before
+    // its children, which are visited from left to right
+    pub fn descendants(&self) -> impl Iterator<Item = &Expression> {
+        let mut stack = vec![self];
+        iter::from_fn(move || {
+            let e = stack.pop()?;
+            stack.extend(e.children().into_iter().rev());
+            Some(e)
+        })
+    }
+
+    // the number of nodes in the tree, counting every atom and operator
+    pub fn node_count(&self) -> usize {
+        self.fold(&mut |_, children: Vec<usize>| 1 + children.iter().sum::<usize>())
+    }
+
+    // the number of nodes on the longest path from the root to a leaf, so an atom has depth 1
+    pub fn depth(&self) -> usize {
+        self.fold(&mut |_, children: Vec<usize>| 1 + children.into_iter().max().unwrap_or(0))
+    }
+
+    // a total order used to build canonical forms, independent of the PartialOrd used for rule
+    // matching (where atoms compare equal to anything so escapes can match)
+    // numerics < variables < escapes < errors < booleans < functions < unary operators < binary
+    // operators < vectors < matrices < integrals < relations < systems < piecewise definitions
+    // < disjunctions < negations, nodes of the same kind are compared by their contents
+    pub fn canonical_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Expression::Atom(a1), Expression::Atom(a2)) => match (a1, a2) {
+                (Atom::Numeric(n1), Atom::Numeric(n2)) => {
+                    n1.partial_cmp(n2).unwrap_or(Ordering::Equal)
+                }
+                (Atom::Variable(v1), Atom::Variable(v2)) => v1.cmp(v2),
+                (Atom::Escape(e1, n1), Atom::Escape(e2, n2)) => e1
+                    .partial_cmp(e2)
+                    .unwrap_or(Ordering::Equal)
+                    .then(n1.cmp(n2)),
+                (Atom::Error(e1), Atom::Error(e2)) => e1.cmp(e2),
+                (Atom::Boolean(b1), Atom::Boolean(b2)) => b1.cmp(b2),
+                (a1, a2) => atom_rank(a1).cmp(&atom_rank(a2)),
+            },
+            (
+                Expression::Function { name: n1, args: a1 },
+                Expression::Function { name: n2, args: a2 },
+            ) => n1.cmp(n2).then_with(|| canonical_cmp_all(a1, a2)),
+            (
+                Expression::Vector {
+                    backing: b1,
+                    size: _,
+                },
+                Expression::Vector {
+                    backing: b2,
+                    size: _,
+                },
+            ) => canonical_cmp_all(b1, b2),
+            (
+                Expression::Matrix {
+                    backing: b1,
+                    shape: s1,
+                },
+                Expression::Matrix {
+                    backing: b2,
+                    shape: s2,
+                },
+            ) => s1.cmp(s2).then_with(|| canonical_cmp_all(b1, b2)),
+            (
+                Expression::Integral {
+                    integrand: _,
+                    var: v1,
+                    lower: _,
+                    upper: _,
+                },
+                Expression::Integral {
+                    integrand: _,
+                    var: v2,
+                    lower: _,
+                    upper: _,
+                },
+            ) => v1
+                .cmp(v2)
+                .then_with(|| canonical_cmp_all(&self.children(), &other.children())),
+            (
+                Expression::Sum {
+                    index: i1,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+                Expression::Sum {
+                    index: i2,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+            ) => i1
+                .cmp(i2)
+                .then_with(|| canonical_cmp_all(&self.children(), &other.children())),
+            (Expression::Relation(o1, a1, a2), Expression::Relation(o2, b1, b2)) => o1
+                .cmp(o2)
+                .then_with(|| a1.canonical_cmp(b1))
+                .then_with(|| a2.canonical_cmp(b2)),
+            (Expression::And(es1), Expression::And(es2))
+            | (Expression::Or(es1), Expression::Or(es2)) => canonical_cmp_all(es1, es2),
+            (Expression::Not(e1), Expression::Not(e2)) => e1.canonical_cmp(e2),
+            (Expression::Piecewise(_), Expression::Piecewise(_)) => {
+                canonical_cmp_all(&self.children(), &other.children())
+            }
+            (Expression::Negate(e1), Expression::Negate(e2))
+            | (Expression::Factorial(e1), Expression::Factorial(e2))
+            | (Expression::Percent(e1), Expression::Percent(e2)) => e1.canonical_cmp(e2),
+            (Expression::Add(a1, a2), Expression::Add(b1, b2))
+            | (Expression::Subtract(a1, a2), Expression::Subtract(b1, b2))
+            | (Expression::Multiply(a1, a2), Expression::Multiply(b1, b2))
+            | (Expression::Divide(a1, a2), Expression::Divide(b1, b2))
+            | (Expression::Power(a1, a2), Expression::Power(b1, b2))
+            | (Expression::Modulus(a1, a2), Expression::Modulus(b1, b2)) => {
+                a1.canonical_cmp(b1).then_with(|| a2.canonical_cmp(b2))
+            }
+            (e1, e2) => expression_rank(e1).cmp(&expression_rank(e2)),
+        }
+    }
+
+    // sorts the operands of commutative operators (chains of Add and Multiply) by canonical_cmp,
+    // so that expressions which only differ by the order of their operands become equal
+    // factors of a product sharing a non numeric base are collected into one power (x * x^2 -> x^3)
+    // products containing vector or matrix literals are left in order, as they do not commute
+    pub fn canonicalize(&self) -> Expression {
+        match self {
+            Expression::Add(_, _) => {
+                let mut terms: Vec<Expression> =
+                    flatten_add(self).iter().map(|e| e.canonicalize()).collect();
+                terms.sort_by(|a, b| a.canonical_cmp(b));
+                rebuild_add(terms)
+            }
+            Expression::Multiply(_, _) => {
+                let mut factors: Vec<Expression> =
+                    flatten_mul(self).iter().map(|e| e.canonicalize()).collect();
+                if !factors.iter().any(|e| {
+                    matches!(
+                        e,
+                        Expression::Vector {
+                            backing: _,
+                            size: _
+                        } | Expression::Matrix {
+                            backing: _,
+                            shape: _
+                        }
+                    )
+                }) {
+                    factors = collect_powers(factors);
+                    factors.sort_by(|a, b| a.canonical_cmp(b));
+                }
+                rebuild_mul(factors)
+            }
+
+            Expression::Atom(a) => Expression::Atom(*a),
+
+            Expression::Negate(e) => Expression::Negate(Box::new(e.canonicalize())),
+            Expression::Factorial(e) => Expression::Factorial(Box::new(e.canonicalize())),
+            Expression::Percent(e) => Expression::Percent(Box::new(e.canonicalize())),
+
+            Expression::Subtract(l, r) => {
+                Expression::Subtract(Box::new(l.canonicalize()), Box::new(r.canonicalize()))
+            }
+            Expression::Divide(l, r) => {
+                Expression::Divide(Box::new(l.canonicalize()), Box::new(r.canonicalize()))
+            }
+            Expression::Power(l, r) => {
+                Expression::Power(Box::new(l.canonicalize()), Box::new(r.canonicalize()))
+            }
+            Expression::Modulus(l, r) => {
+                Expression::Modulus(Box::new(l.canonicalize()), Box::new(r.canonicalize()))
+            }
+
+            Expression::Function { name, args } => Expression::Function {
+                name: name.clone(),
+                args: args.iter().map(|e| Box::new(e.canonicalize())).collect(),
+            },
+            Expression::Vector { backing, size } => Expression::Vector {
+                backing: backing.iter().map(|e| Box::new(e.canonicalize())).collect(),
+                size: *size,
+            },
+            Expression::Matrix { backing, shape } => Expression::Matrix {
+                backing: backing.iter().map(|e| Box::new(e.canonicalize())).collect(),
+                shape: *shape,
+            },
+            e @ Expression::Integral {
+                integrand: _,
+                var: _,
+                lower: _,
+                upper: _,
+            } => e.map_children(Expression::canonicalize),
+            e @ Expression::Sum {
+                index: _,
+                lower: _,
+                upper: _,
+                body: _,
+            } => e.map_children(Expression::canonicalize),
+            e @ (Expression::Relation(_, _, _)
+            | Expression::And(_)
+            | Expression::Or(_)
+            | Expression::Not(_)
+            | Expression::Piecewise(_)) => e.map_children(Expression::canonicalize),
+        }
+    }
+}
+
+fn atom_rank(atom: &Atom) -> u8 {
+    match atom {
+        Atom::Numeric(_) => 0,
+        Atom::Variable(_) => 1,
+        Atom::Escape(_, _) => 2,
+        Atom::Error(_) => 3,
+        Atom::Boolean(_) => 4,
+    }
+}
+
+fn expression_rank(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Atom(_) => 0,
+        Expression::Function { name: _, args: _ } => 1,
+        Expression::Negate(_) => 2,
+        Expression::Factorial(_) => 3,
+        Expression::Percent(_) => 4,
+        Expression::Add(_, _) => 5,
+        Expression::Subtract(_, _) => 6,
+        Expression::Multiply(_, _) => 7,
+        Expression::Divide(_, _) => 8,
+        Expression::Power(_, _) => 9,
+        Expression::Modulus(_, _) => 10,
+        Expression::Vector {
+            backing: _,
+            size: _,
+        } => 11,
+        Expression::Matrix {
+            backing: _,
+            shape: _,
+        } => 12,
+        Expression::Integral {
+            integrand: _,
+            var: _,
+            lower: _,
+            upper: _,
+        } => 13,
+        Expression::Relation(_, _, _) => 14,
+        Expression::And(_) => 15,
+        Expression::Piecewise(_) => 16,
+        Expression::Or(_) => 17,
+        Expression::Not(_) => 18,
+        Expression::Sum {
+            index: _,
+            lower: _,
+            upper: _,
+            body: _,
+        } => 19,
+    }
+}
+
+// how tightly the parser binds the operator at the root of an expression, an operand needs
+// parentheses when it binds looser than its operator: relations, logical connectives and
+// piecewise definitions < addition, subtraction, integrals and sums < multiplication, division and
+// modulus < negation and logical negation < factorials and percentages < powers < everything else
+// fractions and negative numbers are written with an operator, so they bind like it
+pub fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Relation(_, _, _)
+        | Expression::And(_)
+        | Expression::Or(_)
+        | Expression::Piecewise(_) => 0,
+        Expression::Add(_, _) | Expression::Subtract(_, _) => 1,
+        // the integrand and the body reach out to the end of the expression
+        Expression::Integral {
+            integrand: _,
+            var: _,
+            lower: _,
+            upper: _,
+        }
+        | Expression::Sum {
+            index: _,
+            lower: _,
+            upper: _,
+            body: _,
+        } => 1,
+        Expression::Multiply(_, _) | Expression::Divide(_, _) | Expression::Modulus(_, _) => 2,
+        Expression::Atom(Atom::Numeric(Numeric::Fraction(_, _))) => 2,
+        Expression::Negate(_) | Expression::Not(_) => 3,
+        Expression::Atom(Atom::Numeric(n)) if f32::from(*n) < 0.0 => 3,
+        Expression::Factorial(_) | Expression::Percent(_) => 4,
+        Expression::Power(_, _) => 5,
+        Expression::Atom(_)
+        | Expression::Function { name: _, args: _ }
+        | Expression::Vector {
+            backing: _,
+            size: _,
+        }
+        | Expression::Matrix {
+            backing: _,
+            shape: _,
+        } => 6,
+    }
+}
+
+// whether a chain of the operator at the root of an expression groups from the right, only powers
+// do (2^2^3 is 2^(2^3)), every other binary operator groups from the left (8-4-2 is (8-4)-2)
+pub fn is_right_associative(expr: &Expression) -> bool {
+    matches!(expr, Expression::Power(_, _))
+}
+
+// whether two expressions are equal up to the order and grouping of sums and products, x + y and
+// y + x, or (a * b) * c and a * (b * c), unlike ==, which compares the trees as they are
+pub fn equivalent(a: &Expression, b: &Expression) -> bool {
+    a.canonicalize() == b.canonicalize()
+}
+
+// replaces every variable bound in the map by its value, like Expression::substitute_many this is
+// a single pass over the original tree, so a replacement is never substituted into again and
+// {x -> y, y -> x} swaps the two rather than turning both into the same variable
+pub fn substitute_all(expr: &Expression, bindings: &BTreeMap<char, Expression>) -> Expression {
+    expr.substitute_many(bindings)
+}
+
+fn canonical_cmp_all<E: Deref<Target = Expression>>(a: &[E], b: &[E]) -> Ordering {
+    a.iter()
+        .zip(b.iter())
+        .map(|(e1, e2)| e1.canonical_cmp(e2))
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+// the terms of a maximal chain of additions, left to right, however it is grouped
+// a + (b + c) and (a + b) + c both give [a, b, c], anything other than a sum is a single term
+pub fn flatten_add(expr: &Expression) -> Vec<Expression> {
+    let mut terms = Vec::new();
+    collect_operands(expr, &mut terms, &|e| match e {
+        Expression::Add(l, r) => Some((l, r)),
+        _ => None,
+    });
+    terms.into_iter().cloned().collect()
+}
+
+// the factors of a maximal chain of multiplications, left to right, however it is grouped
+pub fn flatten_mul(expr: &Expression) -> Vec<Expression> {
+    let mut factors = Vec::new();
+    collect_operands(expr, &mut factors, &|e| match e {
+        Expression::Multiply(l, r) => Some((l, r)),
+        _ => None,
+    });
+    factors.into_iter().cloned().collect()
+}
+
+// the left associative sum of the terms, the empty sum is 0
+pub fn rebuild_add(terms: Vec<Expression>) -> Expression {
+    match terms.is_empty() {
+        true => Expression::Atom(Atom::Numeric(Numeric::Integer(0))),
+        false => rebuild_operands(terms, Expression::Add),
+    }
+}
+
+// the left associative product of the factors, the empty product is 1
+pub fn rebuild_mul(factors: Vec<Expression>) -> Expression {
+    match factors.is_empty() {
+        true => Expression::Atom(Atom::Numeric(Numeric::Integer(1))),
+        false => rebuild_operands(factors, Expression::Multiply),
+    }
+}
+
+// collects the operands of a maximal chain of one binary operator, left to right
+fn collect_operands<'a>(
+    expr: &'a Expression,
+    operands: &mut Vec<&'a Expression>,
+    split: &dyn Fn(&'a Expression) -> Option<(&'a Expression, &'a Expression)>,
+) {
+    match split(expr) {
+        Some((l, r)) => {
+            collect_operands(l, operands, split);
+            collect_operands(r, operands, split);
+        }
+        None => operands.push(expr),
+    }
+}
+
+// splits a factor into its base and exponent, treating a non power as being raised to 1
+fn split_power(expr: &Expression) -> (&Expression, Expression) {
+    match expr {
+        Expression::Power(base, exponent) => (base, (**exponent).clone()),
+        e => (e, Expression::Atom(Atom::Numeric(Numeric::Integer(1)))),
+    }
+}
+
+// merges the factors of a product which share a non numeric base by adding their exponents
+fn collect_powers(factors: Vec<Expression>) -> Vec<Expression> {
+    let mut collected: Vec<Expression> = Vec::with_capacity(factors.len());
+
+    for factor in factors {
+        let (base, exponent) = split_power(&factor);
+        if matches!(base, Expression::Atom(Atom::Numeric(_))) {
+            collected.push(factor);
+            continue;
+        }
+
+        match collected.iter().position(|e| split_power(e).0 == base) {
+            Some(i) => {
+                let exponent = match (split_power(&collected[i]).1, exponent) {
+                    (Expression::Atom(Atom::Numeric(n1)), Expression::Atom(Atom::Numeric(n2))) => {
+                        Expression::Atom(Atom::Numeric(n1 + n2))
+                    }
+                    (e1, e2) => Expression::Add(Box::new(e1), Box::new(e2)).canonicalize(),
+                };
+                collected[i] = Expression::Power(Box::new(base.clone()), Box::new(exponent));
+            }
+            None => collected.push(factor),
+        }
+    }
+
+    collected
+}
+
+// rebuilds a left associative chain of one binary operator from its operands
+fn rebuild_operands(
+    operands: Vec<Expression>,
+    op: fn(Box<Expression>, Box<Expression>) -> Expression,
+) -> Expression {
+    let mut operands = operands.into_iter();
+    let first = operands
+        .next()
+        .expect("an operator chain has at least one operand");
+    operands.fold(first, |acc, e| op(Box::new(acc), Box::new(e)))
+}
+
+// constructors which do the boxing, so trees can be written out in rust
+// Expression::int(2) * Expression::var('x') + Expression::int(1) is 2 * x + 1
+// add, sub, mul, div and neg match the operator traits below, without needing them in scope
+#[allow(clippy::should_implement_trait)]
+impl Expression {
+    pub fn var(v: char) -> Expression {
+        Expression::Atom(Atom::Variable(v))
+    }
+
+    pub fn int(i: i32) -> Expression {
+        Expression::Atom(Atom::Numeric(Numeric::Integer(i)))
+    }
+
+    pub fn decimal(d: f32) -> Expression {
+        Expression::Atom(Atom::Numeric(Numeric::Decimal(d)))
+    }
+
+    pub fn add(l: Expression, r: Expression) -> Expression {
+        Expression::Add(Box::new(l), Box::new(r))
+    }
+
+    pub fn sub(l: Expression, r: Expression) -> Expression {
+        Expression::Subtract(Box::new(l), Box::new(r))
+    }
+
+    pub fn mul(l: Expression, r: Expression) -> Expression {
+        Expression::Multiply(Box::new(l), Box::new(r))
+    }
+
+    pub fn div(l: Expression, r: Expression) -> Expression {
+        Expression::Divide(Box::new(l), Box::new(r))
+    }
+
+    pub fn pow(l: Expression, r: Expression) -> Expression {
+        Expression::Power(Box::new(l), Box::new(r))
+    }
+
+    pub fn neg(e: Expression) -> Expression {
+        Expression::Negate(Box::new(e))
+    }
+
+    pub fn function(name: &str, args: Vec<Expression>) -> Expression {
+        Expression::Function {
+            name: String::from(name),
+            args: args.into_iter().map(Box::new).collect(),
+        }
+    }
+
+    // the n x n matrix with ones on its diagonal and zeros everywhere else
+    pub fn identity_matrix(n: u8) -> Expression {
+        Expression::Matrix {
+            backing: (0..n)
+                .flat_map(|r| (0..n).map(move |c| Box::new(Expression::int((r == c) as i32))))
+                .collect(),
+            shape: (n, n),
+        }
+    }
+
+    pub fn zero_matrix(rows: u8, cols: u8) -> Expression {
+        Expression::Matrix {
+            backing: vec![Box::new(Expression::int(0)); rows as usize * cols as usize],
+            shape: (rows, cols),
+        }
+    }
+
+    pub fn zero_vector(n: u8) -> Expression {
+        Expression::Vector {
+            backing: vec![Box::new(Expression::int(0)); n as usize],
+            size: n,
+        }
+    }
+}
+
+impl From<Numeric> for Expression {
+    fn from(n: Numeric) -> Expression {
+        Expression::Atom(Atom::Numeric(n))
+    }
+}
+
+impl From<i32> for Expression {
+    fn from(i: i32) -> Expression {
+        Expression::int(i)
+    }
+}
+
+impl From<char> for Expression {
+    fn from(v: char) -> Expression {
+        Expression::var(v)
+    }
+}
+
+impl Neg for Expression {
+    type Output = Expression;
+
+    fn neg(self) -> Expression {
+        Expression::Negate(Box::new(self))
+    }
 }
 
 impl Add for Expression {
@@ -850,6 +1896,52 @@ impl PartialOrd for Expression {
                     shape: s2,
                 },
             ) => b1.partial_cmp(b2).and(s1.partial_cmp(s2)),
+            (
+                Expression::Integral {
+                    integrand: _,
+                    var: v1,
+                    lower: _,
+                    upper: _,
+                },
+                Expression::Integral {
+                    integrand: _,
+                    var: v2,
+                    lower: _,
+                    upper: _,
+                },
+            ) => self
+                .children()
+                .partial_cmp(&other.children())
+                .and(v1.partial_cmp(v2)),
+            (
+                Expression::Sum {
+                    index: i1,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+                Expression::Sum {
+                    index: i2,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+            ) => self
+                .children()
+                .partial_cmp(&other.children())
+                .and(i1.partial_cmp(i2)),
+            (Expression::Relation(o1, a1, a2), Expression::Relation(o2, b1, b2)) => {
+                match a1.partial_cmp(b1) {
+                    Some(Ordering::Equal) => a2.partial_cmp(b2).and(o1.partial_cmp(o2)),
+                    o => o,
+                }
+            }
+            (Expression::And(es1), Expression::And(es2))
+            | (Expression::Or(es1), Expression::Or(es2)) => es1.partial_cmp(es2),
+            (Expression::Not(e1), Expression::Not(e2)) => e1.partial_cmp(e2),
+            (Expression::Piecewise(_), Expression::Piecewise(_)) => {
+                self.children().partial_cmp(&other.children())
+            }
             (Expression::Negate(e1), Expression::Negate(e2))
             | (Expression::Factorial(e1), Expression::Factorial(e2))
             | (Expression::Percent(e1), Expression::Percent(e2)) => e1.partial_cmp(e2),
@@ -894,6 +1986,52 @@ impl PartialOrd for Expression {
                     shape: _,
                 },
             ) => Some(Ordering::Less),
+            (
+                Expression::Integral {
+                    integrand: _,
+                    var: _,
+                    lower: _,
+                    upper: _,
+                },
+                _,
+            ) => Some(Ordering::Greater),
+            (
+                _,
+                Expression::Integral {
+                    integrand: _,
+                    var: _,
+                    lower: _,
+                    upper: _,
+                },
+            ) => Some(Ordering::Less),
+            (
+                Expression::Sum {
+                    index: _,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+                _,
+            ) => Some(Ordering::Greater),
+            (
+                _,
+                Expression::Sum {
+                    index: _,
+                    lower: _,
+                    upper: _,
+                    body: _,
+                },
+            ) => Some(Ordering::Less),
+            (Expression::Relation(_, _, _), _) => Some(Ordering::Greater),
+            (_, Expression::Relation(_, _, _)) => Some(Ordering::Less),
+            (Expression::And(_), _) => Some(Ordering::Greater),
+            (_, Expression::And(_)) => Some(Ordering::Less),
+            (Expression::Piecewise(_), _) => Some(Ordering::Greater),
+            (_, Expression::Piecewise(_)) => Some(Ordering::Less),
+            (Expression::Or(_), _) => Some(Ordering::Greater),
+            (_, Expression::Or(_)) => Some(Ordering::Less),
+            (Expression::Not(_), _) => Some(Ordering::Greater),
+            (_, Expression::Not(_)) => Some(Ordering::Less),
             (Expression::Negate(_), _) => Some(Ordering::Greater),
             (_, Expression::Negate(_)) => Some(Ordering::Less),
             (Expression::Factorial(_), _) => Some(Ordering::Greater),
@@ -966,7 +2104,8 @@ impl fmt::Display for Expression {
                     | Expression::Subtract(_, _)
                     | Expression::Modulus(_, _)
                     | Expression::Multiply(_, _)
-                    | Expression::Divide(_, _) => write!(f, "({})", l)?,
+                    | Expression::Divide(_, _)
+                    | Expression::Power(_, _) => write!(f, "({})", l)?,
                     _ => write!(f, "{}", l)?,
                 };
                 write!(f, " ^ ")?;
@@ -1023,13 +2162,78 @@ impl fmt::Display for Expression {
                 }
                 write!(f, "]")
             }
+
+            // written like a call, the plain syntax has no integral sign
+            Expression::Integral {
+                integrand,
+                var,
+                lower,
+                upper,
+            } => {
+                write!(f, "integrate({}, {}", integrand, var)?;
+                if let (Some(lower), Some(upper)) = (lower, upper) {
+                    write!(f, ", {}, {}", lower, upper)?;
+                }
+                write!(f, ")")
+            }
+            // written like a call, the plain syntax has no summation sign
+            Expression::Sum {
+                index,
+                lower,
+                upper,
+                body,
+            } => write!(f, "sum({}, {}, {}, {})", body, index, lower, upper),
+
+            Expression::Relation(op, l, r) => write!(f, "{} {} {}", l, op, r),
+            Expression::And(es) => {
+                for (i, link) in chain_links(es).into_iter().enumerate() {
+                    match link {
+                        Link::Chained(op, r) => write!(f, " {} {}", op, r)?,
+                        Link::Joined(e @ Expression::Or(_)) if i > 0 => write!(f, " and ({})", e)?,
+                        Link::Joined(e @ Expression::Or(_)) => write!(f, "({})", e)?,
+                        Link::Joined(e) if i > 0 => write!(f, " and {}", e)?,
+                        Link::Joined(e) => write!(f, "{}", e)?,
+                    }
+                }
+                Ok(())
+            }
+            Expression::Or(es) => {
+                for (i, e) in es.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
+            Expression::Not(e) => match **e {
+                Expression::Atom(_) => write!(f, "not {}", e),
+                _ => write!(f, "not ({})", e),
+            },
+
+            // written like a call, value then condition for every branch
+            Expression::Piecewise(branches) => {
+                write!(f, "piecewise(")?;
+                for (i, (value, condition)) in branches.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}, {}", value, condition)?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
 
 impl FromStr for Expression {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(parse(s))
+        // the parser recurses once per level of nesting, deeper input is refused before it can
+        // overflow the stack
+        if nesting_depth(s) > DEFAULT_MAX_DEPTH {
+            return Err(Error::InvalidSyntax);
+        }
+        try_parse(s).ok_or(Error::InvalidSyntax)
     }
 
     type Err = Error;
@@ -1039,11 +2243,14 @@ impl FromStr for Expression {
 mod tests {
     use core::str::FromStr;
 
-    use alloc::string::ToString;
+    use alloc::{boxed::Box, collections::BTreeMap, string::ToString, vec, vec::Vec};
 
     use crate::expression::expression_tree::{Atom, Numeric};
 
-    use super::Expression;
+    use super::{
+        equivalent, flatten_add, flatten_mul, is_right_associative, precedence, rebuild_add,
+        rebuild_mul, substitute_all, Expression,
+    };
 
     #[test]
     fn test_numeric_eq() {
@@ -1053,6 +2260,414 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            Expression::int(2) + Expression::var('x'),
+            Expression::from_str("2 + x").unwrap()
+        );
+        assert_eq!(
+            Expression::mul(2.into(), Expression::pow('x'.into(), 3.into())) - 'y'.into(),
+            Expression::from_str("2 * x ^ 3 - y").unwrap()
+        );
+        assert_eq!(
+            Expression::function("f", vec![-Expression::var('x'), Expression::int(1)]),
+            Expression::from_str("f(-x, 1)").unwrap()
+        );
+        assert_eq!(
+            Expression::div(Expression::neg('a'.into()), Expression::decimal(1.5)),
+            Expression::from_str("-a / 1.5").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_matrix_constructors() {
+        assert_eq!(
+            Expression::identity_matrix(2),
+            Expression::from_str("[1, 0; 0, 1]").unwrap()
+        );
+        assert_eq!(
+            Expression::zero_matrix(2, 3),
+            Expression::from_str("[0, 0, 0; 0, 0, 0]").unwrap()
+        );
+        assert_eq!(
+            Expression::zero_vector(3),
+            Expression::from_str("<0, 0, 0>").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fold_node_count() {
+        let expr = Expression::from_str("(x + 1) ^ 2").unwrap();
+
+        let count = expr.fold(&mut |_, children: Vec<usize>| 1 + children.iter().sum::<usize>());
+        assert_eq!(count, 5);
+
+        let count =
+            Expression::from_str("f(x, <1, 2>)")
+                .unwrap()
+                .fold(&mut |_, children: Vec<usize>| 1 + children.iter().sum::<usize>());
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn test_descendants() {
+        let expr = Expression::from_str("2 * f(x, 3.5) + 4 ^ y").unwrap();
+
+        let numerics = expr
+            .descendants()
+            .filter_map(|e| match e {
+                Expression::Atom(Atom::Numeric(n)) => Some(*n),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            numerics[..],
+            [
+                Numeric::Integer(2),
+                Numeric::Decimal(_),
+                Numeric::Integer(4)
+            ]
+        ));
+
+        let order = expr
+            .descendants()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(order[0], expr.to_string());
+        assert_eq!(order[1..4], ["2 * f(x, 3.5)", "2", "f(x, 3.5)"]);
+        assert_eq!(expr.descendants().count(), expr.node_count());
+    }
+
+    #[test]
+    fn test_node_count_depth() {
+        let expr = Expression::from_str("(x + 1) ^ 2").unwrap();
+        assert_eq!(expr.node_count(), 5);
+        assert_eq!(expr.depth(), 3);
+
+        let expr = Expression::from_str("x").unwrap();
+        assert_eq!(expr.node_count(), 1);
+        assert_eq!(expr.depth(), 1);
+    }
+
+    #[test]
+    fn test_contains_variable() {
+        let expr = Expression::from_str("f(2 * x) + y").unwrap();
+        assert!(expr.contains_variable('x'));
+        assert!(expr.contains_variable('y'));
+        assert!(!expr.contains_variable('z'));
+    }
+
+    #[test]
+    fn test_substitute_many() {
+        let values = BTreeMap::from([
+            ('x', Expression::from_str("y").unwrap()),
+            ('y', Expression::from_str("x").unwrap()),
+        ]);
+        assert_eq!(
+            Expression::from_str("x - y")
+                .unwrap()
+                .substitute_many(&values),
+            Expression::from_str("y - x").unwrap()
+        );
+
+        let values = BTreeMap::from([('x', Expression::from_str("2 * z").unwrap())]);
+        assert_eq!(
+            Expression::from_str("f(x, y) + x ^ 2")
+                .unwrap()
+                .substitute_many(&values),
+            Expression::from_str("f(2 * z, y) + (2 * z) ^ 2").unwrap()
+        );
+
+        // the variable of an integral and the index of a sum are bound in the integrand and the
+        // body, but not in the bounds
+        let values = BTreeMap::from([('k', Expression::from_str("3").unwrap())]);
+        let sum = |upper: &str| Expression::Sum {
+            index: 'k',
+            lower: Box::new(Expression::from_str("1").unwrap()),
+            upper: Box::new(Expression::from_str(upper).unwrap()),
+            body: Box::new(Expression::from_str("k * x").unwrap()),
+        };
+        assert_eq!(sum("k").substitute_many(&values), sum("3"));
+
+        let integral = |upper: &str| Expression::Integral {
+            integrand: Box::new(Expression::from_str("k ^ 2").unwrap()),
+            var: 'k',
+            lower: Some(Box::new(Expression::from_str("0").unwrap())),
+            upper: Some(Box::new(Expression::from_str(upper).unwrap())),
+        };
+        assert_eq!(integral("k").substitute_many(&values), integral("3"));
+    }
+
+    #[test]
+    fn test_substitute_all() {
+        let swap = BTreeMap::from([
+            ('x', Expression::from_str("y").unwrap()),
+            ('y', Expression::from_str("x").unwrap()),
+        ]);
+        let expr = Expression::from_str("x ^ 2 + 2 * x * y - sin(y)").unwrap();
+
+        assert_eq!(
+            substitute_all(&expr, &swap),
+            Expression::from_str("y ^ 2 + 2 * y * x - sin(x)").unwrap()
+        );
+        assert_eq!(substitute_all(&substitute_all(&expr, &swap), &swap), expr);
+
+        let chained = BTreeMap::from([
+            ('x', Expression::from_str("y + 1").unwrap()),
+            ('y', Expression::from_str("z").unwrap()),
+        ]);
+        assert_eq!(
+            substitute_all(&Expression::from_str("x * y").unwrap(), &chained),
+            Expression::from_str("(y + 1) * z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fold_post_order() {
+        let expr = Expression::from_str("(x + 1) ^ 2").unwrap();
+
+        let mut order = Vec::new();
+        expr.fold(&mut |e, _: Vec<()>| order.push(e.to_string()));
+
+        assert_eq!(order, vec!["x", "1", "x + 1", "2", "(x + 1) ^ 2"]);
+    }
+
+    #[test]
+    fn test_from_str_depth() {
+        let deep = |depth| "(".repeat(depth) + "x" + &")".repeat(depth);
+        assert!(Expression::from_str(&deep(64)).is_ok());
+        assert!(Expression::from_str(&deep(200_000)).is_err());
+        assert!(Expression::from_str(&("-".repeat(200_000) + "x")).is_err());
+    }
+
+    #[test]
+    fn test_numeric_promotion() {
+        assert!(matches!(
+            Numeric::Integer(3) / Numeric::Integer(2),
+            Numeric::Fraction(3, 2)
+        ));
+        assert!(matches!(
+            Numeric::Integer(4) / Numeric::Integer(2),
+            Numeric::Integer(2)
+        ));
+        assert!(matches!(
+            Numeric::Integer(2) + Numeric::Decimal(0.5),
+            Numeric::Decimal(d) if d == 2.5
+        ));
+        assert!(matches!(
+            Numeric::Integer(2) * Numeric::Integer(3),
+            Numeric::Integer(6)
+        ));
+        assert!(matches!(
+            Numeric::Integer(i32::MAX) + Numeric::Integer(1),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            Numeric::Integer(i32::MIN) / Numeric::Integer(-1),
+            Numeric::Fraction(i32::MIN, -1)
+        ));
+        assert!(matches!(-Numeric::Integer(i32::MIN), Numeric::Decimal(_)));
+
+        // fractions promote the same way once their numerator or denominator overflows
+        let small = Numeric::Fraction(1, 46341);
+        assert!(matches!(small * small, Numeric::Decimal(d) if d > 0.0));
+        assert!(matches!(
+            small + Numeric::Fraction(1, 46342),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            small - Numeric::Fraction(1, 46342),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            small / Numeric::Fraction(46341, 1),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            Numeric::Integer(i32::MAX) + Numeric::Fraction(1, 2),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            -Numeric::Fraction(i32::MIN, 3),
+            Numeric::Decimal(_)
+        ));
+        assert!(matches!(
+            Numeric::Fraction(1, 2) + Numeric::Fraction(1, 3),
+            Numeric::Fraction(5, 6)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_numeric_serde_tags() {
+        let integer = serde_json::to_string(&Numeric::Integer(5)).unwrap();
+        let decimal = serde_json::to_string(&Numeric::Decimal(5.0)).unwrap();
+        let fraction = serde_json::to_string(&Numeric::Fraction(1, 2)).unwrap();
+
+        assert_eq!(integer, r#"{"Integer":5}"#);
+        assert_eq!(decimal, r#"{"Decimal":5.0}"#);
+        assert_eq!(fraction, r#"{"Fraction":[1,2]}"#);
+
+        // Numeric equality is value based, so match on the variant to check the round trip
+        assert!(matches!(
+            serde_json::from_str::<Numeric>(&integer).unwrap(),
+            Numeric::Integer(5)
+        ));
+        assert!(matches!(
+            serde_json::from_str::<Numeric>(&decimal).unwrap(),
+            Numeric::Decimal(d) if d == 5.0
+        ));
+        assert!(matches!(
+            serde_json::from_str::<Numeric>(&fraction).unwrap(),
+            Numeric::Fraction(1, 2)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_expression_serde_round_trip() {
+        use alloc::boxed::Box;
+
+        let expr =
+            Expression::from_str("-sin(x ^ 2) / (3! + y % 2) * <1, 2.5> - [a, b; c, d]").unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+
+        assert_eq!(serde_json::from_str::<Expression>(&json).unwrap(), expr);
+        assert_eq!(
+            serde_json::to_string(&Expression::from_str("x + 1").unwrap()).unwrap(),
+            r#"{"Add":[{"Atom":{"Variable":"x"}},{"Atom":{"Numeric":{"Integer":1}}}]}"#
+        );
+
+        // the variants of the numbers inside of the tree survive the round trip too
+        let numbers = Expression::Add(
+            Box::new(Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2)))),
+            Box::new(Expression::Atom(Atom::Numeric(Numeric::Complex {
+                re: 1.0,
+                im: -1.0,
+            }))),
+        );
+        match serde_json::from_str(&serde_json::to_string(&numbers).unwrap()).unwrap() {
+            Expression::Add(l, r) => {
+                assert!(matches!(
+                    *l,
+                    Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2)))
+                ));
+                assert!(matches!(
+                    *r,
+                    Expression::Atom(Atom::Numeric(Numeric::Complex { re, im })) if re == 1.0 && im == -1.0
+                ));
+            }
+            e => panic!("round trip produced {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_commutative() {
+        let canonical = |s: &str| Expression::from_str(s).unwrap().canonicalize();
+
+        assert_eq!(canonical("x + y"), canonical("y + x"));
+        assert_eq!(canonical("y + x"), Expression::from_str("x + y").unwrap());
+        assert_eq!(
+            canonical("sin(x) + y + 2"),
+            Expression::from_str("2 + y + sin(x)").unwrap()
+        );
+        assert_eq!(
+            canonical("y * 2 * x"),
+            Expression::from_str("2 * x * y").unwrap()
+        );
+        assert_eq!(canonical("(b + a) * c"), canonical("c * (a + b)"));
+        assert_eq!(
+            canonical("f(y + x)"),
+            Expression::from_str("f(x + y)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_non_commutative() {
+        let canonical = |s: &str| Expression::from_str(s).unwrap().canonicalize();
+
+        assert_eq!(canonical("y - x"), Expression::from_str("y - x").unwrap());
+        assert_eq!(canonical("y / x"), Expression::from_str("y / x").unwrap());
+        assert_eq!(
+            canonical("[1, 2; 3, 4] * x"),
+            Expression::from_str("[1, 2; 3, 4] * x").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_collect_powers() {
+        let canonical = |s: &str| Expression::from_str(s).unwrap().canonicalize();
+
+        assert_eq!(canonical("x * x * x"), Expression::from_str("x^3").unwrap());
+        assert_eq!(canonical("x * x^2"), Expression::from_str("x^3").unwrap());
+        assert_eq!(
+            canonical("x * 2 * y * x"),
+            Expression::from_str("2 * y * x^2").unwrap()
+        );
+        assert_eq!(
+            canonical("x^n * x"),
+            Expression::from_str("x^(1 + n)").unwrap()
+        );
+        assert_eq!(canonical("2 * 2"), Expression::from_str("2 * 2").unwrap());
+    }
+
+    #[test]
+    fn test_flatten() {
+        let parse = |s: &str| Expression::from_str(s).unwrap();
+        let abc = vec![parse("a"), parse("b"), parse("c")];
+
+        assert_eq!(flatten_add(&parse("a + (b + c)")), abc);
+        assert_eq!(flatten_add(&parse("(a + b) + c")), abc);
+        assert_eq!(flatten_mul(&parse("a * (b * c)")), abc);
+        assert_eq!(flatten_mul(&parse("(a * b) * c")), abc);
+        assert_eq!(
+            flatten_add(&parse("a * b + (c - d)")),
+            vec![parse("a * b"), parse("c - d")]
+        );
+        assert_eq!(flatten_mul(&parse("a + b")), vec![parse("a + b")]);
+
+        assert_eq!(rebuild_add(abc.clone()), parse("a + b + c"));
+        assert_eq!(rebuild_mul(abc), parse("a * b * c"));
+        assert_eq!(rebuild_add(Vec::new()), Expression::int(0));
+        assert_eq!(rebuild_mul(Vec::new()), Expression::int(1));
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let equivalent = |a: &str, b: &str| {
+            equivalent(
+                &Expression::from_str(a).unwrap(),
+                &Expression::from_str(b).unwrap(),
+            )
+        };
+
+        assert!(equivalent("x + y", "y + x"));
+        assert!(equivalent("(a + b) + c", "a + (b + c)"));
+        assert!(equivalent("c + (b + a)", "a + b + c"));
+        assert!(equivalent("(a * b) * c", "a * (b * c)"));
+        assert!(equivalent("c * (a * b)", "b * c * a"));
+        assert!(equivalent("sin(y * x) + 1", "1 + sin(x * y)"));
+
+        assert!(!equivalent("x - y", "y - x"));
+        assert!(!equivalent("x / y", "y / x"));
+        assert!(!equivalent("a * (b + c)", "a * b + a * c"));
+        assert_ne!(
+            Expression::from_str("x + y").unwrap(),
+            Expression::from_str("y + x").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fmt_power_associativity() {
+        let nested = Expression::from_str("(2 ^ 2) ^ 3").unwrap();
+        assert_eq!(nested.to_string(), "(2 ^ 2) ^ 3");
+        assert_eq!(Expression::from_str(&nested.to_string()).unwrap(), nested);
+
+        let right = Expression::from_str("2 ^ 2 ^ 3").unwrap();
+        assert_eq!(right.to_string(), "2 ^ 2 ^ 3");
+    }
+
     #[test]
     fn test_fmt_parse() {
         assert_eq!(
@@ -1100,4 +2715,53 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn test_precedence() {
+        let parse = |s: &str| Expression::from_str(s).unwrap();
+
+        assert!(precedence(&parse("x^2")) > precedence(&parse("x*2")));
+        assert!(precedence(&parse("x*2")) > precedence(&parse("x+2")));
+        assert_eq!(precedence(&parse("x%2")), precedence(&parse("x/2")));
+        assert_eq!(precedence(&parse("f(x)")), precedence(&parse("x")));
+
+        // the parser puts the loosest operator at the root
+        for (s, grouped) in [
+            ("-x^2", "-(x^2)"),
+            ("-x!", "-(x!)"),
+            ("x^2!", "(x^2)!"),
+            ("-x*y", "(-x)*y"),
+        ] {
+            assert_eq!(parse(s), parse(grouped));
+        }
+        assert!(precedence(&parse("x^2")) > precedence(&parse("x!")));
+        assert!(precedence(&parse("x!")) > precedence(&parse("-x")));
+        assert!(precedence(&parse("-x")) > precedence(&parse("x*y")));
+
+        let atom = |n| Expression::Atom(Atom::Numeric(n));
+        assert_eq!(
+            precedence(&atom(Numeric::Fraction(1, 2))),
+            precedence(&parse("1/2"))
+        );
+        assert_eq!(
+            precedence(&atom(Numeric::Integer(-2))),
+            precedence(&parse("-2"))
+        );
+        assert_eq!(
+            precedence(&atom(Numeric::Integer(2))),
+            precedence(&parse("x"))
+        );
+    }
+
+    #[test]
+    fn test_is_right_associative() {
+        let parse = |s: &str| Expression::from_str(s).unwrap();
+
+        assert!(is_right_associative(&parse("2^3")));
+        assert_eq!(parse("2^2^3"), parse("2^(2^3)"));
+        for s in ["8-4", "8/4", "8*4", "8+4", "8%4"] {
+            assert!(!is_right_associative(&parse(s)));
+        }
+        assert_eq!(parse("8-4-2"), parse("(8-4)-2"));
+    }
 }
@@ -1,57 +1,316 @@
-use alloc::format;
 use alloc::string::String;
 use alloc::vec;
 use alloc::{boxed::Box, string::ToString, vec::Vec};
 
+use core::fmt::Write;
+
 use nom::bytes::complete::{tag, take_while};
-use nom::multi::separated_list0;
+use nom::multi::{separated_list0, separated_list1};
 use nom::sequence::pair;
 use nom::{
     branch::alt,
     bytes::complete::{take, take_while1},
-    character::complete::{char, digit1, space0},
-    combinator::map,
+    character::complete::{char, digit1, one_of, satisfy, space0},
+    combinator::{eof, map, map_opt, not, opt, peek, recognize, verify},
     multi::many0,
-    sequence::{delimited, preceded, terminated, tuple},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-use crate::expression::expression_tree::{Atom, Expression, Numeric};
+use crate::{
+    expression::expression_tree::{
+        chain_links, format_decimal, imaginary, Atom, Comparison, Expression, Link, Numeric,
+        DISPLAY_PRECISION,
+    },
+    ParseError,
+};
 
 use super::expression_tree::Escape;
 
 pub fn parse(input: &str) -> Expression {
-    parse_add_sub(input)
+    // deeper input would overflow the stack rather than fail to parse
+    if nesting_depth(input) > DEFAULT_MAX_DEPTH {
+        panic!("failed to parse");
+    }
+    parse_disjunction(&strip_spacing(input), Identifiers::default())
         .map_err(|_| "failed to parse")
         .unwrap()
         .1
 }
 
-fn parse_recursive(input: &str) -> IResult<&str, Expression> {
+// ParseOptions: limits to apply when parsing untrusted input, and what it may name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    // inputs longer than this many bytes are rejected before any parsing is done
+    pub max_length: Option<usize>,
+    // inputs nested deeper than this are rejected before any parsing is done, the parser recurses
+    // once per level so unbounded nesting would overflow the stack
+    pub max_depth: Option<usize>,
+    // the characters a single character variable may be
+    pub identifiers: Identifiers,
+}
+
+// the depth of nesting accepted unless the options say otherwise, well within what the parsers can
+// recurse through
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 64;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_length: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            identifiers: Identifiers::default(),
+        }
+    }
+}
+
+// Identifiers: the characters read as variables, restricting them keeps a stray character from
+// being parsed as a variable, Only("abcdefghijklmnopqrstuvwxyz") rejects `Q`, and extending them
+// allows domain specific names, Extended("'") reads `'` as a variable of its own
+// greek letters written as commands, `\\theta`, are always variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Identifiers {
+    // any letter
+    #[default]
+    Alphabetic,
+    // only the given characters
+    Only(&'static str),
+    // any letter or one of the given characters
+    Extended(&'static str),
+}
+
+impl Identifiers {
+    pub fn accepts(self, c: char) -> bool {
+        match self {
+            Identifiers::Alphabetic => c.is_alphabetic(),
+            Identifiers::Only(chars) => chars.contains(c),
+            Identifiers::Extended(chars) => c.is_alphabetic() || chars.contains(c),
+        }
+    }
+}
+
+// parses the whole input, reporting the byte offset where parsing failed instead of panicking
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Expression, ParseError> {
+    if let Some(max_length) = options.max_length {
+        if input.len() > max_length {
+            return Err(ParseError::InputTooLong {
+                length: input.len(),
+                max_length,
+            });
+        }
+    }
+    if let Some(max_depth) = options.max_depth {
+        if nesting_depth(input) > max_depth {
+            return Err(ParseError::TooDeep { max_depth });
+        }
+    }
+
+    let input = strip_spacing(input);
+    match parse_disjunction(&input, options.identifiers) {
+        Ok(("", expr)) => Ok(expr),
+        Ok((rest, _)) => Err(ParseError::InvalidSyntax {
+            position: input.len() - rest.len(),
+        }),
+        Err(nom::Err::Failure(e)) => match begin_environment(e.input) {
+            Ok((_, construct)) => Err(ParseError::Unsupported {
+                construct: construct.to_string(),
+                position: input.len() - e.input.len(),
+            }),
+            Err(_) => Err(ParseError::InvalidSyntax {
+                position: input.len() - e.input.len(),
+            }),
+        },
+        Err(nom::Err::Error(e)) => Err(ParseError::InvalidSyntax {
+            position: input.len() - e.input.len(),
+        }),
+        Err(nom::Err::Incomplete(_)) => Err(ParseError::InvalidSyntax {
+            position: input.len(),
+        }),
+    }
+}
+
+// the spacing commands of latex, which only change how the typeset output looks
+const SPACING: &[&str] = &[
+    "\\,", "\\;", "\\:", "\\>", "\\!", "\\ ", "\\quad", "\\qquad", "~",
+];
+
+// blanks out every spacing command, \\, \\; \\: \\> \\! \\quad \\qquad and ~, so they are read
+// as whitespace, each becomes as many spaces as it has bytes so error positions are unchanged
+fn strip_spacing(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        // \\\\ is a row separator and must not have its second backslash read as a command
+        if rest.starts_with("\\\\") {
+            out.push_str("\\\\");
+            rest = &rest[2..];
+            continue;
+        }
+        let spacing = SPACING.iter().filter(|s| rest.starts_with(**s)).find(|s| {
+            !s.ends_with(char::is_alphabetic) || !rest[s.len()..].starts_with(char::is_alphabetic)
+        });
+        match spacing {
+            Some(s) => {
+                out.extend(core::iter::repeat_n(' ', s.len()));
+                rest = &rest[s.len()..];
+            }
+            None => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+// how many levels of recursion parsing the input takes, counting groups, prefix operators and
+// exponents, every one of which the parser descends into
+// this is a single pass over the input, so it is cheap even when the input is too deep to parse
+pub(crate) fn nesting_depth(input: &str) -> usize {
+    // the operator chains of the enclosing groups, and their total
+    let mut groups = Vec::new();
+    let mut outer: usize = 0;
+    // prefix operators and exponents in the current group which have not yet ended
+    let mut chain = 0;
+    let mut operand_expected = true;
+    let mut max = 0;
+
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let open = match c {
+            '(' | '[' | '{' => Some(true),
+            '<' | '|' if operand_expected => Some(true),
+            '<' => {
+                chain = 0;
+                operand_expected = true;
+                None
+            }
+            ')' | ']' | '}' | '>' | '|' => Some(false),
+            '\\' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                }
+                match name.as_str() {
+                    // the delimiter after these is counted on the next iteration
+                    "left" => {
+                        chars.next_if_eq(&'|');
+                        Some(true)
+                    }
+                    "right" => {
+                        chars.next_if_eq(&'|');
+                        Some(false)
+                    }
+                    "cdot" | "times" | "div" | "" => {
+                        chars.next_if_eq(&'\\');
+                        chain = 0;
+                        operand_expected = true;
+                        None
+                    }
+                    _ => {
+                        operand_expected = true;
+                        None
+                    }
+                }
+            }
+            '-' | '+' if operand_expected => {
+                chain += 1;
+                None
+            }
+            '^' => {
+                chain += 1;
+                operand_expected = true;
+                None
+            }
+            '+' | '-' | '*' | '/' | '%' | ',' | '&' | '=' => {
+                chain = 0;
+                operand_expected = true;
+                None
+            }
+            c if c.is_whitespace() => None,
+            _ => {
+                operand_expected = false;
+                None
+            }
+        };
+
+        match open {
+            Some(true) => {
+                groups.push(chain);
+                outer += chain + 1;
+                chain = 0;
+                operand_expected = true;
+            }
+            Some(false) => {
+                chain = groups.pop().unwrap_or(0);
+                outer = outer.saturating_sub(chain + 1);
+                operand_expected = false;
+            }
+            None => (),
+        }
+        max = max.max(outer + chain);
+    }
+
+    max
+}
+
+fn parse_recursive(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     alt((
-        parse_parentheses,
-        parse_frac,
-        parse_vector,
-        parse_matrix,
+        |input| parse_parentheses(input, identifiers),
+        |input| parse_abs(input, identifiers),
+        |input| parse_brackets(input, identifiers),
+        |input| parse_frac(input, identifiers),
+        |input| parse_binom(input, identifiers),
+        |input| parse_sqrt(input, identifiers),
+        |input| parse_vector(input, identifiers),
+        |input| parse_matrix(input, identifiers),
+        |input| parse_matrix_environment(input, identifiers),
+        |input| parse_cases(input, identifiers),
         parse_numeric,
-        parse_function,
+        |input| parse_operatorname(input, identifiers),
+        |input| parse_integral(input, identifiers),
+        |input| parse_sum(input, identifiers),
+        parse_greek,
+        |input| parse_log_base(input, identifiers),
+        |input| parse_function(input, identifiers),
         parse_escape,
-        parse_variable,
+        parse_unsupported,
+        |input| parse_variable(input, identifiers),
     ))(input)
 }
 
-fn parse_parentheses(input: &str) -> IResult<&str, Expression> {
+// the name of the environment opened by `\begin{name}`
+fn begin_environment(input: &str) -> IResult<&str, &str> {
+    delimited(
+        tag("\\begin{"),
+        take_while1(|c: char| c.is_alphabetic() || c == '*'),
+        char('}'),
+    )(input)
+}
+
+// recognized constructs which can not be parsed yet, these fail the whole parse (rather than being
+// read character by character as variables) so that parse_with_options can name the construct
+fn parse_unsupported(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = space0(input)?;
+    begin_environment(input)?;
+    Err(nom::Err::Failure(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::Verify,
+    )))
+}
+
+fn parse_parentheses(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     delimited(
         space0,
         alt((
             delimited(
                 alt((tag("("), tag("\\left("))),
-                parse_add_sub,
+                |input| parse_add_sub(input, identifiers),
                 alt((tag(")"), tag("\\right)"))),
             ),
             delimited(
                 alt((tag("{"), tag("\\left{"))),
-                parse_add_sub,
+                |input| parse_add_sub(input, identifiers),
                 alt((tag("}"), tag("\\right}"))),
             ),
         )),
@@ -59,9 +318,79 @@ fn parse_parentheses(input: &str) -> IResult<&str, Expression> {
     )(input)
 }
 
-fn parse_numeric(input: &str) -> IResult<&str, Expression> {
+// absolute value bars: `|` both opens and closes, so a bar is always read as opening a new
+// absolute value where an operand is expected (`||x|+1|` is `abs(abs(x)+1)`), bars that would be
+// ambiguous (such as `|a|b|c|`) need the `\\left|` and `\\right|` forms
+fn parse_abs(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
-        delimited(space0, take_while1(is_numeric_value), space0),
+        delimited(
+            space0,
+            alt((
+                delimited(
+                    tag("\\left|"),
+                    |input| parse_add_sub(input, identifiers),
+                    tag("\\right|"),
+                ),
+                delimited(
+                    tag("|"),
+                    |input| parse_add_sub(input, identifiers),
+                    tag("|"),
+                ),
+            )),
+            space0,
+        ),
+        |arg| Expression::Function {
+            name: "abs".to_string(),
+            args: vec![Box::new(arg)],
+        },
+    )(input)
+}
+
+// floor and ceiling brackets: `\\lfloor x \\rfloor` is floor(x) and `\\lceil x \\rceil` is ceil(x), so
+// `\\lfloor a/b \\rfloor` is the floor division of a by b
+fn parse_brackets(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let bracket = |name: &'static str, open: &'static str, close: &'static str| {
+        map(
+            alt((
+                delimited(
+                    pair(tag("\\left"), tag(open)),
+                    |input| parse_add_sub(input, identifiers),
+                    pair(tag("\\right"), tag(close)),
+                ),
+                delimited(
+                    tag(open),
+                    |input| parse_add_sub(input, identifiers),
+                    tag(close),
+                ),
+            )),
+            move |arg| Expression::Function {
+                name: name.to_string(),
+                args: vec![Box::new(arg)],
+            },
+        )
+    };
+
+    delimited(
+        space0,
+        alt((
+            bracket("floor", "\\lfloor", "\\rfloor"),
+            bracket("ceil", "\\lceil", "\\rceil"),
+        )),
+        space0,
+    )(input)
+}
+
+fn parse_numeric(input: &str) -> IResult<&str, Expression> {
+    map_opt(
+        delimited(
+            space0,
+            // the exponent is only taken when digits follow the e, so 2e still leaves the constant e
+            recognize(pair(
+                take_while1(is_numeric_value),
+                opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+            )),
+            space0,
+        ),
         parse_number,
     )(input)
 }
@@ -70,14 +399,21 @@ fn is_numeric_value(c: char) -> bool {
     c.is_ascii_digit() || c == '.'
 }
 
-fn parse_number(input: &str) -> Expression {
-    Expression::Atom(Atom::Numeric(match input.contains('.') {
-        true => Numeric::Decimal(input.parse::<f32>().unwrap()),
-        false => Numeric::Integer(input.parse::<i32>().unwrap()),
-    }))
+// an integer too large for an i32 is kept as a decimal, as from_sexpr does, a malformed number
+// such as 1.2.3 is rejected
+fn parse_number(input: &str) -> Option<Expression> {
+    let numeric = match input.contains(['.', 'e', 'E']) {
+        true => input.parse::<f32>().ok().map(Numeric::Decimal),
+        false => input
+            .parse::<i32>()
+            .map(Numeric::Integer)
+            .or_else(|_| input.parse::<f32>().map(Numeric::Decimal))
+            .ok(),
+    };
+    numeric.map(|n| Expression::Atom(Atom::Numeric(n)))
 }
 
-fn parse_function(input: &str) -> IResult<&str, Expression> {
+fn parse_function(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
         delimited(
             space0,
@@ -85,70 +421,381 @@ fn parse_function(input: &str) -> IResult<&str, Expression> {
                 preceded(
                     space0,
                     alt((
-                        preceded(
+                        // a command may be spaced from its arguments, `\\sin (x)`, a plain name
+                        // has to be followed by them directly to be read as a call
+                        delimited(
                             tag("\\"),
-                            pair(
+                            tuple((
                                 take_while1(|c: char| c.is_alphabetic()),
                                 take_while(|c: char| c.is_alphanumeric()),
-                            ),
+                                primes,
+                            )),
+                            space0,
                         ),
-                        pair(
+                        tuple((
                             take_while1(|c: char| c.is_alphabetic()),
                             take_while(|c: char| c.is_alphanumeric()),
-                        ),
+                            primes,
+                        )),
                     )),
                 ),
-                delimited(
-                    alt((tag("("), tag("\\left("))),
-                    pair(many0(terminated(parse_add_sub, char(','))), parse_add_sub),
-                    alt((tag(")"), tag("\\right)"))),
-                ),
+                |input| parse_arguments(input, identifiers),
             )),
             space0,
         ),
-        |(name, arg_list)| Expression::Function {
-            name: name.0.to_string() + name.1,
-            args: arg_list
-                .0
-                .into_iter()
-                .chain(vec![arg_list.1])
-                .map(Box::new)
-                .collect(),
+        |((first, rest, primes), args)| Expression::Function {
+            name: function_name(&(first.to_string() + rest)) + primes,
+            args: args.into_iter().map(Box::new).collect(),
+        },
+    )(input)
+}
+
+// the primes marking the derivative of a function, f'(x) and f''(x), kept at the end of its name
+fn primes(input: &str) -> IResult<&str, &str> {
+    take_while(|c: char| c == '\'')(input)
+}
+
+// `\\log_{b}(x)` is the logarithm of x to the base b, stored as log(b, x), a base of a single digit
+// or letter may be written without braces
+fn parse_log_base(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        delimited(
+            space0,
+            pair(
+                preceded(tag("\\log_"), |input| parse_script(input, identifiers)),
+                verify(
+                    preceded(space0, |input| parse_arguments(input, identifiers)),
+                    |args: &Vec<Expression>| args.len() == 1,
+                ),
+            ),
+            space0,
+        ),
+        |(base, mut args)| Expression::Function {
+            name: "log".to_string(),
+            args: vec![Box::new(base), Box::new(args.pop().unwrap())],
+        },
+    )(input)
+}
+
+// the argument of a subscript or superscript, braced or a single digit or letter
+fn parse_script(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    alt((
+        |input| parse_braced(input, identifiers),
+        map_opt(recognize(satisfy(|c| c.is_ascii_digit())), parse_number),
+        map(satisfy(|c| identifiers.accepts(c)), |c| {
+            Expression::Atom(Atom::Variable(c))
+        }),
+    ))(input)
+}
+
+// `\int_{a}^{b} f \, dx` is the integral of f from a to b, `\int f \, dx` the indefinite integral
+// the integrand runs up to a differential d followed by a single letter, the last one which leaves
+// an integrand that parses is taken, so nested integrals close in order
+fn parse_integral(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, _) = pair(space0, tag("\\int"))(input)?;
+    let (input, (lower, upper)) = pair(
+        opt(preceded(pair(space0, char('_')), |input| {
+            parse_script(input, identifiers)
+        })),
+        opt(preceded(pair(space0, char('^')), |input| {
+            parse_script(input, identifiers)
+        })),
+    )(input)?;
+
+    // the integrand is parsed on its own, so its errors are reported at the integral
+    let error = || nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Verify));
+    let (end, var, rest) = differential(input).ok_or_else(error)?;
+    let (_, integrand) =
+        terminated(|input| parse_add_sub(input, identifiers), eof)(input[..end].trim_end())
+            .map_err(|_| error())?;
+    let (rest, _) = space0(rest)?;
+
+    Ok((
+        rest,
+        Expression::Integral {
+            integrand: Box::new(integrand),
+            var,
+            lower: lower.map(Box::new),
+            upper: upper.map(Box::new),
+        },
+    ))
+}
+
+// `\\sum_{i=1}^{n} f` is the sum of f for every integer i from 1 to n, the body is a product,
+// so `\\sum_{i=1}^{n} i + 1` adds 1 to the sum, and an index i is a variable in the body rather
+// than the imaginary unit
+fn parse_sum(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, _) = pair(space0, command("\\sum"))(input)?;
+    let (input, (index, lower)) = preceded(
+        pair(space0, char('_')),
+        delimited(
+            char('{'),
+            separated_pair(
+                delimited(space0, satisfy(|c| identifiers.accepts(c)), space0),
+                char('='),
+                |input| parse_add_sub(input, identifiers),
+            ),
+            char('}'),
+        ),
+    )(input)?;
+    let (input, upper) = preceded(pair(space0, char('^')), |input| {
+        parse_script(input, identifiers)
+    })(input)?;
+    let (input, body) = parse_mult_div_mod(input, identifiers)?;
+
+    Ok((
+        input,
+        Expression::Sum {
+            index,
+            lower: Box::new(lower),
+            upper: Box::new(upper),
+            body: Box::new(match index {
+                'i' => bind_imaginary(&body),
+                _ => body,
+            }),
+        },
+    ))
+}
+
+// reads every imaginary unit as the variable i
+fn bind_imaginary(expr: &Expression) -> Expression {
+    match expr {
+        Expression::Atom(Atom::Numeric(n)) if *n == Numeric::I => {
+            Expression::Atom(Atom::Variable('i'))
+        }
+        e => e.map_children(bind_imaginary),
+    }
+}
+
+// the differential that closes an integral, as the offset of its d, its variable and the input
+// after it, each nested \\int outside of brackets takes the next differential as brackets do, so
+// the integrand is only parsed once, a d only starts a differential when it does not continue a
+// word or a command
+fn differential(input: &str) -> Option<(usize, char, &str)> {
+    let mut depth = 0usize;
+    let mut open = 1usize;
+    let mut previous = None;
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth = depth.saturating_sub(1),
+            '\\' if depth == 0 && command("\\int")(&input[i..]).is_ok() => open += 1,
+            'd' if depth == 0
+                && !matches!(previous, Some(p) if p == '\\' || char::is_alphabetic(p)) =>
+            {
+                if let Some(&(_, var)) = chars.peek().filter(|(_, v)| v.is_ascii_alphabetic()) {
+                    let rest = &input[i + 2..];
+                    if !rest.starts_with(|c: char| c.is_alphanumeric()) {
+                        open -= 1;
+                        if open == 0 {
+                            return Some((i, var, rest));
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+// a braced argument of a command, which may be spaced from the command, `\\frac {1}{2}`
+fn parse_braced(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    preceded(
+        space0,
+        delimited(
+            char('{'),
+            |input| parse_add_sub(input, identifiers),
+            char('}'),
+        ),
+    )(input)
+}
+
+fn parse_arguments(input: &str, identifiers: Identifiers) -> IResult<&str, Vec<Expression>> {
+    delimited(
+        alt((tag("("), tag("\\left("))),
+        separated_list1(char(','), |input| parse_add_sub(input, identifiers)),
+        alt((tag(")"), tag("\\right)"))),
+    )(input)
+}
+
+// `\operatorname{name}(x)` is a call to name, with `\operatorname{mod}(a, b)` being the modulus a % b
+fn parse_operatorname(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        delimited(
+            space0,
+            pair(
+                pair(
+                    delimited(
+                        tuple((tag("\\operatorname"), space0, char('{'))),
+                        take_while1(|c: char| c.is_alphanumeric()),
+                        char('}'),
+                    ),
+                    primes,
+                ),
+                preceded(space0, |input| parse_arguments(input, identifiers)),
+            ),
+            space0,
+        ),
+        |((name, primes), mut args)| match (name, primes, args.len()) {
+            ("mod", "", 2) => {
+                let r = args.pop().unwrap();
+                let l = args.pop().unwrap();
+                Expression::Modulus(Box::new(l), Box::new(r))
+            }
+            _ => Expression::Function {
+                name: function_name(name) + primes,
+                args: args.into_iter().map(Box::new).collect(),
+            },
+        },
+    )(input)
+}
+
+// greek letters: (latex command, variable)
+// `\\theta` is stored as the variable θ and re-emitted as `\\theta`
+const GREEK_LETTERS: &[(&str, char)] = &[
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("pi", 'π'),
+    ("rho", 'ρ'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Xi", 'Ξ'),
+    ("Pi", 'Π'),
+    ("Sigma", 'Σ'),
+    ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+];
+
+fn parse_greek(input: &str) -> IResult<&str, Expression> {
+    map_opt(
+        delimited(
+            space0,
+            preceded(tag("\\"), take_while1(|c: char| c.is_ascii_alphabetic())),
+            space0,
+        ),
+        |name: &str| {
+            GREEK_LETTERS
+                .iter()
+                .find(|(command, _)| *command == name)
+                .map(|(_, letter)| Expression::Atom(Atom::Variable(*letter)))
+        },
+    )(input)
+}
+
+// known functions: (function name, latex command)
+// both `\\command(x)` and `command(x)` parse to the function name, which is re-emitted as `\\command`
+const KNOWN_FUNCTIONS: &[(&str, &str)] = &[
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("cot", "cot"),
+    ("sec", "sec"),
+    ("csc", "csc"),
+    ("asin", "arcsin"),
+    ("acos", "arccos"),
+    ("atan", "arctan"),
+    ("sinh", "sinh"),
+    ("cosh", "cosh"),
+    ("tanh", "tanh"),
+    ("coth", "coth"),
+    ("ln", "ln"),
+    ("log", "log"),
+    ("exp", "exp"),
+    ("sqrt", "sqrt"),
+    ("det", "det"),
+    ("gcd", "gcd"),
+    ("max", "max"),
+    ("min", "min"),
+    ("arg", "arg"),
+    ("deg", "deg"),
+    ("dim", "dim"),
+];
+
+// normalizes a parsed function name (with or without the backslash) to its canonical name
+fn function_name(name: &str) -> String {
+    KNOWN_FUNCTIONS
+        .iter()
+        .find(|(function, command)| *function == name || *command == name)
+        .map_or_else(|| name.to_string(), |(function, _)| function.to_string())
+}
+
+// the latex command for a known function, or None for user defined functions
+fn function_command(name: &str) -> Option<&'static str> {
+    KNOWN_FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .map(|(_, command)| *command)
+}
+
+fn parse_sqrt(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        delimited(
+            space0,
+            preceded(tag("\\sqrt"), |input| parse_braced(input, identifiers)),
+            space0,
+        ),
+        |arg| Expression::Function {
+            name: "sqrt".to_string(),
+            args: vec![Box::new(arg)],
         },
     )(input)
 }
 
 // TODO: fix vector and matrix parsing
-fn parse_vector(input: &str) -> IResult<&str, Expression> {
+fn parse_vector(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
         delimited(
             space0,
             delimited(
                 char('<'),
-                pair(many0(terminated(parse_add_sub, char(','))), parse_add_sub),
+                separated_list1(char(','), |input| parse_add_sub(input, identifiers)),
                 char('>'),
             ),
             space0,
         ),
         |vector| Expression::Vector {
-            size: vector.0.len() as u8 + 1,
-            backing: vector
-                .0
-                .into_iter()
-                .chain(vec![vector.1])
-                .map(Box::new)
-                .collect(),
+            size: vector.len() as u8,
+            backing: vector.into_iter().map(Box::new).collect(),
         },
     )(input)
 }
 
-fn parse_matrix(input: &str) -> IResult<&str, Expression> {
+fn parse_matrix(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
         delimited(
             space0,
             delimited(
                 char('['),
-                separated_list0(char(';'), separated_list0(char(','), parse_add_sub)),
+                separated_list0(
+                    char(';'),
+                    separated_list0(char(','), |input| parse_add_sub(input, identifiers)),
+                ),
                 char(']'),
             ),
             space0,
@@ -166,6 +813,76 @@ fn parse_matrix(input: &str) -> IResult<&str, Expression> {
     )(input)
 }
 
+// `\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}`, bmatrix environments are read the same way
+fn parse_matrix_environment(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, _) = space0(input)?;
+    let (input, name) = verify(begin_environment, |name: &str| {
+        name == "pmatrix" || name == "bmatrix"
+    })(input)?;
+    let (input, rows) = separated_list1(
+        delimited(space0, tag("\\\\"), space0),
+        separated_list1(
+            delimited(space0, char('&'), space0),
+            // a trailing \\\\ leaves no row before \\end
+            preceded(not(pair(space0, tag("\\end{"))), |input| {
+                parse_add_sub(input, identifiers)
+            }),
+        ),
+    )(input)?;
+    let (input, _) = delimited(space0, opt(tag("\\\\")), space0)(input)?;
+    let (rest, _) = match terminated(tuple((tag("\\end{"), tag(name), char('}'))), space0)(input) {
+        // closed by another environment, \\begin{pmatrix} ... \\end{bmatrix}, reported at the \\end
+        Err(nom::Err::Error(_)) if input.starts_with("\\end{") => {
+            return Err(nom::Err::Failure(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Tag,
+            )))
+        }
+        result => result?,
+    };
+
+    // every row needs the same number of columns, a ragged matrix is reported at its end
+    let col_count = rows[0].len();
+    if rows.iter().any(|row| row.len() != col_count) {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+
+    Ok((
+        rest,
+        Expression::Matrix {
+            shape: (rows.len() as u8, col_count as u8),
+            backing: rows.into_iter().flatten().map(Box::new).collect(),
+        },
+    ))
+}
+
+// `\\begin{cases} x & x > 0 \\\\ -x & x \\le 0 \\end{cases}`, a value and its condition on every row
+fn parse_cases(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, _) = space0(input)?;
+    let (input, _) = verify(begin_environment, |name: &str| name == "cases")(input)?;
+    let (input, branches) = separated_list1(
+        delimited(space0, tag("\\\\"), space0),
+        pair(
+            preceded(not(pair(space0, tag("\\end{"))), |input| {
+                parse_add_sub(input, identifiers)
+            }),
+            preceded(delimited(space0, char('&'), space0), |input| {
+                parse_disjunction(input, identifiers)
+            }),
+        ),
+    )(input)?;
+    let (input, _) = delimited(
+        pair(space0, opt(tag("\\\\"))),
+        delimited(space0, tag("\\end{cases}"), space0),
+        space0,
+    )(input)?;
+
+    Ok((input, Expression::Piecewise(branches)))
+}
+
 fn parse_escape(input: &str) -> IResult<&str, Expression> {
     map(
         delimited(
@@ -189,46 +906,104 @@ fn parse_escape(input: &str) -> IResult<&str, Expression> {
     )(input)
 }
 
-fn parse_variable(input: &str) -> IResult<&str, Expression> {
-    map(delimited(space0, take(1usize), space0), |value: &str| {
-        Expression::Atom(Atom::Variable(value.chars().next().unwrap()))
-    })(input)
+// a single character the identifiers accept, a letter by default, i is the imaginary unit rather
+// than a variable, operators and delimiters are left for the caller to reject
+fn parse_variable(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        delimited(space0, satisfy(|c| identifiers.accepts(c)), space0),
+        |c| match c {
+            'i' => Expression::Atom(Atom::Numeric(Numeric::I)),
+            c => Expression::Atom(Atom::Variable(c)),
+        },
+    )(input)
 }
 
-fn parse_unary(input: &str) -> IResult<&str, Expression> {
-    alt((parse_unary_prefix, parse_unary_postfix, parse_exponents))(input)
+fn parse_unary(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    alt((
+        |input| parse_unary_prefix(input, identifiers),
+        |input| parse_unary_postfix(input, identifiers),
+    ))(input)
 }
 
-fn parse_exponents(input: &str) -> IResult<&str, Expression> {
-    let (input, num) = parse_recursive(input)?;
-    let (input, ops) = many0(tuple((tag("^"), parse_exponents)))(input)?;
+// the exponent is parsed recursively, so powers are right associative: 2^2^3 is 2^{2^3}
+// an exponent may be negated without braces, x^-2 is x^{-2}
+fn parse_exponents(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, num) = parse_recursive(input, identifiers)?;
+    let (input, ops) = many0(tuple((
+        tag("^"),
+        alt((
+            |input| parse_unary_prefix(input, identifiers),
+            |input| parse_exponents(input, identifiers),
+        )),
+    )))(input)?;
     Ok((input, fold_binary_operators(num, ops)))
 }
 
-fn parse_unary_prefix(input: &str) -> IResult<&str, Expression> {
+// a leading + changes nothing, +x is x, it is only read where an operand is expected so it never
+// takes the place of an addition
+fn parse_unary_prefix(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
-        delimited(space0, tuple((tag("-"), parse_unary)), space0),
+        delimited(
+            space0,
+            tuple((alt((tag("-"), tag("+"))), |input| {
+                parse_unary(input, identifiers)
+            })),
+            space0,
+        ),
         parse_unary_prefix_op,
     )(input)
 }
 
-fn parse_unary_postfix(input: &str) -> IResult<&str, Expression> {
+// the operand is parsed once whether or not an operator follows it, reparsing it when there is none
+// would double the work at every level of nesting
+fn parse_unary_postfix(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
-        delimited(space0, tuple((parse_exponents, tag("!"))), space0),
-        parse_unary_postfix_op,
+        delimited(
+            space0,
+            tuple((
+                |input| parse_exponents(input, identifiers),
+                opt(alt((tag("!"), parse_percent_sign))),
+            )),
+            space0,
+        ),
+        |(operand, operator)| match operator {
+            Some(operator) => parse_unary_postfix_op((operand, operator)),
+            None => operand,
+        },
+    )(input)
+}
+
+// a % is a percentage when no operand follows it (`50%`, `50%+1`, `\frac{50%}{2}`) and a modulus
+// otherwise (`7%3`), so the modulus by a negative number needs parentheses: `7%\left(-3\right)`
+fn parse_percent_sign(input: &str) -> IResult<&str, &str> {
+    terminated(
+        tag("%"),
+        peek(preceded(
+            space0,
+            alt((
+                eof,
+                tag("\\right"),
+                tag("\\cdot"),
+                tag("\\times"),
+                tag("\\div"),
+                tag("\\rfloor"),
+                tag("\\rceil"),
+                recognize(one_of(")]>,;|+-/^}")),
+            )),
+        )),
     )(input)
 }
 
-fn parse_frac(input: &str) -> IResult<&str, Expression> {
+fn parse_frac(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
     map(
         delimited(
             space0,
             delimited(
                 tag("\\frac"),
-                tuple((
-                    delimited(char('{'), parse_add_sub, char('}')),
-                    delimited(char('{'), parse_add_sub, char('}')),
-                )),
+                pair(
+                    |input| parse_braced(input, identifiers),
+                    |input| parse_braced(input, identifiers),
+                ),
                 space0,
             ),
             space0,
@@ -237,25 +1012,234 @@ fn parse_frac(input: &str) -> IResult<&str, Expression> {
     )(input)
 }
 
-fn parse_mult_div_mod(input: &str) -> IResult<&str, Expression> {
-    let (input, num) = parse_unary(input)?;
+fn parse_binom(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        delimited(
+            space0,
+            delimited(
+                tag("\\binom"),
+                pair(
+                    |input| parse_braced(input, identifiers),
+                    |input| parse_braced(input, identifiers),
+                ),
+                space0,
+            ),
+            space0,
+        ),
+        |(n, k)| Expression::Function {
+            name: "binom".to_string(),
+            args: vec![Box::new(n), Box::new(k)],
+        },
+    )(input)
+}
+
+fn parse_mult_div_mod(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, num) = parse_implicit_product(input, identifiers)?;
     let (input, ops) = many0(tuple((
-        alt((tag("\\cdot"), tag("/"), tag("%"))),
-        parse_unary,
+        alt((
+            tag("\\cdot"),
+            tag("\\times"),
+            tag("\\div"),
+            tag("/"),
+            tag("%"),
+        )),
+        |input| parse_implicit_product(input, identifiers),
     )))(input)?;
     Ok((input, fold_binary_operators(num, ops)))
 }
 
-fn parse_add_sub(input: &str) -> IResult<&str, Expression> {
-    let (input, num) = parse_mult_div_mod(input)?;
-    let (input, ops) = many0(tuple((alt((tag("+"), tag("-"))), parse_mult_div_mod)))(input)?;
+// a numeric written directly against a variable, function or group is a product (`2x`, `3\sin(x)`),
+// which is how latexify writes numeric coefficients, a negated numeric negates the product (`-2x`)
+fn parse_implicit_product(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, expr) = parse_unary(input, identifiers)?;
+    let (negated, expr) = match expr {
+        Expression::Atom(Atom::Numeric(_)) => (false, expr),
+        Expression::Negate(e) if matches!(*e, Expression::Atom(Atom::Numeric(_))) => (true, *e),
+        expr => return Ok((input, expr)),
+    };
+    let negate = |e: Expression| match negated {
+        true => Expression::Negate(Box::new(e)),
+        false => e,
+    };
+
+    // the factor has to start with a letter, a command or an opening group, and a lone character
+    // such as the `\\` of `\\right)` is not a variable here
+    match preceded(
+        pair(
+            not(preceded(
+                space0,
+                alt((tag("\\cdot"), tag("\\times"), tag("\\div"))),
+            )),
+            peek(preceded(
+                space0,
+                satisfy(|c| c.is_alphabetic() || "\\({|".contains(c)),
+            )),
+        ),
+        verify(
+            |input| parse_unary(input, identifiers),
+            |e: &Expression| match e {
+                Expression::Atom(Atom::Variable(c)) => c.is_alphabetic(),
+                Expression::Atom(Atom::Numeric(Numeric::Complex { re: _, im: _ })) => true,
+                Expression::Atom(_) | Expression::Negate(_) => false,
+                _ => true,
+            },
+        ),
+    )(input)
+    {
+        Ok((input, factor)) => Ok((
+            input,
+            negate(Expression::Multiply(Box::new(expr), Box::new(factor))),
+        )),
+        Err(_) => Ok((input, negate(expr))),
+    }
+}
+
+fn parse_add_sub(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, num) = parse_mult_div_mod(input, identifiers)?;
+    let (input, ops) = many0(tuple((alt((tag("+"), tag("-"))), |input| {
+        parse_mult_div_mod(input, identifiers)
+    })))(input)?;
     Ok((input, fold_binary_operators(num, ops)))
 }
 
+// systems joined by `\\lor`, which binds looser than `\\land`
+fn parse_disjunction(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        separated_list1(command("\\lor"), |input| parse_system(input, identifiers)),
+        |mut systems| match systems.len() {
+            1 => systems.pop().unwrap(),
+            _ => Expression::Or(systems.into_iter().map(Box::new).collect()),
+        },
+    )(input)
+}
+
+// relations joined by `\\land`, the relations of a chain are members of the system themselves, so
+// `0 < x < 1 \\land y > 0` has three members
+fn parse_system(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    map(
+        separated_list1(command("\\land"), |input| {
+            parse_negation(input, identifiers)
+        }),
+        |relations| match relations.len() {
+            1 => relations.into_iter().next().unwrap(),
+            _ => Expression::And(
+                relations
+                    .into_iter()
+                    .flat_map(|r| match r {
+                        Expression::And(es) => es,
+                        r => vec![Box::new(r)],
+                    })
+                    .collect(),
+            ),
+        },
+    )(input)
+}
+
+// a relation, `\\top` or `\\bot`, or a parenthesized logical expression, negated by any number of
+// `\\lnot`s in front of it: `\\lnot x > 0` is the negation of x > 0
+fn parse_negation(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    alt((
+        map(
+            preceded(
+                delimited(space0, alt((command("\\lnot"), command("\\neg"))), space0),
+                |input| parse_negation(input, identifiers),
+            ),
+            |e| Expression::Not(Box::new(e)),
+        ),
+        |input| parse_logic_group(input, identifiers),
+        delimited(
+            space0,
+            alt((
+                map(command("\\top"), |_| Expression::Atom(Atom::Boolean(true))),
+                map(command("\\bot"), |_| Expression::Atom(Atom::Boolean(false))),
+            )),
+            space0,
+        ),
+        |input| parse_relation(input, identifiers),
+    ))(input)
+}
+
+// parentheses around a logical expression, parentheses around anything else are left to be part of
+// a relation, so `(x+1)*2 > 0` is not cut short after `(x+1)`
+fn parse_logic_group(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    verify(
+        delimited(
+            pair(space0, alt((tag("("), tag("\\left(")))),
+            |input| parse_disjunction(input, identifiers),
+            pair(alt((tag(")"), tag("\\right)"))), space0),
+        ),
+        is_logical,
+    )(input)
+}
+
+fn is_logical(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Atom(Atom::Boolean(_))
+            | Expression::Relation(_, _, _)
+            | Expression::And(_)
+            | Expression::Or(_)
+            | Expression::Not(_)
+    )
+}
+
+// a comparison of two expressions, a chain of comparisons `0 < x \\le 1` is the system of the
+// comparisons between neighbouring expressions, 0 < x and x <= 1
+fn parse_relation(input: &str, identifiers: Identifiers) -> IResult<&str, Expression> {
+    let (input, first) = parse_add_sub(input, identifiers)?;
+    let (input, rest) = many0(pair(parse_comparison, |input| {
+        parse_add_sub(input, identifiers)
+    }))(input)?;
+
+    let mut lhs = first;
+    let mut relations = Vec::new();
+    for (op, rhs) in rest {
+        relations.push(Box::new(Expression::Relation(
+            op,
+            Box::new(lhs),
+            Box::new(rhs.clone()),
+        )));
+        lhs = rhs;
+    }
+    Ok((
+        input,
+        match relations.len() {
+            0 => lhs,
+            1 => *relations.pop().unwrap(),
+            _ => Expression::And(relations),
+        },
+    ))
+}
+
+fn parse_comparison(input: &str) -> IResult<&str, Comparison> {
+    alt((
+        map(tag("="), |_| Comparison::Equal),
+        map(tag("<="), |_| Comparison::LessEqual),
+        map(tag(">="), |_| Comparison::GreaterEqual),
+        map(alt((command("\\neq"), command("\\ne"))), |_| {
+            Comparison::NotEqual
+        }),
+        map(alt((command("\\leq"), command("\\le"))), |_| {
+            Comparison::LessEqual
+        }),
+        map(alt((command("\\geq"), command("\\ge"))), |_| {
+            Comparison::GreaterEqual
+        }),
+        map(alt((tag("<"), command("\\lt"))), |_| Comparison::Less),
+        map(alt((tag(">"), command("\\gt"))), |_| Comparison::Greater),
+    ))(input)
+}
+
+// a command which is not the start of a longer one, `\\le` but not the `\\le` of `\\left`
+fn command<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag(name), not(satisfy(|c| c.is_ascii_alphabetic())))
+}
+
 fn parse_unary_prefix_op(operator_pair: (&str, Expression)) -> Expression {
     let (operator, operand) = operator_pair;
     match operator {
         "-" => Expression::Negate(Box::new(operand)),
+        "+" => operand,
         _ => panic!("Invalid operator"),
     }
 }
@@ -264,6 +1248,7 @@ fn parse_unary_postfix_op(operator_pair: (Expression, &str)) -> Expression {
     let (operand, operator) = operator_pair;
     match operator {
         "!" => Expression::Factorial(Box::new(operand)),
+        "%" => Expression::Percent(Box::new(operand)),
         _ => panic!("Invalid operator"),
     }
 }
@@ -278,123 +1263,716 @@ fn parse_binary_op(operator_pair: (&str, Expression), expr1: Expression) -> Expr
     match operator {
         "+" => Expression::Add(Box::new(expr1), Box::new(expr2)),
         "-" => Expression::Subtract(Box::new(expr1), Box::new(expr2)),
-        "\\cdot" => Expression::Multiply(Box::new(expr1), Box::new(expr2)),
-        "/" => Expression::Divide(Box::new(expr1), Box::new(expr2)),
+        "\\cdot" | "\\times" => Expression::Multiply(Box::new(expr1), Box::new(expr2)),
+        "/" | "\\div" => Expression::Divide(Box::new(expr1), Box::new(expr2)),
         "^" => Expression::Power(Box::new(expr1), Box::new(expr2)),
         "%" => Expression::Modulus(Box::new(expr1), Box::new(expr2)),
         _ => panic!("Invalid operator"),
     }
 }
 
+// LatexOptions: how latexify writes an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatexOptions {
+    // wraps every binary operation in \left( and \right), whatever the precedence, so the output
+    // can be re-parsed unambiguously by looser grammars (numeric coefficients are then not juxtaposed)
+    pub force_parens: bool,
+    // how matrices are written, the [a,b;c,d] shorthand or a standard matrix environment
+    pub matrix_style: MatrixStyle,
+    // the symbol written between the factors of a product
+    pub mult_symbol: MultSymbol,
+    // groups with plain ( and ) instead of \\left( and \\right)
+    pub plain_parens: bool,
+    // the number of significant digits decimals are rounded to, trailing zeros are dropped, None
+    // writes every digit it takes to read the decimal back exactly
+    pub precision: Option<usize>,
+    // writes the sum of a whole number and a proper fraction (as produced by to_mixed_number) as a
+    // mixed number, 3\\frac{1}{2}, which reads back as a product rather than a sum
+    pub mixed_numbers: bool,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        LatexOptions {
+            force_parens: false,
+            matrix_style: MatrixStyle::default(),
+            mult_symbol: MultSymbol::default(),
+            plain_parens: false,
+            precision: Some(DISPLAY_PRECISION),
+            mixed_numbers: false,
+        }
+    }
+}
+
+// MatrixStyle: how latexify writes matrices, the environments render in mathjax and katex where the
+// shorthand does not, and all three are read back by parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixStyle {
+    #[default]
+    Shorthand,
+    // \\begin{pmatrix}a&b\\\\c&d\\end{pmatrix}
+    Pmatrix,
+    // \\begin{bmatrix} a & b \\\\ c & d \\end{bmatrix}, with its columns spaced apart
+    Bmatrix,
+}
+
+// MultSymbol: how latexify separates the factors of a product, numeric coefficients are juxtaposed
+// with what they scale (2x) whichever is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultSymbol {
+    #[default]
+    Cdot,
+    Times,
+    // factors are written side by side (xy), falling back to \\cdot before a number or a sign
+    // the output is for display only, neither parser reads xy back as a product
+    Juxtaposition,
+}
+
 pub fn latexify(expr: &Expression) -> String {
-    match expr {
-        Expression::Atom(a) => a.to_string(),
+    latexify_with(expr, &LatexOptions::default())
+}
 
-        Expression::Negate(e) => match **e {
-            Expression::Atom(_) => "-".to_string() + &latexify(&e),
-            _ => format!("-\\left({}\\right)", &latexify(&e)),
-        },
-        Expression::Factorial(e) => match **e {
-            Expression::Atom(_) => format!("{}!", &latexify(&e)),
-            _ => format!("\\left({}\\right)!", &latexify(&e)),
-        },
-        Expression::Percent(e) => match **e {
-            Expression::Atom(_) => format!("{}%", &latexify(&e)),
-            _ => format!("\\left({}\\right)%", &latexify(&e)),
+pub fn latexify_with(expr: &Expression, options: &LatexOptions) -> String {
+    let mut out = String::new();
+    latexify_into(expr, &mut out, options);
+    out
+}
+
+// latexify with every matrix written as a bmatrix environment
+pub fn latexify_matrix_env(expr: &Expression) -> String {
+    latexify_with(
+        expr,
+        &LatexOptions {
+            matrix_style: MatrixStyle::Bmatrix,
+            ..LatexOptions::default()
         },
+    )
+}
+
+// writes the latex form of an expression into a single reused buffer
+fn latexify_into(expr: &Expression, out: &mut String, options: &LatexOptions) {
+    if options.force_parens && is_binary_operation(expr) {
+        out.push_str(open_paren(options));
+        latexify_node(expr, out, options);
+        out.push_str(close_paren(options));
+    } else {
+        latexify_node(expr, out, options);
+    }
+}
+
+fn latexify_node(expr: &Expression, out: &mut String, options: &LatexOptions) {
+    match expr {
+        Expression::Atom(Atom::Variable(v)) => {
+            match GREEK_LETTERS.iter().find(|(_, letter)| letter == v) {
+                Some((command, _)) => {
+                    out.push('\\');
+                    out.push_str(command);
+                }
+                None => out.push(*v),
+            }
+        }
+        Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))) => {
+            write!(out, "\\frac{{{}}}{{{}}}", n, d).unwrap()
+        }
+        Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) => {
+            out.push_str(&format_decimal(*d, options.precision))
+        }
+        Expression::Atom(Atom::Numeric(Numeric::Complex { re, im })) => {
+            let im = imaginary(*im, options.precision);
+            match (*re == 0.0, im.starts_with('-')) {
+                (true, _) => out.push_str(&im),
+                (false, true) => {
+                    write!(out, "{}{}", format_decimal(*re, options.precision), im).unwrap()
+                }
+                (false, false) => {
+                    write!(out, "{}+{}", format_decimal(*re, options.precision), im).unwrap()
+                }
+            }
+        }
+        Expression::Atom(Atom::Boolean(b)) => out.push_str(if *b { "\\top" } else { "\\bot" }),
+        Expression::Atom(a) => write!(out, "{}", a).unwrap(),
+
+        Expression::Negate(e) => {
+            out.push('-');
+            match **e {
+                _ if options.mixed_numbers && is_mixed_number(e) => latexify_into(e, out, options),
+                _ if is_complex_sum(e) => latexify_parens(e, out, options),
+                Expression::Atom(_) => latexify_into(e, out, options),
+                _ => latexify_parens(e, out, options),
+            }
+        }
+        Expression::Factorial(e) => {
+            match **e {
+                _ if is_complex_sum(e) => latexify_parens(e, out, options),
+                Expression::Atom(_) => latexify_into(e, out, options),
+                _ => latexify_parens(e, out, options),
+            }
+            out.push('!');
+        }
+        Expression::Percent(e) => {
+            match **e {
+                _ if is_complex_sum(e) => latexify_parens(e, out, options),
+                Expression::Atom(_) => latexify_into(e, out, options),
+                _ => latexify_parens(e, out, options),
+            }
+            out.push('%');
+        }
 
-        Expression::Add(l, r) => format!("{}+{}", &latexify(&l), &latexify(&r)),
-        Expression::Subtract(l, r) => format!("{}-{}", &latexify(&l), &latexify(&r)),
-        Expression::Modulus(l, r) => format!("{}%{}", &latexify(&l), &latexify(&r)),
+        Expression::Add(l, r) if options.mixed_numbers && is_mixed_number(expr) => {
+            latexify_into(l, out, options);
+            latexify_into(r, out, options);
+        }
+        // sums group from the left, so a sum as the right operand keeps its parentheses, a-(b+c)
+        Expression::Add(l, r) | Expression::Subtract(l, r) => {
+            latexify_into(l, out, options);
+            out.push(if matches!(expr, Expression::Add(_, _)) {
+                '+'
+            } else {
+                '-'
+            });
+            match **r {
+                _ if is_complex_sum(r) => latexify_parens(r, out, options),
+                _ if options.mixed_numbers && is_mixed_number(r) => latexify_into(r, out, options),
+                Expression::Add(_, _) | Expression::Subtract(_, _) => {
+                    latexify_parens(r, out, options)
+                }
+                _ => latexify_into(r, out, options),
+            }
+        }
+        // the modulus binds like a product, looser operands are grouped and so is a product or
+        // modulus to its right
+        Expression::Modulus(l, r) => {
+            match **l {
+                _ if is_complex_sum(l) => latexify_parens(l, out, options),
+                Expression::Add(_, _) | Expression::Subtract(_, _) => {
+                    latexify_parens(l, out, options)
+                }
+                _ => latexify_into(l, out, options),
+            }
+            out.push('%');
+            match **r {
+                _ if is_complex_sum(r) => latexify_parens(r, out, options),
+                Expression::Add(_, _)
+                | Expression::Subtract(_, _)
+                | Expression::Multiply(_, _)
+                | Expression::Modulus(_, _) => latexify_parens(r, out, options),
+                _ => latexify_into(r, out, options),
+            }
+        }
 
         Expression::Multiply(l, r) => {
-            format!(
-                "{}\\cdot{}",
-                match **l {
-                    Expression::Add(_, _)
-                    | Expression::Subtract(_, _)
-                    | Expression::Modulus(_, _) => format!("\\left({}\\right)", &latexify(&l)),
-                    _ => format!("{}", &latexify(&l)),
-                },
-                match **r {
+            for (i, e) in [l, r].into_iter().enumerate() {
+                let start = out.len();
+                match **e {
+                    _ if is_complex_sum(e) => latexify_parens(e, out, options),
                     Expression::Add(_, _)
                     | Expression::Subtract(_, _)
-                    | Expression::Modulus(_, _) => format!("\\left({}\\right)", &latexify(&r)),
-                    _ => format!("{}", &latexify(&r)),
+                    | Expression::Modulus(_, _) => latexify_parens(e, out, options),
+                    // products group from the left, so a product to the right keeps its parentheses
+                    Expression::Multiply(_, _) if i == 1 => latexify_parens(e, out, options),
+                    // the body of a sum would take in the factors after it
+                    Expression::Sum {
+                        index: _,
+                        lower: _,
+                        upper: _,
+                        body: _,
+                    } if i == 0 => latexify_parens(e, out, options),
+                    _ => latexify_into(e, out, options),
                 }
-            )
+                if i == 0 {
+                    continue;
+                }
+
+                // a non-negative integer or decimal coefficient is juxtaposed with what it scales
+                // (2x), the parser reads no other coefficient back and -3x as the negation of 3x,
+                // so anything else is separated by the chosen symbol
+                let implicit = !options.force_parens
+                    && match **l {
+                        Expression::Atom(Atom::Numeric(Numeric::Integer(n))) => n >= 0,
+                        Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) => {
+                            !d.is_sign_negative()
+                        }
+                        _ => false,
+                    }
+                    && matches!(
+                        **r,
+                        Expression::Atom(Atom::Variable(_))
+                            | Expression::Function { name: _, args: _ }
+                            | Expression::Add(_, _)
+                            | Expression::Subtract(_, _)
+                            | Expression::Modulus(_, _)
+                    );
+                // a digit juxtaposed with e reads back as an exponent (2e+3 is 2000), so it keeps
+                // its symbol
+                let exponent = out[..start].ends_with(|c: char| c.is_ascii_digit())
+                    && out[start..].starts_with(['e', 'E']);
+                let symbol = match options.mult_symbol {
+                    _ if implicit && !exponent => "",
+                    MultSymbol::Cdot => "\\cdot",
+                    MultSymbol::Times => "\\times",
+                    // juxtaposing a number or a sign would merge it with the left factor
+                    MultSymbol::Juxtaposition
+                        if exponent
+                            || out[start..]
+                                .starts_with(|c: char| c.is_ascii_digit() || c == '-') =>
+                    {
+                        "\\cdot"
+                    }
+                    MultSymbol::Juxtaposition => "",
+                };
+                out.insert_str(start, symbol);
+
+                // keeps a command from running into a following letter (\cdot x rather than \cdotx)
+                let start = start + symbol.len();
+                if ends_with_command(&out[..start])
+                    && out[start..].starts_with(|c: char| c.is_alphabetic())
+                {
+                    out.insert(start, ' ');
+                }
+            }
         }
 
         Expression::Divide(l, r) => {
-            format!("\\frac{{{}}}{{{}}}", &latexify(&l), &latexify(&r))
+            out.push_str("\\frac{");
+            latexify_into(l, out, options);
+            out.push_str("}{");
+            latexify_into(r, out, options);
+            out.push('}');
         }
 
         Expression::Power(l, r) => {
-            format!(
-                "{}^{}",
-                match **l {
-                    Expression::Add(_, _)
-                    | Expression::Subtract(_, _)
-                    | Expression::Modulus(_, _)
-                    | Expression::Multiply(_, _)
-                    | Expression::Divide(_, _) => format!("\\left({}\\right)", &latexify(&l)),
-                    _ => format!("{}", &latexify(&l)),
-                },
-                match **r {
-                    Expression::Atom(_) => format!("{}", &latexify(&r)),
-                    _ => format!("{{{}}}", &latexify(&r)),
+            match **l {
+                _ if is_complex_sum(l) => latexify_parens(l, out, options),
+                Expression::Add(_, _)
+                | Expression::Subtract(_, _)
+                | Expression::Modulus(_, _)
+                | Expression::Multiply(_, _)
+                | Expression::Divide(_, _)
+                | Expression::Power(_, _) => latexify_parens(l, out, options),
+                _ => {
+                    let start = out.len();
+                    latexify_into(l, out, options);
+
+                    // a leading sign binds looser than the power, (-2)^2 rather than -2^2
+                    if out[start..].starts_with('-') {
+                        out.truncate(start);
+                        latexify_parens(l, out, options);
+                    }
                 }
-            )
+            }
+            out.push('^');
+            let start = out.len();
+            latexify_into(r, out, options);
+
+            // only a single character can follow ^ unbraced, x^{10} rather than x^10
+            if out[start..].chars().count() > 1 {
+                out.insert(start, '{');
+                out.push('}');
+            }
+        }
+
+        Expression::Function { name, args } if name == "abs" && args.len() == 1 => {
+            out.push_str("\\left|");
+            latexify_into(&args[0], out, options);
+            out.push_str("\\right|");
+        }
+
+        Expression::Function { name, args }
+            if (name == "floor" || name == "ceil") && args.len() == 1 =>
+        {
+            let (open, close) = match name.as_str() {
+                "floor" => ("\\lfloor ", "\\rfloor"),
+                _ => ("\\lceil ", "\\rceil"),
+            };
+            out.push_str(open);
+            latexify_into(&args[0], out, options);
+            out.push_str(close);
+        }
+
+        Expression::Function { name, args } if name == "binom" && args.len() == 2 => {
+            out.push_str("\\binom{");
+            latexify_into(&args[0], out, options);
+            out.push_str("}{");
+            latexify_into(&args[1], out, options);
+            out.push('}');
+        }
+
+        Expression::Function { name, args } if name == "sqrt" && args.len() == 1 => {
+            out.push_str("\\sqrt{");
+            latexify_into(&args[0], out, options);
+            out.push('}');
+        }
+
+        Expression::Function { name, args } if name == "log" && args.len() == 2 => {
+            out.push_str("\\log_{");
+            latexify_into(&args[0], out, options);
+            out.push('}');
+            out.push_str(open_paren(options));
+            latexify_into(&args[1], out, options);
+            out.push_str(close_paren(options));
         }
 
         Expression::Function { name, args } => {
-            let mut out = format!("{}\\left(", name);
+            // names without a command of their own are set upright, so erf is not read as e r f
+            // the primes of a derivative follow the name, \\sin''(x)
+            let base = name.trim_end_matches('\'');
+            match function_command(base) {
+                Some(command) => {
+                    out.push('\\');
+                    out.push_str(command);
+                }
+                None if base.chars().count() > 1 => {
+                    write!(out, "\\operatorname{{{}}}", base).unwrap()
+                }
+                None => out.push_str(base),
+            }
+            out.push_str(&name[base.len()..]);
+            out.push_str(open_paren(options));
             for (i, arg) in args.iter().enumerate() {
                 if i > 0 {
-                    out += ",";
+                    out.push(',');
                 }
-                out += &latexify(&arg);
+                latexify_into(arg, out, options);
             }
-            format!("{}\\right)", out)
+            out.push_str(close_paren(options));
         }
 
         Expression::Vector {
             backing: vec,
             size: _,
         } => {
-            format!("<");
+            out.push('<');
             for (i, e) in vec.iter().enumerate() {
                 if i > 0 {
-                    format!(",");
+                    out.push(',');
+                }
+                latexify_into(e, out, options);
+            }
+            out.push('>');
+        }
+
+        Expression::Matrix {
+            backing: vec,
+            shape: (rs, cs),
+        } if options.matrix_style != MatrixStyle::Shorthand => {
+            let (begin, column, row, end) = match options.matrix_style {
+                MatrixStyle::Bmatrix => ("\\begin{bmatrix} ", " & ", " \\\\ ", " \\end{bmatrix}"),
+                _ => ("\\begin{pmatrix}", "&", "\\\\", "\\end{pmatrix}"),
+            };
+            out.push_str(begin);
+            for r in 0..*rs {
+                if r > 0 {
+                    out.push_str(row);
+                }
+                for c in 0..*cs {
+                    if c > 0 {
+                        out.push_str(column);
+                    }
+                    latexify_into(&vec[(*cs * r + c) as usize], out, options);
                 }
-                format!("{}", e);
             }
-            format!(">")
+            out.push_str(end);
         }
 
         Expression::Matrix {
             backing: vec,
             shape: (rs, cs),
         } => {
-            format!("[");
+            out.push('[');
             for r in 0..*rs {
                 if r > 0 {
-                    format!(";");
+                    out.push(';');
                 }
                 for c in 0..*cs {
                     if c > 0 {
-                        format!(",");
+                        out.push(',');
+                    }
+                    latexify_into(&vec[(*cs * r + c) as usize], out, options);
+                }
+            }
+            out.push(']');
+        }
+
+        Expression::Integral {
+            integrand,
+            var,
+            lower,
+            upper,
+        } => {
+            out.push_str("\\int");
+            if let Some(lower) = lower {
+                out.push_str("_{");
+                latexify_into(lower, out, options);
+                out.push('}');
+            }
+            if let Some(upper) = upper {
+                out.push_str("^{");
+                latexify_into(upper, out, options);
+                out.push('}');
+            }
+            out.push(' ');
+            latexify_into(integrand, out, options);
+            write!(out, " \\, d{}", var).unwrap();
+        }
+        // the body is a product, so a sum as the body is grouped
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => {
+            out.push_str("\\sum_{");
+            latexify_into(&Expression::Atom(Atom::Variable(*index)), out, options);
+            out.push('=');
+            latexify_into(lower, out, options);
+            out.push_str("}^{");
+            latexify_into(upper, out, options);
+            out.push_str("} ");
+            match **body {
+                _ if is_complex_sum(body) => latexify_parens(body, out, options),
+                Expression::Add(_, _) | Expression::Subtract(_, _) => {
+                    latexify_parens(body, out, options)
+                }
+                _ => latexify_into(body, out, options),
+            }
+        }
+
+        Expression::Relation(op, l, r) => {
+            latexify_into(l, out, options);
+            out.push_str(comparison_command(*op));
+            latexify_into(r, out, options);
+        }
+        // chains are written once, 0<x<1, members which do not continue a chain are joined by
+        // \\land
+        Expression::And(es) => {
+            for (i, link) in chain_links(es).into_iter().enumerate() {
+                match link {
+                    Link::Chained(op, r) => {
+                        out.push_str(comparison_command(op));
+                        latexify_into(r, out, options);
+                    }
+                    Link::Joined(e) => {
+                        if i > 0 {
+                            out.push_str("\\land ");
+                        }
+                        match e {
+                            Expression::Or(_) => latexify_parens(e, out, options),
+                            _ => latexify_into(e, out, options),
+                        }
                     }
-                    format!("{}", vec[(*cs * r + c) as usize]);
                 }
             }
-            format!("]")
         }
+        Expression::Or(es) => {
+            for (i, e) in es.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("\\lor ");
+                }
+                latexify_into(e, out, options);
+            }
+        }
+        // a negated relation keeps its parentheses, \\lnot\\left(x>0\\right)
+        Expression::Not(e) => {
+            out.push_str("\\lnot ");
+            match **e {
+                Expression::Atom(_) | Expression::Not(_) => latexify_into(e, out, options),
+                _ => latexify_parens(e, out, options),
+            }
+        }
+
+        Expression::Piecewise(branches) => {
+            out.push_str("\\begin{cases}");
+            for (i, (value, condition)) in branches.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("\\\\");
+                }
+                latexify_into(value, out, options);
+                out.push('&');
+                latexify_into(condition, out, options);
+            }
+            out.push_str("\\end{cases}");
+        }
+    }
+}
+
+// the space after a command keeps it from running into a following letter
+fn comparison_command(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Equal => "=",
+        Comparison::NotEqual => "\\neq ",
+        Comparison::Less => "<",
+        Comparison::LessEqual => "\\leq ",
+        Comparison::Greater => ">",
+        Comparison::GreaterEqual => "\\geq ",
+    }
+}
+
+// latexify (with decimals written in full) followed by parse, every expression the parser can
+// produce comes back structurally equal, which makes this a hook for property tests over generated
+// expressions
+pub fn roundtrip(expr: &Expression) -> Expression {
+    let options = LatexOptions {
+        precision: None,
+        ..LatexOptions::default()
+    };
+    parse(&latexify_with(expr, &options))
+}
+
+// rewrites powers with a negative numeric exponent as reciprocals before latexify, so they render
+// as fractions: x^{-2} -> \\frac{1}{x^2}, x^{-1} -> \\frac{1}{x}
+pub fn reciprocal_form(expr: &Expression) -> Expression {
+    match expr.map_children(reciprocal_form) {
+        Expression::Power(base, exponent) => {
+            let magnitude = match *exponent {
+                Expression::Atom(Atom::Numeric(n)) if f32::from(n) < 0.0 => Some(-n),
+                Expression::Negate(ref e) => match **e {
+                    Expression::Atom(Atom::Numeric(n)) if f32::from(n) > 0.0 => Some(n),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match magnitude {
+                Some(n) => Expression::Divide(
+                    Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(1)))),
+                    match n {
+                        Numeric::Integer(1) => base,
+                        n => Box::new(Expression::Power(
+                            base,
+                            Box::new(Expression::Atom(Atom::Numeric(n))),
+                        )),
+                    },
+                ),
+                None => Expression::Power(base, exponent),
+            }
+        }
+        e => e,
+    }
+}
+
+// rewrites every improper fraction, written as a fraction or as a division of integers, as the
+// sum of a whole number and a proper fraction: \\frac{7}{2} -> 3+\\frac{1}{2}, -7/2 -> -(3+1/2)
+// fractions which divide evenly become whole numbers, proper fractions are left as they are
+pub fn to_mixed_number(expr: &Expression) -> Expression {
+    let mixed = |n: i32, d: i32| {
+        let (n, d) = (i64::from(n), i64::from(d));
+        if d == 0 || n.abs() < d.abs() {
+            return None;
+        }
+
+        let whole = i32::try_from(n.abs() / d.abs()).ok()?;
+        let remainder = n.abs() % d.abs();
+        let number = match remainder {
+            0 => Expression::Atom(Atom::Numeric(Numeric::Integer(whole))),
+            _ => Expression::Add(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(whole)))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Fraction(
+                    i32::try_from(remainder).ok()?,
+                    i32::try_from(d.abs()).ok()?,
+                )))),
+            ),
+        };
+        Some(match (n < 0) != (d < 0) {
+            true => Expression::Negate(Box::new(number)),
+            false => number,
+        })
+    };
+
+    let expr = expr.map_children(to_mixed_number);
+    let parts = match &expr {
+        Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))) => Some((*n, *d)),
+        Expression::Divide(l, r) => match (&**l, &**r) {
+            (
+                Expression::Atom(Atom::Numeric(Numeric::Integer(n))),
+                Expression::Atom(Atom::Numeric(Numeric::Integer(d))),
+            ) => Some((*n, *d)),
+            _ => None,
+        },
+        _ => None,
+    };
+    parts.and_then(|(n, d)| mixed(n, d)).unwrap_or(expr)
+}
+
+// a positive whole number plus a proper fraction, the form to_mixed_number produces
+fn is_mixed_number(expr: &Expression) -> bool {
+    match expr {
+        Expression::Add(l, r) => matches!(
+            (&**l, &**r),
+            (
+                Expression::Atom(Atom::Numeric(Numeric::Integer(w))),
+                Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))),
+            ) if *w > 0 && 0 < *n && n < d
+        ),
+        _ => false,
+    }
+}
+
+fn latexify_parens(expr: &Expression, out: &mut String, options: &LatexOptions) {
+    // binary operations are already wrapped when parentheses are forced
+    if options.force_parens && is_binary_operation(expr) {
+        latexify_into(expr, out, options);
+    } else {
+        out.push_str(open_paren(options));
+        latexify_into(expr, out, options);
+        out.push_str(close_paren(options));
+    }
+}
+
+fn open_paren(options: &LatexOptions) -> &'static str {
+    if options.plain_parens {
+        "("
+    } else {
+        "\\left("
+    }
+}
+
+fn close_paren(options: &LatexOptions) -> &'static str {
+    if options.plain_parens {
+        ")"
+    } else {
+        "\\right)"
+    }
+}
+
+// whether the latex ends with a command name, which a following letter would extend
+fn ends_with_command(latex: &str) -> bool {
+    match latex.rfind('\\') {
+        Some(i) => {
+            let name = &latex[i + 1..];
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
     }
 }
 
+// a complex number with a real part is written as a sum, a+bi, and is grouped like one
+fn is_complex_sum(expr: &Expression) -> bool {
+    matches!(expr, Expression::Atom(Atom::Numeric(Numeric::Complex { re, im: _ })) if *re != 0.0)
+}
+
+fn is_binary_operation(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Add(_, _)
+            | Expression::Subtract(_, _)
+            | Expression::Multiply(_, _)
+            | Expression::Divide(_, _)
+            | Expression::Power(_, _)
+            | Expression::Modulus(_, _)
+    )
+}
+
+#[cfg(test)]
 mod tests {
+    use alloc::{format, string::ToString};
+
     use super::*;
+    use crate::expression::eval::{evaluate, try_eval, EvalContext, Value};
+    use crate::modifier::{
+        adaptable_modifier::AdaptableModifier,
+        default::{approximator, evaluator, simplifier},
+    };
+    use crate::EvalError;
 
     #[test]
     fn complex_latex() {
@@ -452,7 +2030,7 @@ mod tests {
     #[test]
     fn complex_string_latex() {
         assert_eq!(
-            "\\frac{5}{6}\\cdot5+4^{2+x}-1!+arc\\left(6\\right)",
+            "\\frac{5}{6}\\cdot5+4^{2+x}-1!+\\operatorname{arc}\\left(6\\right)",
             crate::expression::latex::latexify(&Expression::Add(
                 Box::new(Expression::Subtract(
                     Box::new(Expression::Add(
@@ -484,4 +2062,1433 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn known_function_latex() {
+        assert_eq!(parse("\\sin(x)"), parse("sin(x)"));
+        assert_eq!(
+            parse("\\sin\\left(x\\right)"),
+            Expression::Function {
+                name: "sin".to_string(),
+                args: vec![Box::new(Expression::Atom(Atom::Variable('x')))],
+            }
+        );
+        assert_eq!(parse("\\arcsin(x)"), parse("asin(x)"));
+        assert_eq!(parse("\\sqrt{x}"), parse("sqrt(x)"));
+    }
+
+    #[test]
+    fn known_function_string_latex() {
+        assert_eq!("\\sin\\left(x\\right)", latexify(&parse("sin(x)")));
+        assert_eq!("\\arcsin\\left(x\\right)", latexify(&parse("\\arcsin(x)")));
+        assert_eq!("\\sqrt{x}", latexify(&parse("sqrt(x)")));
+        assert_eq!(
+            "\\operatorname{arc}\\left(x\\right)",
+            latexify(&parse("arc(x)"))
+        );
+    }
+
+    #[test]
+    fn abs_latex() {
+        let abs = |arg: Expression| Expression::Function {
+            name: "abs".to_string(),
+            args: vec![Box::new(arg)],
+        };
+
+        assert_eq!(parse("|x|"), abs(Expression::Atom(Atom::Variable('x'))));
+        assert_eq!(
+            parse("|x+1|"),
+            abs(Expression::Add(
+                Box::new(Expression::Atom(Atom::Variable('x'))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(1))))
+            ))
+        );
+        assert_eq!(parse("\\left|x+1\\right|"), parse("|x+1|"));
+        assert_eq!(parse("||x|+1|"), abs(parse("|x|+1")));
+        assert_eq!(parse("|x|\\cdot|y|"), parse("abs(x)\\cdot abs(y)"));
+    }
+
+    #[test]
+    fn abs_string_latex() {
+        assert_eq!("\\left|x\\right|", latexify(&parse("|x|")));
+        assert_eq!("\\left|x+1\\right|", latexify(&parse("abs(x+1)")));
+        assert_eq!(
+            "\\left|\\left|x\\right|+1\\right|",
+            latexify(&parse("||x|+1|"))
+        );
+    }
+
+    #[test]
+    fn latexify_into_matches_string_latex() {
+        // output of the allocating latexify, kept to check the buffered writer byte for byte
+        let expr = parse("-\\left(x+1\\right)\\cdot\\sin(x)^{2}+|y|-\\sqrt{z}+<1,2>+[1,2;3,4]+\\left(a+b\\right)^{c\\cdot d}+\\left(x-1\\right)!+-x+f(x,y)\\cdot\\left(u%v\\right)");
+
+        assert_eq!(
+            "-\\left(x+1\\right)\\cdot\\sin\\left(x\\right)^2+\\left|y\\right|-\\sqrt{z}+<1,2>+[1,2;3,4]+\\left(a+b\\right)^{c\\cdot d}+\\left(x-1\\right)!+-x+f\\left(x,y\\right)\\cdot\\left(u%v\\right)",
+            latexify(&expr)
+        );
+        assert_eq!("5%", latexify(&Expression::Percent(Box::new(parse("5")))));
+        assert_eq!(
+            "\\left(x+1\\right)%",
+            latexify(&Expression::Percent(Box::new(parse("x+1"))))
+        );
+    }
+
+    #[test]
+    fn implicit_multiplication_latex() {
+        assert_eq!(latexify(&parse("2\\cdot x")), "2x");
+        assert_eq!(latexify(&parse("2\\cdot 3")), "2\\cdot3");
+        assert_eq!(latexify(&parse("x\\cdot y")), "x\\cdot y");
+        assert_eq!(
+            latexify(&parse("3\\cdot\\sin(x)")),
+            "3\\sin\\left(x\\right)"
+        );
+        assert_eq!(latexify(&parse("2\\cdot(x+1)")), "2\\left(x+1\\right)");
+        assert_eq!(latexify(&parse("2\\cdot x^2")), "2\\cdot x^2");
+        assert_eq!(latexify(&parse("2\\cdot e+3")), "2\\cdot e+3");
+        assert_eq!(latexify(&parse("2\\cdot e-3")), "2\\cdot e-3");
+        assert_eq!(
+            parse(&latexify(&parse("2\\cdot e+3"))),
+            parse("2\\cdot e+3")
+        );
+    }
+
+    #[test]
+    fn implicit_multiplication_string_latex() {
+        for s in ["2x", "3\\sin(x)", "2\\left(x+1\\right)", "2\\sqrt{x}"] {
+            let expr = parse(s);
+            assert!(matches!(expr, Expression::Multiply(_, _)), "{}", s);
+            assert_eq!(parse(&latexify(&expr)), expr);
+        }
+
+        assert_eq!(parse("2x^2"), parse("2\\cdot x^2"));
+        assert!(matches!(parse("(x+1)^2"), Expression::Power(_, _)));
+        assert!(matches!(
+            parse("\\left(x+1\\right)^{2}"),
+            Expression::Power(_, _)
+        ));
+        assert!(matches!(parse("2-x"), Expression::Subtract(_, _)));
+        assert!(matches!(parse("2\\cdot3"), Expression::Multiply(_, _)));
+    }
+
+    #[test]
+    fn percent_latex() {
+        let percent = |e: Expression| Expression::Percent(Box::new(e));
+
+        assert_eq!(
+            parse("50%"),
+            percent(Expression::Atom(Atom::Numeric(Numeric::Integer(50))))
+        );
+        assert_eq!(
+            parse("\\frac{50%}{2}"),
+            Expression::Divide(
+                Box::new(percent(Expression::Atom(Atom::Numeric(Numeric::Integer(
+                    50
+                ))))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+            )
+        );
+        assert_eq!(
+            parse("7%3"),
+            Expression::Modulus(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(7)))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(3)))),
+            )
+        );
+    }
+
+    #[test]
+    fn percent_string_latex() {
+        for s in ["50%", "\\left(x+1\\right)%", "50%\\cdot x", "y%3"] {
+            assert_eq!(latexify(&parse(s)), s);
+        }
+    }
+
+    #[test]
+    fn greek_latex() {
+        assert_eq!(
+            parse("\\alpha + \\beta"),
+            Expression::Add(
+                Box::new(Expression::Atom(Atom::Variable('α'))),
+                Box::new(Expression::Atom(Atom::Variable('β'))),
+            )
+        );
+        assert_ne!(parse("\\alpha"), parse("\\beta"));
+        assert_eq!(parse("\\Omega"), Expression::Atom(Atom::Variable('Ω')));
+        assert_eq!(
+            parse("\\exp(\\eta)"),
+            Expression::Function {
+                name: "exp".to_string(),
+                args: vec![Box::new(Expression::Atom(Atom::Variable('η')))],
+            }
+        );
+    }
+
+    #[test]
+    fn greek_string_latex() {
+        for s in [
+            "\\theta",
+            "\\alpha+\\beta",
+            "2\\theta",
+            "\\sin\\left(\\theta\\right)^2",
+            "x\\cdot\\phi",
+        ] {
+            assert_eq!(latexify(&parse(s)), s);
+        }
+    }
+
+    #[test]
+    fn power_associativity_latex() {
+        assert_eq!(parse("2^2^3"), parse("2^{2^3}"));
+        assert_ne!(parse("2^2^3"), parse("(2^2)^3"));
+        assert_eq!(latexify(&parse("(x^2)^3")), "\\left(x^2\\right)^3");
+        assert_eq!(latexify(&parse("x^{2^3}")), "x^{2^3}");
+
+        for s in ["2^2^3", "(2^2)^3"] {
+            let expr = parse(s);
+            assert_eq!(parse(&latexify(&expr)), expr);
+        }
+    }
+
+    #[test]
+    fn negative_exponent_latex() {
+        let power = |base: &str, n| {
+            Expression::Power(
+                Box::new(parse(base)),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(n)))),
+            )
+        };
+
+        assert_eq!(latexify(&power("x", -1)), "x^{-1}");
+        assert_eq!(latexify(&power("x", -12)), "x^{-12}");
+        assert_eq!(latexify(&parse("x^{-1}")), "x^{-1}");
+        assert_eq!(parse(&latexify(&power("x", -2))), parse("x^{-2}"));
+    }
+
+    #[test]
+    fn mixed_number_latex() {
+        let options = LatexOptions {
+            mixed_numbers: true,
+            ..Default::default()
+        };
+        let fraction = |n, d| Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d)));
+        let mixed = |s: &str| latexify_with(&to_mixed_number(&parse(s)), &options);
+
+        assert_eq!(
+            to_mixed_number(&parse("\\frac{7}{2}")),
+            Expression::Add(Box::new(Expression::int(3)), Box::new(fraction(1, 2)))
+        );
+        assert_eq!(mixed("\\frac{7}{2}"), "3\\frac{1}{2}");
+        assert_eq!(mixed("-\\frac{7}{2}"), "-3\\frac{1}{2}");
+        assert_eq!(mixed("x-\\frac{22}{7}"), "x-3\\frac{1}{7}");
+        assert_eq!(mixed("\\frac{6}{3}"), "2");
+        assert_eq!(mixed("\\frac{1}{2}"), "\\frac{1}{2}");
+        assert_eq!(mixed("\\frac{x}{2}"), "\\frac{x}{2}");
+        assert_eq!(
+            latexify_with(&to_mixed_number(&fraction(7, 2)), &options),
+            "3\\frac{1}{2}"
+        );
+
+        // off by default, the sum is written out
+        assert_eq!(
+            latexify(&to_mixed_number(&parse("\\frac{7}{2}"))),
+            "3+\\frac{1}{2}"
+        );
+        assert_eq!(latexify(&parse("\\frac{7}{2}")), "\\frac{7}{2}");
+    }
+
+    #[test]
+    fn reciprocal_form_latex() {
+        let power = |base: &str, n| {
+            Expression::Power(
+                Box::new(parse(base)),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(n)))),
+            )
+        };
+
+        assert_eq!(
+            latexify(&reciprocal_form(&power("x", -2))),
+            "\\frac{1}{x^2}"
+        );
+        assert_eq!(latexify(&reciprocal_form(&parse("x^{-1}"))), "\\frac{1}{x}");
+        assert_eq!(
+            latexify(&reciprocal_form(&parse("(a+b)^{-3}"))),
+            "\\frac{1}{\\left(a+b\\right)^3}"
+        );
+        assert_eq!(
+            latexify(&reciprocal_form(&parse("y\\cdot x^{-2}"))),
+            "y\\cdot\\frac{1}{x^2}"
+        );
+        assert_eq!(reciprocal_form(&parse("x^{2}")), parse("x^{2}"));
+        assert_eq!(reciprocal_form(&parse("x^{-y}")), parse("x^{-y}"));
+    }
+
+    #[test]
+    fn unary_minus_latex() {
+        let int = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+        let var = |v| Box::new(Expression::Atom(Atom::Variable(v)));
+        let neg = |e| Box::new(Expression::Negate(e));
+
+        assert_eq!(parse("5 - -3"), Expression::Subtract(int(5), neg(int(3))));
+        assert_eq!(
+            parse("-\\left(x+1\\right)"),
+            *neg(Box::new(Expression::Add(var('x'), int(1))))
+        );
+        assert_eq!(
+            parse("3\\cdot -2"),
+            Expression::Multiply(int(3), neg(int(2)))
+        );
+        assert_eq!(parse("x^-2"), parse("x^{-2}"));
+        assert_eq!(
+            parse("-2x"),
+            *neg(Box::new(Expression::Multiply(int(2), var('x'))))
+        );
+        assert_eq!(parse("1-2x"), parse("1-2\\cdot x"));
+    }
+
+    #[test]
+    fn unary_plus_latex() {
+        assert_eq!(parse("+x"), parse("x"));
+        assert_eq!(parse("2 + +3"), parse("2+3"));
+        assert_eq!(parse("+2x"), parse("2\\cdot x"));
+        assert_eq!(parse("\\frac{+1}{-x}"), parse("\\frac{1}{-x}"));
+        assert_eq!(parse("x^+2"), parse("x^{2}"));
+        assert_eq!(parse("+50%+1"), parse("50%+1"));
+    }
+
+    #[test]
+    fn times_div_latex() {
+        assert_eq!(parse("2\\times3"), parse("2\\cdot3"));
+        assert_eq!(parse("6\\div2"), parse("6/2"));
+        assert_eq!(parse("a \\times b \\div c"), parse("a\\cdot b/c"));
+        assert_eq!(parse("2\\times x+1"), parse("2\\cdot x+1"));
+        assert_eq!(parse("50%\\times2"), parse("50%\\cdot2"));
+        assert_eq!(latexify(&parse("6\\div2")), "\\frac{6}{2}");
+    }
+
+    #[test]
+    fn multi_digit_exponent_latex() {
+        assert_eq!(latexify(&parse("4^{10}")), "4^{10}");
+        assert_eq!(parse(&latexify(&parse("4^{10}"))), parse("4^{10}"));
+        assert_eq!(latexify(&parse("x^{2.5}")), "x^{2.5}");
+        assert_eq!(latexify(&parse("x^2")), "x^2");
+        assert_eq!(latexify(&parse("x^y")), "x^y");
+    }
+
+    #[test]
+    fn pmatrix_latex() {
+        let matrix = parse("[1,2;3,4]");
+
+        assert_eq!(
+            parse("\\begin{pmatrix} 1 & 2 \\\\ 3 & 4 \\end{pmatrix}"),
+            matrix
+        );
+        assert_eq!(
+            parse("\\begin{bmatrix}1&2\\\\3&4\\\\\\end{bmatrix}"),
+            matrix
+        );
+        assert_eq!(
+            parse("\\begin{pmatrix}x+1&\\frac{y}{2}\\end{pmatrix}"),
+            parse("[x+1,\\frac{y}{2}]")
+        );
+    }
+
+    #[test]
+    fn pmatrix_string_latex() {
+        let options = LatexOptions {
+            matrix_style: MatrixStyle::Pmatrix,
+            ..Default::default()
+        };
+        let matrix = parse("[1,2;3,x]");
+
+        assert_eq!(
+            latexify_with(&matrix, &options),
+            "\\begin{pmatrix}1&2\\\\3&x\\end{pmatrix}"
+        );
+        assert_eq!(parse(&latexify_with(&matrix, &options)), matrix);
+        assert_eq!(latexify(&matrix), "[1,2;3,x]");
+    }
+
+    #[test]
+    fn bmatrix_string_latex() {
+        let matrix = parse("[1,2;3,x]");
+
+        assert_eq!(
+            latexify_matrix_env(&matrix),
+            "\\begin{bmatrix} 1 & 2 \\\\ 3 & x \\end{bmatrix}"
+        );
+        assert_eq!(parse(&latexify_matrix_env(&matrix)), matrix);
+        assert_eq!(
+            latexify_matrix_env(&parse("[a,b]+[c,d]")),
+            "\\begin{bmatrix} a & b \\end{bmatrix}+\\begin{bmatrix} c & d \\end{bmatrix}"
+        );
+    }
+
+    #[test]
+    fn pmatrix_errors_latex() {
+        let options = ParseOptions::default();
+
+        assert_eq!(
+            parse_with_options("\\begin{pmatrix}1&2\\\\3\\end{pmatrix}", &options),
+            Err(ParseError::InvalidSyntax { position: 21 })
+        );
+        assert_eq!(
+            parse_with_options("\\begin{pmatrix}1&2\\end{bmatrix}", &options),
+            Err(ParseError::InvalidSyntax { position: 18 })
+        );
+        assert_eq!(
+            parse_with_options("\\begin{bmatrix}1\\\\2 \\\\ \\end{pmatrix}", &options),
+            Err(ParseError::InvalidSyntax { position: 23 })
+        );
+    }
+
+    #[test]
+    fn force_parens_latex() {
+        let options = LatexOptions {
+            force_parens: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            latexify_with(&parse("a+b\\cdot c"), &options),
+            "\\left(a+\\left(b\\cdot c\\right)\\right)"
+        );
+        assert_eq!(
+            latexify_with(&parse("2\\cdot(x+1)^2"), &options),
+            "\\left(2\\cdot\\left(\\left(x+1\\right)^2\\right)\\right)"
+        );
+        assert_eq!(
+            latexify_with(&parse("\\sin(\\frac{x}{2})"), &options),
+            "\\sin\\left(\\left(\\frac{x}{2}\\right)\\right)"
+        );
+        assert_eq!(latexify_with(&parse("-x"), &options), "-x");
+
+        let expr = parse("a-b\\cdot c^2+d");
+        assert_eq!(parse(&latexify_with(&expr, &options)), expr);
+    }
+
+    #[test]
+    fn scientific_latex() {
+        assert!(matches!(
+            parse("1e3"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 1000.0
+        ));
+        assert!(matches!(
+            parse("2E-4"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 2e-4
+        ));
+        assert_eq!(parse("e"), Expression::Atom(Atom::Variable('e')));
+        assert_eq!(
+            parse("\\frac{e}{2}"),
+            Expression::Divide(
+                Box::new(Expression::Atom(Atom::Variable('e'))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+            )
+        );
+    }
+
+    #[test]
+    fn operatorname_latex() {
+        assert_eq!(
+            parse("\\operatorname{mod}(7, 2)"),
+            Expression::Modulus(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(7)))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+            )
+        );
+        assert_eq!(
+            parse("\\operatorname{f}(x)"),
+            Expression::Function {
+                name: "f".to_string(),
+                args: vec![Box::new(Expression::Atom(Atom::Variable('x')))],
+            }
+        );
+    }
+
+    #[test]
+    fn prime_latex() {
+        let x = || Box::new(Expression::Atom(Atom::Variable('x')));
+
+        assert_eq!(
+            parse("f'(x)"),
+            Expression::Function {
+                name: "f'".to_string(),
+                args: vec![x()],
+            }
+        );
+        assert_eq!(
+            parse("\\sin'(x)"),
+            Expression::Function {
+                name: "sin'".to_string(),
+                args: vec![x()],
+            }
+        );
+        assert_eq!(latexify(&parse("\\sin'(x)")), "\\sin'\\left(x\\right)");
+        for s in [
+            "f''(x)",
+            "\\cos''(2x)",
+            "\\operatorname{erf}'(x)",
+            "f'(x)+f(x)",
+        ] {
+            assert_eq!(roundtrip(&parse(s)), parse(s), "{}", s);
+        }
+
+        // known functions have their derivative evaluated, unknown ones can not be
+        assert_eq!(
+            evaluate(&parse("\\sin'(0)")),
+            Ok(Value::Scalar(Numeric::Decimal(1.0)))
+        );
+        assert_eq!(
+            evaluate(&parse("\\cos''(0)")),
+            Ok(Value::Scalar(Numeric::Decimal(-1.0)))
+        );
+        assert_eq!(
+            evaluate(&parse("f'(0)")),
+            Err(EvalError::UnknownFunction("f'".to_string()))
+        );
+    }
+
+    #[test]
+    fn operatorname_roundtrip_latex() {
+        let erf = parse("\\operatorname{erf}(x)");
+        assert_eq!(
+            erf,
+            Expression::Function {
+                name: "erf".to_string(),
+                args: vec![Box::new(Expression::Atom(Atom::Variable('x')))],
+            }
+        );
+        assert_eq!(latexify(&erf), "\\operatorname{erf}\\left(x\\right)");
+        assert_eq!(roundtrip(&erf), erf);
+
+        assert_eq!(
+            latexify(&parse("\\operatorname{sgn}(x)+f(x)+\\sin(x)")),
+            "\\operatorname{sgn}\\left(x\\right)+f\\left(x\\right)+\\sin\\left(x\\right)"
+        );
+        for s in ["\\operatorname{mod1}(x)", "\\operatorname{clamp}(x,0,1)"] {
+            assert_eq!(roundtrip(&parse(s)), parse(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn utility_function_latex() {
+        assert_eq!(parse("\\operatorname{sgn}(-2)"), parse("sgn(-2)"));
+        assert_eq!(
+            evaluate(&parse("\\operatorname{sgn}(-2)")),
+            Ok(Value::Scalar(Numeric::Integer(-1)))
+        );
+        assert_eq!(
+            evaluate(&parse("\\operatorname{clamp}\\left(5,0,3\\right)")),
+            Ok(Value::Scalar(Numeric::Integer(3)))
+        );
+    }
+
+    #[test]
+    fn floor_division_latex() {
+        let expr = parse("\\lfloor 7/2 \\rfloor");
+        assert_eq!(
+            expr,
+            Expression::Function {
+                name: "floor".to_string(),
+                args: vec![Box::new(Expression::Divide(
+                    Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(7)))),
+                    Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+                ))],
+            }
+        );
+
+        let value = expr
+            .evaluate_im::<AdaptableModifier, AdaptableModifier, AdaptableModifier, 100>(
+                &approximator(),
+                &evaluator(),
+                &simplifier(),
+            )
+            .1
+            .unwrap();
+        assert_eq!(value, Expression::Atom(Atom::Numeric(Numeric::Integer(3))));
+    }
+
+    #[test]
+    fn binom_latex() {
+        let binom = Expression::Function {
+            name: "binom".to_string(),
+            args: vec![
+                Box::new(Expression::Atom(Atom::Variable('n'))),
+                Box::new(parse("k-1")),
+            ],
+        };
+        assert_eq!(parse("\\binom{n}{k-1}"), binom);
+        assert_eq!(parse("\\binom {n} {k-1}"), binom);
+        assert_eq!(latexify(&binom), "\\binom{n}{k-1}");
+        assert_eq!(roundtrip(&binom), binom);
+
+        match parse("\\binom{5}{2} = 10") {
+            Expression::Relation(Comparison::Equal, l, r) => {
+                assert_eq!(evaluate(&l), evaluate(&r));
+                assert!(matches!(
+                    evaluate(&l),
+                    Ok(Value::Scalar(Numeric::Integer(10)))
+                ));
+            }
+            e => panic!("expected an equation, found {:?}", e),
+        }
+    }
+
+    #[test]
+    fn floor_ceil_latex() {
+        let function = |name: &str, arg: Expression| Expression::Function {
+            name: name.to_string(),
+            args: vec![Box::new(arg)],
+        };
+
+        assert_eq!(
+            parse("\\lfloor x \\rfloor"),
+            function("floor", Expression::Atom(Atom::Variable('x')))
+        );
+        assert_eq!(parse("\\lceil x+1 \\rceil"), function("ceil", parse("x+1")));
+        assert_eq!(
+            parse("\\left\\lceil\\lfloor x\\rfloor\\right\\rceil"),
+            function(
+                "ceil",
+                function("floor", Expression::Atom(Atom::Variable('x')))
+            )
+        );
+
+        match parse("\\lfloor 3.7 \\rfloor = 3") {
+            Expression::Relation(Comparison::Equal, l, r) => {
+                assert_eq!(evaluate(&l), evaluate(&r));
+                assert!(matches!(
+                    evaluate(&l),
+                    Ok(Value::Scalar(Numeric::Integer(3)))
+                ));
+            }
+            e => panic!("expected an equation, found {:?}", e),
+        }
+        assert_eq!(
+            evaluate(&parse("\\lceil \\frac{7}{2} \\rceil")),
+            Ok(Value::Scalar(Numeric::Integer(4)))
+        );
+    }
+
+    #[test]
+    fn floor_ceil_string_latex() {
+        assert_eq!("\\lfloor x\\rfloor", latexify(&parse("floor(x)")));
+        assert_eq!("\\lceil x+1\\rceil", latexify(&parse("ceil(x+1)")));
+
+        for s in [
+            "\\lfloor x\\rfloor",
+            "\\lceil \\frac{x}{2}\\rceil",
+            "2\\lfloor y\\rfloor",
+        ] {
+            assert_eq!(latexify(&parse(s)), s);
+        }
+    }
+
+    #[test]
+    fn parse_with_options_latex() {
+        assert_eq!(
+            parse_with_options("\\frac{1}{x}", &ParseOptions::default()),
+            Ok(parse("\\frac{1}{x}"))
+        );
+        assert_eq!(
+            parse_with_options("1+2)", &ParseOptions::default()),
+            Err(ParseError::InvalidSyntax { position: 3 })
+        );
+        assert!(matches!(
+            parse_with_options("99999999999", &ParseOptions::default()),
+            Ok(Expression::Atom(Atom::Numeric(Numeric::Decimal(d)))) if d == 99999999999.0
+        ));
+        assert!(matches!(
+            parse_with_options("1.2.3", &ParseOptions::default()),
+            Err(ParseError::InvalidSyntax { position: _ })
+        ));
+    }
+
+    #[test]
+    fn operator_not_variable_latex() {
+        let options = ParseOptions::default();
+        for s in ["+", ")", ",", "x+)", "2*,"] {
+            assert!(
+                matches!(
+                    parse_with_options(s, &options),
+                    Err(ParseError::InvalidSyntax { position: _ })
+                ),
+                "{}",
+                s
+            );
+        }
+        assert_eq!(
+            parse_with_options("a+b", &options),
+            Ok(Expression::Add(
+                Box::new(Expression::Atom(Atom::Variable('a'))),
+                Box::new(Expression::Atom(Atom::Variable('b')))
+            ))
+        );
+    }
+
+    #[test]
+    fn spacing_commands_latex() {
+        assert_eq!(parse("2\\,x"), parse("2x"));
+        assert_eq!(parse("a \\; + \\quad b"), parse("a+b"));
+        assert_eq!(parse("\\frac{1}{2}\\!x~y"), parse("\\frac{1}{2}xy"));
+        assert_eq!(parse("x\\qquad=\\:1"), parse("x=1"));
+        assert_eq!(
+            parse("\\begin{pmatrix} 1 & 2 \\\\ 3 & 4 \\end{pmatrix}"),
+            parse("[1, 2; 3, 4]")
+        );
+        assert_eq!(
+            parse_with_options("1 \\, + \\, )", &ParseOptions::default()),
+            Err(ParseError::InvalidSyntax { position: 5 })
+        );
+    }
+
+    #[test]
+    fn cases_latex() {
+        let abs = parse("\\begin{cases} x & x>0 \\\\ -x & x<=0 \\end{cases}");
+
+        assert_eq!(
+            abs,
+            Expression::Piecewise(vec![
+                (parse("x"), parse("x>0")),
+                (parse("-x"), parse("x\\le 0")),
+            ])
+        );
+        assert_eq!(
+            latexify(&abs),
+            "\\begin{cases}x&x>0\\\\-x&x\\leq 0\\end{cases}"
+        );
+        assert_eq!(parse(&latexify(&abs)), abs);
+
+        let at = |x| {
+            let ctx = EvalContext {
+                variables: [('x', Numeric::Integer(x))].into_iter().collect(),
+            };
+            try_eval(&abs, &ctx)
+        };
+        assert_eq!(at(3), Ok(Value::Scalar(Numeric::Integer(3))));
+        assert_eq!(at(-4), Ok(Value::Scalar(Numeric::Integer(4))));
+        assert_eq!(at(0), Ok(Value::Scalar(Numeric::Integer(0))));
+    }
+
+    #[test]
+    fn cases_no_branch_latex() {
+        let positive = parse("\\begin{cases}1&x>0\\\\\\end{cases}");
+        let ctx = EvalContext {
+            variables: [('x', Numeric::Integer(-1))].into_iter().collect(),
+        };
+
+        assert_eq!(
+            positive,
+            Expression::Piecewise(vec![(parse("1"), parse("x>0"))])
+        );
+        assert_eq!(try_eval(&positive, &ctx), Err(EvalError::DomainError));
+    }
+
+    #[test]
+    fn unsupported_environment_latex() {
+        let options = ParseOptions::default();
+        assert_eq!(
+            parse_with_options("1+\\begin{align}x\\end{align}", &options),
+            Err(ParseError::Unsupported {
+                construct: "align".to_string(),
+                position: 2,
+            })
+        );
+        assert_eq!(
+            parse_with_options("\\begin{gather}x\\end{gather}", &options)
+                .unwrap_err()
+                .to_string(),
+            "Unsupported construct gather at byte 0"
+        );
+    }
+
+    #[test]
+    fn max_length_latex() {
+        let options = ParseOptions {
+            max_length: Some(16),
+            ..Default::default()
+        };
+
+        assert_eq!(parse_with_options("1+2", &options), Ok(parse("1+2")));
+        // deep enough that it would overflow the stack if it were parsed
+        assert_eq!(
+            parse_with_options(&"(".repeat(1_000_000), &options),
+            Err(ParseError::InputTooLong {
+                length: 1_000_000,
+                max_length: 16
+            })
+        );
+    }
+
+    #[test]
+    fn max_depth_latex() {
+        let options = ParseOptions::default();
+
+        assert_eq!(
+            parse_with_options(
+                &format!("{}x{}", "(".repeat(10_000), ")".repeat(10_000)),
+                &options
+            ),
+            Err(ParseError::TooDeep { max_depth: 64 })
+        );
+        assert_eq!(
+            parse_with_options(&format!("-{}", "\\frac{1}{".repeat(10_000)), &options),
+            Err(ParseError::TooDeep { max_depth: 64 })
+        );
+        assert_eq!(
+            parse_with_options(&"-".repeat(10_000), &options),
+            Err(ParseError::TooDeep { max_depth: 64 })
+        );
+
+        let nested = format!("{}x{}", "(".repeat(32), ")".repeat(32));
+        assert_eq!(parse_with_options(&nested, &options), Ok(parse("x")));
+        assert_eq!(
+            parse_with_options(
+                &nested,
+                &ParseOptions {
+                    max_depth: Some(16),
+                    ..Default::default()
+                }
+            ),
+            Err(ParseError::TooDeep { max_depth: 16 })
+        );
+        assert_eq!(
+            parse_with_options(
+                "\\left|x\\right| + |y| \\cdot 2^{3^{-4}}",
+                &ParseOptions {
+                    max_depth: Some(5),
+                    ..Default::default()
+                }
+            ),
+            Ok(parse("\\left|x\\right| + |y| \\cdot 2^{3^{-4}}"))
+        );
+    }
+
+    // parse fails the same way on input too deep to recurse through, rather than overflowing
+    #[test]
+    #[should_panic(expected = "failed to parse")]
+    fn parse_too_deep_latex() {
+        parse(&format!("{}x{}", "(".repeat(200_000), ")".repeat(200_000)));
+    }
+
+    #[test]
+    fn identifiers_latex() {
+        let lowercase = ParseOptions {
+            identifiers: Identifiers::Only("abcdefghijklmnopqrstuvwxyz"),
+            ..Default::default()
+        };
+
+        assert_eq!(parse_with_options("x+2y", &lowercase), Ok(parse("x+2y")));
+        assert_eq!(
+            parse_with_options("Q", &lowercase),
+            Err(ParseError::InvalidSyntax { position: 0 })
+        );
+        assert_eq!(
+            parse_with_options("x+Q", &lowercase),
+            Err(ParseError::InvalidSyntax { position: 1 })
+        );
+        // commands and function names are not single character variables
+        assert_eq!(
+            parse_with_options("\\theta+Q(x)", &lowercase),
+            Ok(parse("\\theta+Q(x)"))
+        );
+
+        let primed = ParseOptions {
+            identifiers: Identifiers::Extended("'"),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_options("'+x", &primed),
+            Ok(Expression::Add(
+                Box::new(Expression::Atom(Atom::Variable('\''))),
+                Box::new(Expression::Atom(Atom::Variable('x')))
+            ))
+        );
+        assert!(parse_with_options("'+x", &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn roundtrip_latex() {
+        let x = || Box::new(Expression::Atom(Atom::Variable('x')));
+        let n = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+
+        for expr in [
+            *n(42),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(2.5))),
+            *x(),
+            Expression::Atom(Atom::Variable('α')),
+            Expression::Negate(x()),
+            Expression::Factorial(n(5)),
+            Expression::Percent(n(50)),
+            Expression::Add(x(), n(1)),
+            Expression::Subtract(x(), n(1)),
+            Expression::Multiply(n(2), x()),
+            Expression::Multiply(x(), Box::new(Expression::Add(x(), n(1)))),
+            Expression::Divide(x(), Box::new(Expression::Divide(n(1), x()))),
+            Expression::Power(x(), n(10)),
+            Expression::Power(Box::new(Expression::Power(x(), n(2))), n(3)),
+            Expression::Modulus(x(), n(3)),
+            Expression::Function {
+                name: "sin".to_string(),
+                args: vec![x()],
+            },
+            Expression::Function {
+                name: "f".to_string(),
+                args: vec![x(), n(2)],
+            },
+            Expression::Function {
+                name: "abs".to_string(),
+                args: vec![Box::new(Expression::Negate(x()))],
+            },
+            Expression::Function {
+                name: "sqrt".to_string(),
+                args: vec![Box::new(Expression::Add(x(), n(1)))],
+            },
+            Expression::Function {
+                name: "floor".to_string(),
+                args: vec![x()],
+            },
+            Expression::Vector {
+                backing: vec![x(), Box::new(Expression::Add(x(), n(1))), n(3)],
+                size: 3,
+            },
+            Expression::Matrix {
+                backing: vec![n(1), x(), Box::new(Expression::Negate(x())), n(4)],
+                shape: (2, 2),
+            },
+            Expression::Matrix {
+                backing: vec![n(1), n(2), n(3)],
+                shape: (1, 3),
+            },
+            Expression::Multiply(n(2), Box::new(Expression::Power(x(), n(2)))),
+            Expression::Divide(
+                Box::new(Expression::Vector {
+                    backing: vec![x(), n(1)],
+                    size: 2,
+                }),
+                n(2),
+            ),
+            Expression::Add(
+                Box::new(Expression::Matrix {
+                    backing: vec![x(), Box::new(Expression::Divide(x(), n(2)))],
+                    shape: (2, 1),
+                }),
+                x(),
+            ),
+            Expression::Multiply(
+                x(),
+                Box::new(Expression::Vector {
+                    backing: vec![x()],
+                    size: 1,
+                }),
+            ),
+            Expression::Function {
+                name: "f".to_string(),
+                args: vec![Box::new(Expression::Vector {
+                    backing: vec![x(), n(1)],
+                    size: 2,
+                })],
+            },
+            Expression::Vector {
+                backing: vec![Box::new(Expression::Vector {
+                    backing: vec![x(), n(1)],
+                    size: 2,
+                })],
+                size: 1,
+            },
+            Expression::Negate(Box::new(Expression::Matrix {
+                backing: vec![x(), n(1)],
+                shape: (1, 2),
+            })),
+            Expression::Power(
+                Box::new(Expression::Matrix {
+                    backing: vec![x(), n(1), n(1), x()],
+                    shape: (2, 2),
+                }),
+                n(2),
+            ),
+            Expression::Factorial(Box::new(Expression::Factorial(n(3)))),
+            Expression::Negate(Box::new(Expression::Negate(x()))),
+            Expression::Power(Box::new(Expression::Negate(n(2))), n(2)),
+            Expression::Add(
+                Box::new(Expression::Multiply(
+                    n(2),
+                    Box::new(Expression::Atom(Atom::Variable('e'))),
+                )),
+                n(3),
+            ),
+            Expression::Multiply(
+                x(),
+                Box::new(Expression::Multiply(
+                    Box::new(Expression::Atom(Atom::Variable('y'))),
+                    Box::new(Expression::Atom(Atom::Variable('z'))),
+                )),
+            ),
+        ] {
+            assert_eq!(roundtrip(&expr), expr, "{}", latexify(&expr));
+        }
+
+        // the parser never produces negative or fractional literals, they come back as operations
+        assert_eq!(roundtrip(&n(-3)), Expression::Negate(n(3)));
+        assert_eq!(
+            roundtrip(&Expression::Power(n(-2), n(2))),
+            Expression::Power(Box::new(Expression::Negate(n(2))), n(2))
+        );
+        assert_eq!(
+            roundtrip(&Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2)))),
+            Expression::Divide(n(1), n(2))
+        );
+        // so only non-negative integer and decimal coefficients are juxtaposed
+        let numeric = |n| Box::new(Expression::Atom(Atom::Numeric(n)));
+        let i = || numeric(Numeric::I);
+        for factor in [
+            x(),
+            Box::new(parse("\\sin(x)")),
+            Box::new(Expression::Add(x(), n(1))),
+        ] {
+            for (coefficient, parsed) in [
+                (Numeric::Fraction(1, 2), Expression::Divide(n(1), n(2))),
+                (
+                    Numeric::Complex { re: 1.0, im: 2.0 },
+                    Expression::Add(n(1), Box::new(Expression::Multiply(n(2), i()))),
+                ),
+                (
+                    Numeric::Complex { re: 0.0, im: 2.0 },
+                    Expression::Multiply(n(2), i()),
+                ),
+                (Numeric::Integer(-3), Expression::Negate(n(3))),
+                (
+                    Numeric::Decimal(-2.5),
+                    Expression::Negate(numeric(Numeric::Decimal(2.5))),
+                ),
+            ] {
+                let expr = Expression::Multiply(numeric(coefficient), factor.clone());
+                assert_eq!(
+                    roundtrip(&expr),
+                    Expression::Multiply(Box::new(parsed), factor.clone()),
+                    "{}",
+                    latexify(&expr)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mult_symbol_latex() {
+        let with = |mult_symbol, s| {
+            latexify_with(
+                &parse(s),
+                &LatexOptions {
+                    mult_symbol,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(with(MultSymbol::Cdot, "x\\cdot y"), "x\\cdot y");
+        assert_eq!(with(MultSymbol::Cdot, "2\\cdot x"), "2x");
+        assert_eq!(with(MultSymbol::Times, "x\\cdot y"), "x\\times y");
+        assert_eq!(with(MultSymbol::Times, "2\\cdot 3"), "2\\times3");
+        assert_eq!(with(MultSymbol::Times, "2\\cdot x"), "2x");
+        assert_eq!(with(MultSymbol::Juxtaposition, "x\\cdot y"), "xy");
+        assert_eq!(
+            with(MultSymbol::Juxtaposition, "\\alpha\\cdot x\\cdot\\sin(x)"),
+            "\\alpha x\\sin\\left(x\\right)"
+        );
+        assert_eq!(with(MultSymbol::Juxtaposition, "x\\cdot 2"), "x\\cdot2");
+        assert_eq!(with(MultSymbol::Juxtaposition, "x\\cdot(-y)"), "x\\cdot-y");
+
+        let expr = parse("a\\cdot b^2\\cdot\\frac{c}{d}");
+        for mult_symbol in [MultSymbol::Cdot, MultSymbol::Times] {
+            assert_eq!(
+                parse(&with(mult_symbol, "a\\cdot b^2\\cdot\\frac{c}{d}")),
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn plain_parens_latex() {
+        let options = LatexOptions {
+            plain_parens: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            latexify_with(&parse("2\\cdot(x+1)\\cdot\\sin(x)"), &options),
+            "2(x+1)\\cdot\\sin(x)"
+        );
+        assert_eq!(latexify(&parse("\\sin(x)")), "\\sin\\left(x\\right)");
+
+        let expr = parse("(a+b)^{c-d}\\cdot f(x,y)");
+        assert_eq!(parse(&latexify_with(&expr, &options)), expr);
+    }
+
+    #[test]
+    fn complex_number_latex() {
+        let complex = |re, im| Expression::Atom(Atom::Numeric(Numeric::Complex { re, im }));
+
+        assert_eq!(latexify(&complex(3.0, 2.0)), "3+2i");
+        assert_eq!(latexify(&complex(3.0, -1.0)), "3-i");
+        assert_eq!(latexify(&complex(0.0, 2.5)), "2.5i");
+        assert_eq!(
+            latexify(&Expression::Power(
+                Box::new(complex(1.0, 1.0)),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2))))
+            )),
+            "\\left(1+i\\right)^2"
+        );
+
+        assert_eq!(
+            evaluate(&parse("2i\\cdot i+\\sqrt{-4}")),
+            Ok(Value::Scalar(Numeric::Complex { re: -2.0, im: 2.0 }))
+        );
+        assert_eq!(
+            evaluate(&roundtrip(&complex(3.0, -2.0))),
+            Ok(Value::Scalar(Numeric::Complex { re: 3.0, im: -2.0 }))
+        );
+    }
+
+    #[test]
+    fn log_base_latex() {
+        let log = |b: Expression, x: Expression| Expression::Function {
+            name: "log".to_string(),
+            args: vec![Box::new(b), Box::new(x)],
+        };
+        let int = |i| Expression::Atom(Atom::Numeric(Numeric::Integer(i)));
+
+        assert_eq!(parse("\\log_{2}(8)"), log(int(2), int(8)));
+        assert_eq!(parse("\\log_2(8)"), log(int(2), int(8)));
+        assert_eq!(
+            parse("\\log_{b+1}\\left(x\\right)"),
+            log(parse("b+1"), Expression::Atom(Atom::Variable('x')))
+        );
+        assert_eq!(latexify(&log(int(2), int(8))), "\\log_{2}\\left(8\\right)");
+        assert_eq!(
+            roundtrip(&parse("\\log_{10}(x^2)")),
+            parse("\\log_{10}(x^2)")
+        );
+
+        let value = f32::from(evaluate(&parse("\\log_{2}(8)")).unwrap().scalar().unwrap());
+        assert!((value - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integral_latex() {
+        let x = || Box::new(Expression::Atom(Atom::Variable('x')));
+        let int = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+        let definite = Expression::Integral {
+            integrand: x(),
+            var: 'x',
+            lower: Some(int(0)),
+            upper: Some(int(1)),
+        };
+
+        assert_eq!(parse("\\int_{0}^{1} x dx"), definite);
+        assert_eq!(parse("\\int_0^1 x \\, dx"), definite);
+        assert_eq!(
+            parse("\\int x^2+1\\,dx"),
+            Expression::Integral {
+                integrand: Box::new(parse("x^2+1")),
+                var: 'x',
+                lower: None,
+                upper: None,
+            }
+        );
+        assert_eq!(
+            parse("\\int_0^1 x dx + \\int_0^1 y dy"),
+            Expression::Add(
+                Box::new(definite.clone()),
+                Box::new(parse("\\int_0^1 y \\, dy"))
+            )
+        );
+        assert_eq!(
+            parse("\\int_0^1 \\int_0^x x\\cdot y \\, dy \\, dx"),
+            Expression::Integral {
+                integrand: Box::new(Expression::Integral {
+                    integrand: Box::new(parse("x\\cdot y")),
+                    var: 'y',
+                    lower: Some(int(0)),
+                    upper: Some(x()),
+                }),
+                var: 'x',
+                lower: Some(int(0)),
+                upper: Some(int(1)),
+            }
+        );
+
+        assert_eq!(latexify(&definite), "\\int_{0}^{1} x \\, dx");
+        for s in [
+            "\\int_{0}^{1} x \\, dx",
+            "\\int \\sin(t)+t \\, dt",
+            "2\\cdot\\int_{a}^{b+1} x^2 \\, dx",
+        ] {
+            assert_eq!(roundtrip(&parse(s)), parse(s));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn nested_integral_latex() {
+        let options = ParseOptions::default();
+        let start = std::time::Instant::now();
+        let nested = |k: usize, d: usize| "\\int ".repeat(k) + "x" + &" dx + x".repeat(d);
+
+        assert!(parse_with_options(&nested(40, 39), &options).is_err());
+        assert!(parse_with_options(&nested(40, 40), &options).is_ok());
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn decimal_precision_latex() {
+        let sum = evaluate(&parse("0.1+0.2")).unwrap().scalar().unwrap();
+        let decimal = |d| Expression::Atom(Atom::Numeric(Numeric::Decimal(d)));
+        let precision = |p| LatexOptions {
+            precision: p,
+            ..LatexOptions::default()
+        };
+
+        assert_eq!(latexify(&Expression::Atom(Atom::Numeric(sum))), "0.3");
+        assert_eq!(sum.to_string(), "0.3");
+        // whole decimals keep their point when displayed, so they do not read as integers
+        assert_eq!(Numeric::Decimal(2.0).to_string(), "2.0");
+        assert_eq!(Numeric::Decimal(1.9999999).to_string(), "2.0");
+        // a decimal with more whole digits than the precision is written in scientific notation
+        assert_eq!(Numeric::Decimal(-1234567.8).to_string(), "-1.23457e6");
+        assert_eq!(Numeric::Decimal(123456789.0).to_string(), "1.23457e8");
+        assert_eq!(Numeric::Decimal(1e20).to_string(), "1e20");
+        assert_eq!(Numeric::Decimal(123456.0).to_string(), "123456.0");
+        assert_eq!(latexify(&decimal(2.5)), "2.5");
+        assert_eq!(latexify(&decimal(-0.00012345678)), "-0.000123457");
+        assert_eq!(latexify(&decimal(1234567.8)), "1.23457e6");
+        assert_eq!(parse(&latexify(&decimal(1e20))), decimal(1e20));
+        assert_eq!(
+            latexify_with(&decimal(1.23456), &precision(Some(3))),
+            "1.23"
+        );
+        assert_eq!(latexify_with(&decimal(2.0), &precision(Some(3))), "2");
+
+        // 0.3 + 0.6 is 0.90000004 in f32
+        let sum = Expression::Atom(Atom::Numeric(
+            evaluate(&parse("0.3+0.6")).unwrap().scalar().unwrap(),
+        ));
+        assert_eq!(latexify(&sum), "0.9");
+        assert_eq!(latexify_with(&sum, &precision(None)), "0.90000004");
+        assert_eq!(
+            latexify_with(
+                &Expression::Atom(Atom::Numeric(Numeric::Complex {
+                    re: 0.1 + 0.2,
+                    im: 1.0 / 3.0
+                })),
+                &precision(Some(2))
+            ),
+            "0.3+0.33i"
+        );
+    }
+
+    #[test]
+    fn command_spacing_latex() {
+        for (spaced, tight) in [
+            ("\\frac {1}{2}", "\\frac{1}{2}"),
+            ("\\frac {1} {2}", "\\frac{1}{2}"),
+            ("\\sin (x)", "\\sin(x)"),
+            ("\\sin \\left(x\\right)", "\\sin\\left(x\\right)"),
+            ("\\sqrt {4}", "\\sqrt{4}"),
+            ("\\log_{2} (x)", "\\log_{2}(x)"),
+            ("\\operatorname {erf} (x)", "\\operatorname{erf}(x)"),
+            ("\\int _{0} ^{1} x \\, dx", "\\int_{0}^{1} x \\, dx"),
+        ] {
+            assert_eq!(parse(spaced), parse(tight), "{}", spaced);
+        }
+    }
+
+    #[test]
+    fn right_operand_parens_latex() {
+        assert_eq!(latexify(&parse("a-(b+c)")), "a-\\left(b+c\\right)");
+        assert_eq!(latexify(&parse("a-(b-c)")), "a-\\left(b-c\\right)");
+        assert_eq!(latexify(&parse("a-b+c")), "a-b+c");
+        assert_eq!(latexify(&parse("(a+b)%c")), "\\left(a+b\\right)%c");
+
+        for s in [
+            "a-(b+c)",
+            "a-(b-c)",
+            "a+(b-c)",
+            "(a-b)-c",
+            "a%(b%c)",
+            "a%(b \\cdot c)",
+            "(a+b)%c",
+        ] {
+            assert_eq!(roundtrip(&parse(s)), parse(s), "{}", s);
+        }
+        assert_eq!(
+            evaluate(&roundtrip(&parse("10-(4+3)"))),
+            Ok(Value::Scalar(Numeric::Integer(3)))
+        );
+    }
+
+    #[test]
+    fn relation_latex() {
+        let relation =
+            |op, l: Expression, r: Expression| Expression::Relation(op, Box::new(l), Box::new(r));
+
+        assert_eq!(
+            parse("0 < x < 1"),
+            Expression::And(vec![
+                Box::new(relation(
+                    Comparison::Less,
+                    Expression::int(0),
+                    Expression::var('x')
+                )),
+                Box::new(relation(
+                    Comparison::Less,
+                    Expression::var('x'),
+                    Expression::int(1)
+                )),
+            ])
+        );
+        assert_eq!(
+            parse("x^2 \\geq 2y"),
+            relation(Comparison::GreaterEqual, parse("x^2"), parse("2y"))
+        );
+        assert_eq!(
+            parse("a \\le \\left(b+c\\right)"),
+            relation(Comparison::LessEqual, parse("a"), parse("b+c"))
+        );
+        assert_eq!(latexify(&parse("0<x\\le 1")), "0<x\\leq 1");
+        assert_eq!(latexify(&parse("x \\neq y")), "x\\neq y");
+        assert_eq!(latexify(&parse("0<x<1 \\land y>0")), "0<x<1\\land y>0");
+        assert_eq!(parse("0<x \\land x<1"), parse("0<x<1"));
+        assert_eq!(parse("0<x<1").to_string(), "0 < x < 1");
+
+        for s in [
+            "0<x<1",
+            "a=b+1",
+            "-1\\leq \\sin\\left(x\\right)\\leq 1",
+            "0<x<1\\land y>0",
+            "a\\geq b>c\\neq d",
+        ] {
+            assert_eq!(latexify(&parse(s)), s);
+            assert_eq!(roundtrip(&parse(s)), parse(s));
+        }
+    }
+
+    #[test]
+    fn logic_latex() {
+        let not = |e: Expression| Expression::Not(Box::new(e));
+
+        assert_eq!(
+            parse("x<0 \\lor x>1 \\land y=0"),
+            Expression::Or(vec![
+                Box::new(parse("x<0")),
+                Box::new(parse("x>1 \\land y=0")),
+            ])
+        );
+        assert_eq!(parse("\\lnot x>0"), not(parse("x>0")));
+        assert_eq!(parse("\\neg\\lnot(x>0)"), not(not(parse("x>0"))));
+        assert_eq!(
+            parse("\\left(x<0 \\lor x>1\\right) \\land y=0"),
+            Expression::And(vec![
+                Box::new(parse("x<0 \\lor x>1")),
+                Box::new(parse("y=0")),
+            ])
+        );
+        assert_eq!(
+            parse("(x+1)\\cdot 2>0"),
+            parse("\\left(x+1\\right)\\cdot 2>0")
+        );
+        assert_eq!(parse("\\top"), Expression::Atom(Atom::Boolean(true)));
+        assert_eq!(parse("x<0 \\lor x>1").to_string(), "x < 0 or x > 1");
+
+        for s in [
+            "x<0\\lor x>1",
+            "\\lnot \\left(x>0\\right)\\land y>0",
+            "\\left(x<0\\lor x>1\\right)\\land y=0",
+            "\\lnot \\lnot \\bot",
+        ] {
+            assert_eq!(latexify(&parse(s)), s);
+            assert_eq!(roundtrip(&parse(s)), parse(s));
+        }
+    }
+
+    #[test]
+    fn evaluate_logic_latex() {
+        assert_eq!(evaluate(&parse("2 < 3")), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate(&parse("\\lnot(2>3)")), Ok(Value::Boolean(true)));
+        assert_eq!(
+            evaluate(&parse("1 < 2 < 1 \\lor \\frac{1}{2} = 0.5")),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            evaluate(&parse("2 \\le 1 \\lor \\lnot\\top")),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(evaluate(&parse("i < 1")), Err(EvalError::DomainError));
+
+        let outside = parse("\\begin{cases} 1 & x<0 \\lor x>1 \\\\ 0 & \\top \\end{cases}");
+        let at = |x| {
+            let ctx = EvalContext {
+                variables: [('x', Numeric::Integer(x))].into_iter().collect(),
+            };
+            try_eval(&outside, &ctx)
+        };
+        assert_eq!(at(2), Ok(Value::Scalar(Numeric::Integer(1))));
+        assert_eq!(at(0), Ok(Value::Scalar(Numeric::Integer(0))));
+    }
+
+    #[test]
+    fn sum_latex() {
+        let var = |c| Box::new(Expression::Atom(Atom::Variable(c)));
+        let n = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+
+        let sum = parse("\\sum_{i=1}^{3} i");
+        assert_eq!(
+            sum,
+            Expression::Sum {
+                index: 'i',
+                lower: n(1),
+                upper: n(3),
+                body: var('i'),
+            }
+        );
+        assert_eq!(latexify(&sum), "\\sum_{i=1}^{3} i");
+        assert_eq!(evaluate(&sum), Ok(Value::Scalar(Numeric::Integer(6))));
+
+        // the body is a product, a sum after it is not part of it
+        assert_eq!(
+            parse("\\sum_{k=0}^{n} 2k + 1"),
+            Expression::Add(
+                Box::new(Expression::Sum {
+                    index: 'k',
+                    lower: n(0),
+                    upper: var('n'),
+                    body: Box::new(Expression::Multiply(n(2), var('k'))),
+                }),
+                n(1)
+            )
+        );
+        for s in [
+            "\\sum_{k=0}^{n} \\left(k+1\\right)",
+            "\\left(\\sum_{k=1}^{n} k\\right)\\cdot2",
+            "\\sum_{j=1}^{2} \\sum_{k=j}^{3} j\\cdot k",
+        ] {
+            assert_eq!(latexify(&parse(s)), s);
+        }
+
+        assert_eq!(
+            evaluate(&parse("\\sum_{j=1}^{2} \\sum_{k=j}^{3} j\\cdot k")),
+            Ok(Value::Scalar(Numeric::Integer(16)))
+        );
+        assert_eq!(
+            evaluate(&parse("\\sum_{k=1}^{0} k")),
+            Ok(Value::Scalar(Numeric::Integer(0)))
+        );
+        assert_eq!(
+            evaluate(&parse("\\sum_{k=1}^{2.5} k")),
+            Err(EvalError::DomainError)
+        );
+        assert_eq!(
+            evaluate(&parse("\\sum_{k=1}^{1000000000} k")),
+            Err(EvalError::StepLimitExceeded)
+        );
+    }
 }
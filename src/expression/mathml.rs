@@ -0,0 +1,410 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::expression::expression_tree::{
+    chain_links, Atom, Comparison, Expression, Link, Numeric,
+};
+
+// renders an expression as presentation mathml, with the same grouping latexify uses: fractions
+// become <mfrac>, powers <msup>, square roots <msqrt> and matrices <mtable>
+pub fn to_mathml(expr: &Expression) -> String {
+    let mut out = String::from("<math>");
+    to_mathml_into(expr, &mut out);
+    out.push_str("</math>");
+    out
+}
+
+fn to_mathml_into(expr: &Expression, out: &mut String) {
+    match expr {
+        Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))) => {
+            write!(out, "<mfrac><mn>{}</mn><mn>{}</mn></mfrac>", n, d).unwrap()
+        }
+        Expression::Atom(Atom::Numeric(n)) if f32::from(*n) < 0.0 => {
+            write!(out, "<mrow><mo>-</mo><mn>{}</mn></mrow>", -*n).unwrap()
+        }
+        Expression::Atom(Atom::Numeric(n)) => write!(out, "<mn>{}</mn>", n).unwrap(),
+        Expression::Atom(Atom::Variable(v)) => {
+            out.push_str("<mi>");
+            push_escaped(*v, out);
+            out.push_str("</mi>");
+        }
+        Expression::Atom(a) => write!(out, "<mi>{}</mi>", a).unwrap(),
+
+        Expression::Negate(e) => {
+            out.push_str("<mrow><mo>-</mo>");
+            to_mathml_operand(e, out);
+            out.push_str("</mrow>");
+        }
+        Expression::Factorial(e) => {
+            out.push_str("<mrow>");
+            to_mathml_operand(e, out);
+            out.push_str("<mo>!</mo></mrow>");
+        }
+        Expression::Percent(e) => {
+            out.push_str("<mrow>");
+            to_mathml_operand(e, out);
+            out.push_str("<mo>%</mo></mrow>");
+        }
+
+        Expression::Add(l, r) => to_mathml_binary(l, "+", r, out),
+        Expression::Subtract(l, r) => to_mathml_binary(l, "-", r, out),
+        Expression::Modulus(l, r) => to_mathml_binary(l, "%", r, out),
+
+        Expression::Multiply(l, r) => {
+            out.push_str("<mrow>");
+            for (i, e) in [l, r].into_iter().enumerate() {
+                if i > 0 {
+                    out.push_str("<mo>&#x22C5;</mo>");
+                }
+                match **e {
+                    Expression::Add(_, _)
+                    | Expression::Subtract(_, _)
+                    | Expression::Modulus(_, _) => to_mathml_parens(e, out),
+                    _ => to_mathml_into(e, out),
+                }
+            }
+            out.push_str("</mrow>");
+        }
+
+        Expression::Divide(l, r) => {
+            out.push_str("<mfrac>");
+            to_mathml_into(l, out);
+            to_mathml_into(r, out);
+            out.push_str("</mfrac>");
+        }
+
+        Expression::Power(l, r) => {
+            out.push_str("<msup>");
+            match **l {
+                Expression::Add(_, _)
+                | Expression::Subtract(_, _)
+                | Expression::Modulus(_, _)
+                | Expression::Multiply(_, _)
+                | Expression::Divide(_, _)
+                | Expression::Power(_, _) => to_mathml_parens(l, out),
+                // a sign binds looser than the power, (-2)^2 rather than -2^2
+                Expression::Negate(_) => to_mathml_parens(l, out),
+                Expression::Atom(Atom::Numeric(n)) if f32::from(n) < 0.0 => {
+                    to_mathml_parens(l, out)
+                }
+                _ => to_mathml_into(l, out),
+            }
+            to_mathml_into(r, out);
+            out.push_str("</msup>");
+        }
+
+        Expression::Function { name, args } if name == "sqrt" && args.len() == 1 => {
+            out.push_str("<msqrt>");
+            to_mathml_into(&args[0], out);
+            out.push_str("</msqrt>");
+        }
+
+        Expression::Function { name, args }
+            if (name == "abs" || name == "floor" || name == "ceil") && args.len() == 1 =>
+        {
+            let (open, close) = match name.as_str() {
+                "abs" => ("|", "|"),
+                "floor" => ("&#x230A;", "&#x230B;"),
+                _ => ("&#x2308;", "&#x2309;"),
+            };
+            write!(out, "<mrow><mo>{}</mo>", open).unwrap();
+            to_mathml_into(&args[0], out);
+            write!(out, "<mo>{}</mo></mrow>", close).unwrap();
+        }
+
+        // the invisible function application operator keeps f(x) from reading as f times x
+        Expression::Function { name, args } => {
+            out.push_str("<mrow><mi>");
+            name.chars().for_each(|c| push_escaped(c, out));
+            out.push_str("</mi><mo>&#x2061;</mo><mrow><mo>(</mo>");
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("<mo>,</mo>");
+                }
+                to_mathml_into(arg, out);
+            }
+            out.push_str("<mo>)</mo></mrow></mrow>");
+        }
+
+        Expression::Vector {
+            backing: vec,
+            size: _,
+        } => {
+            out.push_str("<mrow><mo>&#x27E8;</mo>");
+            for (i, e) in vec.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("<mo>,</mo>");
+                }
+                to_mathml_into(e, out);
+            }
+            out.push_str("<mo>&#x27E9;</mo></mrow>");
+        }
+
+        Expression::Matrix {
+            backing: vec,
+            shape: (rs, cs),
+        } => {
+            out.push_str("<mrow><mo>[</mo><mtable>");
+            for r in 0..*rs {
+                out.push_str("<mtr>");
+                for c in 0..*cs {
+                    out.push_str("<mtd>");
+                    to_mathml_into(&vec[(*cs * r + c) as usize], out);
+                    out.push_str("</mtd>");
+                }
+                out.push_str("</mtr>");
+            }
+            out.push_str("</mtable><mo>]</mo></mrow>");
+        }
+
+        Expression::Integral {
+            integrand,
+            var,
+            lower,
+            upper,
+        } => {
+            out.push_str("<mrow>");
+            match (lower, upper) {
+                (Some(lower), Some(upper)) => {
+                    out.push_str("<msubsup><mo>&#x222B;</mo>");
+                    to_mathml_into(lower, out);
+                    to_mathml_into(upper, out);
+                    out.push_str("</msubsup>");
+                }
+                (Some(bound), None) | (None, Some(bound)) => {
+                    let script = if lower.is_some() { "msub" } else { "msup" };
+                    write!(out, "<{}><mo>&#x222B;</mo>", script).unwrap();
+                    to_mathml_into(bound, out);
+                    write!(out, "</{}>", script).unwrap();
+                }
+                (None, None) => out.push_str("<mo>&#x222B;</mo>"),
+            }
+            to_mathml_into(integrand, out);
+            out.push_str("<mo>&#x2146;</mo><mi>");
+            push_escaped(*var, out);
+            out.push_str("</mi></mrow>");
+        }
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => {
+            out.push_str("<mrow><munderover><mo>&#x2211;</mo><mrow><mi>");
+            push_escaped(*index, out);
+            out.push_str("</mi><mo>=</mo>");
+            to_mathml_into(lower, out);
+            out.push_str("</mrow>");
+            to_mathml_into(upper, out);
+            out.push_str("</munderover>");
+            to_mathml_into(body, out);
+            out.push_str("</mrow>");
+        }
+
+        Expression::Relation(op, l, r) => to_mathml_binary(l, comparison(*op), r, out),
+        // chained relations share their middle operand, other members are joined by a wedge
+        Expression::And(es) => {
+            out.push_str("<mrow>");
+            for (i, link) in chain_links(es).into_iter().enumerate() {
+                match link {
+                    Link::Chained(op, r) => {
+                        write!(out, "<mo>{}</mo>", comparison(op)).unwrap();
+                        to_mathml_into(r, out);
+                    }
+                    Link::Joined(e) => {
+                        if i > 0 {
+                            out.push_str("<mo>&#x2227;</mo>");
+                        }
+                        match e {
+                            Expression::Or(_) => to_mathml_parens(e, out),
+                            _ => to_mathml_into(e, out),
+                        }
+                    }
+                }
+            }
+            out.push_str("</mrow>");
+        }
+        Expression::Or(es) => {
+            out.push_str("<mrow>");
+            for (i, e) in es.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("<mo>&#x2228;</mo>");
+                }
+                to_mathml_into(e, out);
+            }
+            out.push_str("</mrow>");
+        }
+        Expression::Not(e) => {
+            out.push_str("<mrow><mo>&#x00AC;</mo>");
+            to_mathml_operand(e, out);
+            out.push_str("</mrow>");
+        }
+
+        // a brace in front of a table with one row per branch, its value then its condition
+        Expression::Piecewise(branches) => {
+            out.push_str("<mrow><mo>{</mo><mtable>");
+            for (value, condition) in branches {
+                out.push_str("<mtr><mtd>");
+                to_mathml_into(value, out);
+                out.push_str("</mtd><mtd>");
+                to_mathml_into(condition, out);
+                out.push_str("</mtd></mtr>");
+            }
+            out.push_str("</mtable></mrow>");
+        }
+    }
+}
+
+fn comparison(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Equal => "=",
+        Comparison::NotEqual => "&#x2260;",
+        Comparison::Less => "&lt;",
+        Comparison::LessEqual => "&#x2264;",
+        Comparison::Greater => "&gt;",
+        Comparison::GreaterEqual => "&#x2265;",
+    }
+}
+
+// sums group from the left, so a sum subtracted on the right keeps its parentheses, a-(b+c)
+fn to_mathml_binary(l: &Expression, op: &str, r: &Expression, out: &mut String) {
+    out.push_str("<mrow>");
+    to_mathml_into(l, out);
+    write!(out, "<mo>{}</mo>", op).unwrap();
+    match r {
+        Expression::Add(_, _) | Expression::Subtract(_, _) if op == "-" => to_mathml_parens(r, out),
+        _ => to_mathml_into(r, out),
+    }
+    out.push_str("</mrow>");
+}
+
+// the operand of a unary operator, parenthesized unless it is an atom
+fn to_mathml_operand(expr: &Expression, out: &mut String) {
+    match expr {
+        Expression::Atom(_) => to_mathml_into(expr, out),
+        _ => to_mathml_parens(expr, out),
+    }
+}
+
+fn to_mathml_parens(expr: &Expression, out: &mut String) {
+    out.push_str("<mrow><mo>(</mo>");
+    to_mathml_into(expr, out);
+    out.push_str("<mo>)</mo></mrow>");
+}
+
+fn push_escaped(c: char, out: &mut String) {
+    match c {
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '&' => out.push_str("&amp;"),
+        c => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, string::String, vec::Vec};
+    use core::str::FromStr;
+
+    use super::to_mathml;
+    use crate::expression::expression_tree::{Atom, Expression, Numeric};
+
+    fn mathml(s: &str) -> String {
+        to_mathml(&Expression::from_str(s).unwrap())
+    }
+
+    // every opened element is closed in order, and the whole document is a single <math> element
+    fn is_well_formed(xml: &str) -> bool {
+        let mut open = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => return false,
+            };
+            let tag = &rest[start + 1..end];
+            match tag.strip_prefix('/') {
+                Some(name) if open.pop() != Some(name) => return false,
+                Some(_) => (),
+                None => open.push(tag),
+            }
+            rest = &rest[end + 1..];
+        }
+        open.is_empty() && xml.starts_with("<math>") && xml.ends_with("</math>")
+    }
+
+    #[test]
+    fn test_mathml_fraction() {
+        let fraction = mathml("(x + 1) / 2");
+
+        assert_eq!(
+            fraction,
+            "<math><mfrac><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow><mn>2</mn></mfrac></math>"
+        );
+        assert!(is_well_formed(&fraction));
+    }
+
+    #[test]
+    fn test_mathml_power() {
+        assert_eq!(
+            mathml("x ^ 2"),
+            "<math><msup><mi>x</mi><mn>2</mn></msup></math>"
+        );
+        assert_eq!(
+            mathml("(a * b) ^ (n - 1)"),
+            "<math><msup><mrow><mo>(</mo><mrow><mi>a</mi><mo>&#x22C5;</mo><mi>b</mi></mrow>\
+             <mo>)</mo></mrow><mrow><mi>n</mi><mo>-</mo><mn>1</mn></mrow></msup></math>"
+        );
+        assert_eq!(
+            mathml("(-x) ^ 2"),
+            "<math><msup><mrow><mo>(</mo><mrow><mo>-</mo><mi>x</mi></mrow><mo>)</mo></mrow>\
+             <mn>2</mn></msup></math>"
+        );
+        assert_eq!(
+            to_mathml(&Expression::Power(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(-2)))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+            )),
+            "<math><msup><mrow><mo>(</mo><mrow><mo>-</mo><mn>2</mn></mrow><mo>)</mo></mrow>\
+             <mn>2</mn></msup></math>"
+        );
+    }
+
+    #[test]
+    fn test_mathml_subtract() {
+        assert_eq!(
+            mathml("a - (b + c)"),
+            "<math><mrow><mi>a</mi><mo>-</mo><mrow><mo>(</mo><mrow><mi>b</mi><mo>+</mo><mi>c</mi>\
+             </mrow><mo>)</mo></mrow></mrow></math>"
+        );
+        assert_eq!(
+            mathml("a - (b - c)"),
+            "<math><mrow><mi>a</mi><mo>-</mo><mrow><mo>(</mo><mrow><mi>b</mi><mo>-</mo><mi>c</mi>\
+             </mrow><mo>)</mo></mrow></mrow></math>"
+        );
+        assert_eq!(
+            mathml("a - b - c"),
+            "<math><mrow><mrow><mi>a</mi><mo>-</mo><mi>b</mi></mrow><mo>-</mo><mi>c</mi></mrow></math>"
+        );
+    }
+
+    #[test]
+    fn test_mathml_well_formed() {
+        for s in [
+            "-(x + 1) * sin(x) ^ 2 + abs(y) - sqrt(z)",
+            "<1, 2> + [1, 2; 3, 4]",
+            "f(x, y) * (u % v) + (x - 1)! + 50%",
+        ] {
+            assert!(is_well_formed(&mathml(s)), "{}", mathml(s));
+        }
+    }
+
+    #[test]
+    fn test_mathml_matrix() {
+        assert_eq!(
+            mathml("[1, 2; 3, 4]"),
+            "<math><mrow><mo>[</mo><mtable><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr>\
+             <mtr><mtd><mn>3</mn></mtd><mtd><mn>4</mn></mtd></mtr></mtable><mo>]</mo></mrow></math>"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    expression::{
+        eval::{evaluate, Value},
+        expression_tree::{Atom, Expression, Numeric},
+    },
+    MatrixError, ShapeError,
+};
+
+// multiplies an r x c matrix by a vector of length c, giving the vector of length r whose entries
+// are the (unsimplified) dot products of the rows of the matrix with the vector
+pub fn matrix_vector_multiply(m: &Expression, v: &Expression) -> Result<Expression, ShapeError> {
+    let (entries, (rs, cs)) = match m {
+        Expression::Matrix { backing, shape } => (backing, *shape),
+        _ => return Err(ShapeError::NotAMatrix),
+    };
+    let vector = match v {
+        Expression::Vector { backing, size: _ } => backing,
+        _ => return Err(ShapeError::NotAVector),
+    };
+    if vector.len() != cs as usize {
+        return Err(ShapeError::Mismatch {
+            expected: cs as usize,
+            found: vector.len(),
+        });
+    }
+
+    let backing = (0..rs as usize)
+        .map(|r| {
+            let row = &entries[r * cs as usize..(r + 1) * cs as usize];
+            Box::new(dot(row, vector))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Expression::Vector { size: rs, backing })
+}
+
+// multiplies an r x n matrix by an n x c matrix, giving the r x c matrix of (unsimplified) dot
+// products of the rows of the first with the columns of the second
+pub fn matrix_multiply(a: &Expression, b: &Expression) -> Result<Expression, ShapeError> {
+    let (left, right, (rs, n), (m, cs)) = match (a, b) {
+        (
+            Expression::Matrix {
+                backing: left,
+                shape: left_shape,
+            },
+            Expression::Matrix {
+                backing: right,
+                shape: right_shape,
+            },
+        ) => (left, right, *left_shape, *right_shape),
+        _ => return Err(ShapeError::NotAMatrix),
+    };
+    if n != m {
+        return Err(ShapeError::Mismatch {
+            expected: n as usize,
+            found: m as usize,
+        });
+    }
+
+    let (n, width) = (n as usize, cs as usize);
+    let backing = (0..rs as usize)
+        .flat_map(|r| {
+            (0..width).map(move |c| {
+                let row = &left[r * n..(r + 1) * n];
+                let column = (0..n)
+                    .map(|k| right[k * width + c].clone())
+                    .collect::<Vec<_>>();
+                Box::new(dot(row, &column))
+            })
+        })
+        .collect();
+
+    Ok(Expression::Matrix {
+        backing,
+        shape: (rs, cs),
+    })
+}
+
+// inverts a square matrix of numbers by gauss-jordan elimination, integer entries give an exact
+// inverse of integers and fractions, entries which do not evaluate to a number are rejected
+pub fn inverse(m: &Expression) -> Result<Expression, MatrixError> {
+    let (entries, n) = match m {
+        Expression::Matrix {
+            backing,
+            shape: (rs, cs),
+        } if rs == cs => (backing, *rs as usize),
+        Expression::Matrix {
+            backing: _,
+            shape: _,
+        } => return Err(MatrixError::NotSquare),
+        _ => return Err(MatrixError::NotAMatrix),
+    };
+
+    // every row is augmented with the matching row of the identity matrix
+    let mut rows = (0..n)
+        .map(|r| {
+            let mut row = entries[r * n..(r + 1) * n]
+                .iter()
+                .map(|e| {
+                    evaluate(e)
+                        .and_then(Value::scalar)
+                        .map_err(|_| MatrixError::NotNumeric)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            row.extend((0..n).map(|c| Numeric::Integer((r == c) as i32)));
+            Ok(row)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .find(|&r| rows[r][col] != Numeric::Integer(0))
+            .ok_or(MatrixError::Singular)?;
+        rows.swap(col, pivot);
+
+        let scale = rows[col][col];
+        for e in rows[col].iter_mut() {
+            *e = reduce(*e / scale);
+        }
+
+        let pivot_row = rows[col].clone();
+        for row in rows
+            .iter_mut()
+            .enumerate()
+            .filter(|(r, _)| *r != col)
+            .map(|(_, row)| row)
+        {
+            let factor = row[col];
+            for (e, p) in row.iter_mut().zip(&pivot_row) {
+                *e = reduce(*e - reduce(factor * *p));
+            }
+        }
+    }
+
+    Ok(Expression::Matrix {
+        backing: rows
+            .into_iter()
+            .flat_map(|row| row.into_iter().skip(n))
+            .map(|e| Box::new(Expression::Atom(Atom::Numeric(e))))
+            .collect(),
+        shape: (n as u8, n as u8),
+    })
+}
+
+// keeps fractions in lowest terms with a positive denominator, so repeated elimination steps do not
+// overflow, whole fractions become integers
+pub(crate) fn reduce(n: Numeric) -> Numeric {
+    match n {
+        Numeric::Fraction(a, b) => {
+            let gcd = gcd(a.unsigned_abs(), b.unsigned_abs()) as i32;
+            let sign = if b < 0 { -1 } else { 1 };
+            match (sign * a / gcd, sign * b / gcd) {
+                (a, 1) => Numeric::Integer(a),
+                (a, b) => Numeric::Fraction(a, b),
+            }
+        }
+        n => n,
+    }
+}
+
+pub(crate) fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a.max(1)
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// a0 * b0 + a1 * b1 + ..., folded from the left
+fn dot(a: &[Box<Expression>], b: &[Box<Expression>]) -> Expression {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| Expression::Multiply(x.clone(), y.clone()))
+        .reduce(|acc, e| Expression::Add(Box::new(acc), Box::new(e)))
+        .unwrap_or(Expression::int(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::str::FromStr;
+
+    use super::{inverse, matrix_multiply, matrix_vector_multiply};
+    use crate::{
+        expression::expression_tree::{Atom, Expression, Numeric},
+        modifier::{adaptable_modifier::AdaptableModifier, default::simplifier},
+        MatrixError, ShapeError,
+    };
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_matrix_vector_multiply() {
+        let rotation = parse("[c, -s; s, c]");
+
+        let mut rotated = matrix_vector_multiply(&rotation, &parse("<1, 0>")).unwrap();
+        assert_eq!(rotated, parse("<c * 1 + (-s) * 0, s * 1 + c * 0>"));
+
+        rotated.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(rotated, parse("<c, s>"));
+    }
+
+    #[test]
+    fn test_matrix_vector_multiply_rectangular() {
+        let product = matrix_vector_multiply(&parse("[1, 2, 3]"), &parse("<x, y, z>")).unwrap();
+        assert_eq!(product, parse("<1 * x + 2 * y + 3 * z>"));
+    }
+
+    #[test]
+    fn test_matrix_multiply_identity() {
+        let matrix = parse("[a, b, c; d, e, f; g, h, i]");
+
+        for mut product in [
+            matrix_multiply(&Expression::identity_matrix(3), &matrix).unwrap(),
+            matrix_multiply(&matrix, &Expression::identity_matrix(3)).unwrap(),
+        ] {
+            product.simplify_im::<AdaptableModifier, 100>(&simplifier());
+            assert_eq!(product, matrix);
+        }
+
+        let mut zero = matrix_multiply(&matrix, &Expression::zero_matrix(3, 2)).unwrap();
+        zero.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(zero, Expression::zero_matrix(3, 2));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix = parse("[4, 7; 2, 6]");
+        let inverted = inverse(&matrix).unwrap();
+
+        let entries = match &inverted {
+            Expression::Matrix { backing, shape: _ } => backing
+                .iter()
+                .map(|e| match **e {
+                    Expression::Atom(Atom::Numeric(n)) => n,
+                    _ => panic!("inverse produced a symbolic entry"),
+                })
+                .collect::<Vec<_>>(),
+            _ => panic!("inverse produced a non-matrix"),
+        };
+        assert!(matches!(
+            entries[..],
+            [
+                Numeric::Fraction(3, 5),
+                Numeric::Fraction(-7, 10),
+                Numeric::Fraction(-1, 5),
+                Numeric::Fraction(2, 5)
+            ]
+        ));
+
+        let mut product = matrix_multiply(&matrix, &inverted).unwrap();
+        product.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(product, Expression::identity_matrix(2));
+    }
+
+    #[test]
+    fn test_inverse_pivoting() {
+        let matrix = parse("[0, 1, 0; 1, 0, 0; 0, 0, 2]");
+
+        let mut product = matrix_multiply(&inverse(&matrix).unwrap(), &matrix).unwrap();
+        product.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(product, Expression::identity_matrix(3));
+    }
+
+    #[test]
+    fn test_inverse_errors() {
+        assert_eq!(inverse(&parse("[1, 2; 2, 4]")), Err(MatrixError::Singular));
+        assert_eq!(inverse(&parse("[1, 2, 3]")), Err(MatrixError::NotSquare));
+        assert_eq!(
+            inverse(&parse("[1, x; 0, 1]")),
+            Err(MatrixError::NotNumeric)
+        );
+        assert_eq!(inverse(&parse("<1, 2>")), Err(MatrixError::NotAMatrix));
+    }
+
+    #[test]
+    fn test_matrix_multiply_shape_errors() {
+        assert_eq!(
+            matrix_multiply(&parse("[1, 2; 3, 4]"), &parse("[1, 2, 3]")),
+            Err(ShapeError::Mismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            matrix_multiply(&parse("[1, 2]"), &Expression::zero_vector(2)),
+            Err(ShapeError::NotAMatrix)
+        );
+    }
+
+    #[test]
+    fn test_matrix_vector_multiply_shape_errors() {
+        assert_eq!(
+            matrix_vector_multiply(&parse("[1, 2; 3, 4]"), &parse("<1, 2, 3>")),
+            Err(ShapeError::Mismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            matrix_vector_multiply(&parse("<1, 2>"), &parse("<1, 2>")),
+            Err(ShapeError::NotAMatrix)
+        );
+        assert_eq!(
+            matrix_vector_multiply(&parse("[1, 2]"), &parse("x")),
+            Err(ShapeError::NotAVector)
+        );
+    }
+}
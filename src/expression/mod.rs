@@ -1,3 +1,12 @@
+pub mod algebra;
+pub mod arena;
+pub mod calculus;
+pub mod dot;
+pub mod eval;
 pub mod expression_tree;
 pub mod latex;
+pub mod mathml;
+pub mod matrix;
+pub mod sexpr;
+pub mod unicode;
 mod parser;
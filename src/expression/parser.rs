@@ -1,14 +1,13 @@
-use alloc::vec;
 use alloc::{boxed::Box, string::ToString, vec::Vec};
 
 use nom::bytes::complete::take_while;
-use nom::multi::separated_list0;
+use nom::multi::{separated_list0, separated_list1};
 use nom::sequence::pair;
 use nom::{
     branch::alt,
     bytes::complete::{take, take_while1},
-    character::complete::{char, digit1, space0},
-    combinator::map,
+    character::complete::{char, digit1, one_of, satisfy, space0},
+    combinator::{eof, map, map_opt, opt, peek, recognize, value},
     multi::many0,
     sequence::{delimited, preceded, terminated, tuple},
     IResult,
@@ -20,11 +19,9 @@ use super::expression_tree::Escape;
 
 // TODO: explain parser
 
-pub fn parse(input: &str) -> Expression {
-    parse_add_sub(input)
-        .map_err(|_| "failed to parse")
-        .unwrap()
-        .1
+// the expression making up the whole of the input, if it parses
+pub fn try_parse(input: &str) -> Option<Expression> {
+    terminated(parse_add_sub, eof)(input).ok().map(|(_, expr)| expr)
 }
 
 fn parse_recursive(input: &str) -> IResult<&str, Expression> {
@@ -48,8 +45,16 @@ fn parse_parentheses(input: &str) -> IResult<&str, Expression> {
 }
 
 fn parse_numeric(input: &str) -> IResult<&str, Expression> {
-    map(
-        delimited(space0, take_while1(is_numeric_value), space0),
+    map_opt(
+        delimited(
+            space0,
+            // the exponent is only taken when digits follow the e, so 2e still leaves the constant e
+            recognize(pair(
+                take_while1(is_numeric_value),
+                opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+            )),
+            space0,
+        ),
         parse_number,
     )(input)
 }
@@ -58,11 +63,18 @@ fn is_numeric_value(c: char) -> bool {
     c.is_ascii_digit() || c == '.'
 }
 
-fn parse_number(input: &str) -> Expression {
-    Expression::Atom(Atom::Numeric(match input.contains('.') {
-        true => Numeric::Decimal(input.parse::<f32>().unwrap()),
-        false => Numeric::Integer(input.parse::<i32>().unwrap()),
-    }))
+// an integer too large for an i32 is kept as a decimal, as from_sexpr does, a malformed number
+// such as 1.2.3 is rejected
+fn parse_number(input: &str) -> Option<Expression> {
+    let numeric = match input.contains(['.', 'e', 'E']) {
+        true => input.parse::<f32>().ok().map(Numeric::Decimal),
+        false => input
+            .parse::<i32>()
+            .map(Numeric::Integer)
+            .or_else(|_| input.parse::<f32>().map(Numeric::Decimal))
+            .ok(),
+    };
+    numeric.map(|n| Expression::Atom(Atom::Numeric(n)))
 }
 
 fn parse_function(input: &str) -> IResult<&str, Expression> {
@@ -72,25 +84,25 @@ fn parse_function(input: &str) -> IResult<&str, Expression> {
             tuple((
                 preceded(
                     space0,
-                    pair(
+                    // the primes of a derivative are kept at the end of the name, f'(x)
+                    tuple((
                         take_while1(|c: char| c.is_alphabetic()),
                         take_while(|c: char| c.is_alphanumeric()),
-                    ),
+                        take_while(|c: char| c == '\''),
+                    )),
                 ),
                 delimited(
                     char('('),
-                    pair(many0(terminated(parse_add_sub, char(','))), parse_add_sub),
+                    separated_list1(char(','), parse_add_sub),
                     char(')'),
                 ),
             )),
             space0,
         ),
         |(name, arg_list)| Expression::Function {
-            name: name.0.to_string() + name.1,
+            name: name.0.to_string() + name.1 + name.2,
             args: arg_list
-                .0
                 .into_iter()
-                .chain(vec![arg_list.1])
                 .map(Box::new)
                 .collect(),
         },
@@ -103,7 +115,10 @@ fn parse_vector(input: &str) -> IResult<&str, Expression> {
             space0,
             preceded(
                 char('<'),
-                many0(terminated(parse_add_sub, alt((char(','), char('>'))))),
+                alt((
+                    value(Vec::new(), char('>')),
+                    many0(terminated(parse_add_sub, alt((char(','), char('>'))))),
+                )),
             ),
             space0,
         ),
@@ -133,8 +148,9 @@ fn parse_matrix(input: &str) -> IResult<&str, Expression> {
             space0,
         ),
         |flatten_matrix| {
-            let row_count = flatten_matrix.len() as u8;
-            let col_count = if row_count == 0 { 0u8 } else { flatten_matrix[0].len() as u8 }; // assuming every row has the same number of columns
+            let col_count = flatten_matrix.first().map_or(0u8, |row| row.len() as u8); // assuming every row has the same number of columns
+            // [] closes a single row with nothing in it
+            let row_count = if col_count == 0 { 0u8 } else { flatten_matrix.len() as u8 };
 
             let backing = flatten_matrix.into_iter().flatten().map(Box::new).collect();
             Expression::Matrix {
@@ -168,33 +184,61 @@ fn parse_escape(input: &str) -> IResult<&str, Expression> {
     )(input)
 }
 
+// a single letter, i is the imaginary unit rather than a variable
 fn parse_variable(input: &str) -> IResult<&str, Expression> {
-    map(delimited(space0, take(1usize), space0), |value: &str| {
-        Expression::Atom(Atom::Variable(value.chars().next().unwrap()))
-    })(input)
+    map(
+        delimited(space0, satisfy(|c| c.is_alphabetic()), space0),
+        |c| match c {
+            'i' => Expression::Atom(Atom::Numeric(Numeric::I)),
+            c => Expression::Atom(Atom::Variable(c)),
+        },
+    )(input)
 }
 
 fn parse_unary(input: &str) -> IResult<&str, Expression> {
-    alt((parse_unary_prefix, parse_unary_postfix, parse_exponents))(input)
+    alt((parse_unary_prefix, parse_unary_postfix))(input)
 }
 
+// the exponent is parsed recursively, so powers are right associative: 2^2^3 is 2^(2^3)
+// an exponent may be negated without parentheses, x^-2 is x^(-2)
 fn parse_exponents(input: &str) -> IResult<&str, Expression> {
     let (input, num) = parse_recursive(input)?;
-    let (input, ops) = many0(tuple((char('^'), parse_exponents)))(input)?;
+    let (input, ops) =
+        many0(tuple((char('^'), alt((parse_unary_prefix, parse_exponents)))))(input)?;
     Ok((input, fold_binary_operators(num, ops)))
 }
 
+// a leading + changes nothing, +x is x, it is only read where an operand is expected so it never
+// takes the place of an addition
 fn parse_unary_prefix(input: &str) -> IResult<&str, Expression> {
     map(
-        delimited(space0, tuple((char('-'), parse_unary)), space0),
+        delimited(space0, tuple((one_of("-+"), parse_unary)), space0),
         parse_unary_prefix_op,
     )(input)
 }
 
+// the operand is parsed once whether or not an operator follows it, reparsing it when there is none
+// would double the work at every level of nesting
 fn parse_unary_postfix(input: &str) -> IResult<&str, Expression> {
     map(
-        delimited(space0, tuple((parse_exponents, char('!'))), space0),
-        parse_unary_postfix_op,
+        delimited(
+            space0,
+            tuple((parse_exponents, opt(alt((char('!'), parse_percent_sign))))),
+            space0,
+        ),
+        |(operand, operator)| match operator {
+            Some(operator) => parse_unary_postfix_op((operand, operator)),
+            None => operand,
+        },
+    )(input)
+}
+
+// a % is a percentage when no operand follows it (`50%`, `50% + 1`, `f(50%)`) and a modulus
+// otherwise (`7 % 3`), so taking the modulus by a negative number needs parentheses: `7 % (-3)`
+fn parse_percent_sign(input: &str) -> IResult<&str, char> {
+    terminated(
+        char('%'),
+        peek(preceded(space0, alt((eof, recognize(one_of(")]>,;|+-*/^")))))),
     )(input)
 }
 
@@ -214,6 +258,7 @@ fn parse_unary_prefix_op(operator_pair: (char, Expression)) -> Expression {
     let (operator, operand) = operator_pair;
     match operator {
         '-' => Expression::Negate(Box::new(operand)),
+        '+' => operand,
         _ => panic!("Invalid operator"),
     }
 }
@@ -222,6 +267,7 @@ fn parse_unary_postfix_op(operator_pair: (Expression, char)) -> Expression {
     let (operand, operator) = operator_pair;
     match operator {
         '!' => Expression::Factorial(Box::new(operand)),
+        '%' => Expression::Percent(Box::new(operand)),
         _ => panic!("Invalid operator"),
     }
 }
@@ -248,9 +294,13 @@ fn parse_binary_op(operator_pair: (char, Expression), expr1: Expression) -> Expr
 mod tests {
     use alloc::{boxed::Box, string::ToString, vec};
 
-    use super::parse;
+    use super::{parse_add_sub, try_parse};
     use crate::expression::expression_tree::*;
 
+    fn parse(input: &str) -> Expression {
+        try_parse(input).expect("failed to parse")
+    }
+
     #[test]
     fn test_integer() {
         assert_eq!(
@@ -271,6 +321,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scientific() {
+        assert!(matches!(
+            parse("1e3"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 1000.0
+        ));
+        assert!(matches!(
+            parse("1.5e3"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 1500.0
+        ));
+        assert!(matches!(
+            parse("2E-4"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 2e-4
+        ));
+        assert!(matches!(
+            parse("3e+2"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 300.0
+        ));
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        assert!(matches!(
+            parse("99999999999"),
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(d))) if d == 99999999999.0
+        ));
+        assert_eq!(try_parse("1.2.3"), None);
+    }
+
+    #[test]
+    fn test_scientific_constant() {
+        assert_eq!(parse("e"), Expression::Atom(Atom::Variable('e')));
+        assert_eq!(
+            parse("2*e"),
+            Expression::Multiply(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+                Box::new(Expression::Atom(Atom::Variable('e'))),
+            )
+        );
+        assert_eq!(
+            parse("e^2"),
+            Expression::Power(
+                Box::new(Expression::Atom(Atom::Variable('e'))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(2)))),
+            )
+        );
+    }
+
     #[test]
     fn test_escape() {
         assert_eq!(
@@ -459,6 +557,103 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_percent() {
+        assert_eq!(
+            parse("50%"),
+            Expression::Percent(Box::new(Expression::Atom(Atom::Numeric(
+                Numeric::Integer(50)
+            ))))
+        );
+        assert_eq!(
+            parse("50% + 1"),
+            Expression::Add(
+                Box::new(Expression::Percent(Box::new(Expression::Atom(Atom::Numeric(
+                    Numeric::Integer(50)
+                ))))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(1)))),
+            )
+        );
+        assert_eq!(
+            parse("f(x%)"),
+            Expression::Function {
+                name: "f".to_string(),
+                args: vec![Box::new(Expression::Percent(Box::new(Expression::Atom(
+                    Atom::Variable('x')
+                ))))],
+            }
+        );
+    }
+
+    #[test]
+    fn test_percent_modulus() {
+        assert_eq!(
+            parse("7 % 3"),
+            Expression::Modulus(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(7)))),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(3)))),
+            )
+        );
+        assert_eq!(
+            parse("7%(-3)"),
+            Expression::Modulus(
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(7)))),
+                Box::new(Expression::Negate(Box::new(Expression::Atom(Atom::Numeric(
+                    Numeric::Integer(3)
+                ))))),
+            )
+        );
+    }
+
+    #[test]
+    fn test_power_right_associative() {
+        let int = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+        assert_eq!(
+            parse("2^2^3"),
+            Expression::Power(int(2), Box::new(Expression::Power(int(2), int(3))))
+        );
+        assert_eq!(
+            parse("(2^2)^3"),
+            Expression::Power(Box::new(Expression::Power(int(2), int(2))), int(3))
+        );
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        let int = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+        let var = |v| Box::new(Expression::Atom(Atom::Variable(v)));
+        let neg = |e| Box::new(Expression::Negate(e));
+
+        assert_eq!(parse("5 - -3"), Expression::Subtract(int(5), neg(int(3))));
+        assert_eq!(parse("5--3"), Expression::Subtract(int(5), neg(int(3))));
+        assert_eq!(
+            parse("-(x+1)"),
+            Expression::Negate(Box::new(Expression::Add(var('x'), int(1))))
+        );
+        assert_eq!(parse("3 * -2"), Expression::Multiply(int(3), neg(int(2))));
+        assert_eq!(parse("a/-b"), Expression::Divide(var('a'), neg(var('b'))));
+        assert_eq!(
+            parse("-x^2"),
+            Expression::Negate(Box::new(Expression::Power(var('x'), int(2))))
+        );
+        assert_eq!(parse("x^-2"), Expression::Power(var('x'), neg(int(2))));
+        assert_eq!(parse("--x"), Expression::Negate(neg(var('x'))));
+    }
+
+    #[test]
+    fn test_unary_plus() {
+        let int = |i| Box::new(Expression::Atom(Atom::Numeric(Numeric::Integer(i))));
+        let var = |v| Box::new(Expression::Atom(Atom::Variable(v)));
+
+        assert_eq!(parse("+x"), *var('x'));
+        assert_eq!(parse("+5"), *int(5));
+        assert_eq!(parse("2 + +3"), Expression::Add(int(2), int(3)));
+        assert_eq!(parse("2++3"), Expression::Add(int(2), int(3)));
+        assert_eq!(parse("2 - +x"), Expression::Subtract(int(2), var('x')));
+        assert_eq!(parse("-+x"), Expression::Negate(var('x')));
+        assert_eq!(parse("x^+2"), Expression::Power(var('x'), int(2)));
+    }
+
     #[test]
     fn test_parentheses() {
         assert_eq!(
@@ -557,7 +752,7 @@ mod tests {
                     Box::new(integer_atom!(2)),
                     Box::new(integer_atom!(3)),
                 ],
-                size: 3 as u8,
+                size: 3,
             }
         )
     }
@@ -574,7 +769,7 @@ mod tests {
                         Box::new(integer_atom!(3)),
                         Box::new(integer_atom!(4)),
                     ],
-                    size: 3 as u8,
+                    size: 3,
                 })
             )
         )
@@ -607,7 +802,7 @@ mod tests {
                         Box::new(integer_atom!(10))
                     )),
                 ],
-                size: 5 as u8,
+                size: 5,
             }
         )
     }
@@ -618,6 +813,18 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_function_primes() {
+        assert_eq!(
+            parse("f''(x)"),
+            Expression::Function {
+                name: "f''".to_string(),
+                args: vec![Box::new(variable_atom!('x'))],
+            }
+        );
+        assert_eq!(parse("f''(x)").to_string(), "f''(x)");
+    }
+
     #[test]
     fn test_function_in_vector() {
         assert_eq!(
@@ -643,7 +850,7 @@ mod tests {
                         Box::new(variable_atom!('t'))
                     ))
                 ],
-                size: 3 as u8,
+                size: 3,
             }
         )
     }
@@ -661,7 +868,7 @@ mod tests {
                             Box::new(integer_atom!(2)),
                             Box::new(integer_atom!(3)),
                         ],
-                        size: 3 as u8,
+                        size: 3,
                     }),
                     Box::new(Expression::Vector {
                         backing: vec![
@@ -669,7 +876,7 @@ mod tests {
                             Box::new(integer_atom!(5)),
                             Box::new(integer_atom!(6)),
                         ],
-                        size: 3 as u8,
+                        size: 3,
                     }),
                 ]
                 .into_iter()
@@ -850,4 +1057,12 @@ mod tests {
             Expression::Matrix { backing: vec![], shape: (0, 0) }
         )
     }
+
+    #[test]
+    fn test_operator_not_variable() {
+        for s in ["+", ")", ","] {
+            assert!(parse_add_sub(s).is_err(), "{}", s);
+        }
+        assert_eq!(parse_add_sub("x+)").unwrap().0, "+)");
+    }
 }
@@ -0,0 +1,570 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::{char, multispace0},
+    multi::many0,
+    sequence::{delimited, preceded},
+    IResult,
+};
+
+use crate::{
+    expression::expression_tree::{
+        format_decimal_point, Atom, Comparison, Escape, Expression, Numeric,
+    },
+    ParseError,
+};
+
+// the heads of lists which are not function calls, a function with one of these names is written
+// as (call name args...) so that it reads back as a function
+const HEADS: &[&str] = &[
+    "call",
+    "-",
+    "!",
+    "%",
+    "+",
+    "*",
+    "/",
+    "^",
+    "mod",
+    "vector",
+    "matrix",
+    "integral",
+    "sum",
+    "=",
+    "!=",
+    "<",
+    "<=",
+    ">",
+    ">=",
+    "and",
+    "or",
+    "not",
+    "piecewise",
+    "complex",
+    "error",
+];
+
+// writes an expression as an s-expression, every operation is a list of its operator followed by
+// its operands, (+ (* (/ 5 6) x) 1)
+// numbers and variables are written as they are, fractions as 5/6, decimals always with a point
+// and complex numbers as (complex re im), a negation is (- x), matrices list their rows,
+// (matrix (1 2) (3 4)), and piecewise definitions their (value condition) branches
+pub fn to_sexpr(expr: &Expression) -> String {
+    let mut out = String::new();
+    to_sexpr_into(expr, &mut out);
+    out
+}
+
+fn to_sexpr_into(expr: &Expression, out: &mut String) {
+    match expr {
+        Expression::Atom(a) => atom(a, out),
+
+        Expression::Negate(e) => list("-", [&**e], out),
+        Expression::Factorial(e) => list("!", [&**e], out),
+        Expression::Percent(e) => list("%", [&**e], out),
+        Expression::Not(e) => list("not", [&**e], out),
+
+        Expression::Add(l, r) => list("+", [&**l, &**r], out),
+        Expression::Subtract(l, r) => list("-", [&**l, &**r], out),
+        Expression::Multiply(l, r) => list("*", [&**l, &**r], out),
+        Expression::Divide(l, r) => list("/", [&**l, &**r], out),
+        Expression::Power(l, r) => list("^", [&**l, &**r], out),
+        Expression::Modulus(l, r) => list("mod", [&**l, &**r], out),
+        Expression::Relation(op, l, r) => list(comparison(*op), [&**l, &**r], out),
+
+        Expression::Function { name, args } if HEADS.contains(&name.as_str()) => {
+            write!(out, "(call {}", name).unwrap();
+            for arg in args {
+                out.push(' ');
+                to_sexpr_into(arg, out);
+            }
+            out.push(')');
+        }
+        Expression::Function { name, args } => list(name, args.iter().map(|e| &**e), out),
+        Expression::Vector {
+            backing: vec,
+            size: _,
+        } => list("vector", vec.iter().map(|e| &**e), out),
+        Expression::And(es) => list("and", es.iter().map(|e| &**e), out),
+        Expression::Or(es) => list("or", es.iter().map(|e| &**e), out),
+
+        Expression::Matrix {
+            backing: vec,
+            shape: (rs, cs),
+        } => {
+            out.push_str("(matrix");
+            for r in 0..*rs as usize {
+                let row = &vec[r * *cs as usize..(r + 1) * *cs as usize];
+                out.push(' ');
+                row_into(row.iter().map(|e| &**e), out);
+            }
+            out.push(')');
+        }
+        Expression::Piecewise(branches) => {
+            out.push_str("(piecewise");
+            for (value, condition) in branches {
+                out.push(' ');
+                row_into([value, condition], out);
+            }
+            out.push(')');
+        }
+
+        // (sum body index lower upper)
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => {
+            out.push_str("(sum ");
+            to_sexpr_into(body, out);
+            write!(out, " {}", index).unwrap();
+            for bound in [lower, upper] {
+                out.push(' ');
+                to_sexpr_into(bound, out);
+            }
+            out.push(')');
+        }
+
+        // (integral f x), or (integral f x lower upper) with nil for a missing bound
+        Expression::Integral {
+            integrand,
+            var,
+            lower,
+            upper,
+        } => {
+            out.push_str("(integral ");
+            to_sexpr_into(integrand, out);
+            write!(out, " {}", var).unwrap();
+            if lower.is_some() || upper.is_some() {
+                for bound in [lower, upper] {
+                    out.push(' ');
+                    match bound {
+                        Some(bound) => to_sexpr_into(bound, out),
+                        None => out.push_str("nil"),
+                    }
+                }
+            }
+            out.push(')');
+        }
+    }
+}
+
+fn list<'a>(head: &str, operands: impl IntoIterator<Item = &'a Expression>, out: &mut String) {
+    out.push('(');
+    out.push_str(head);
+    for e in operands {
+        out.push(' ');
+        to_sexpr_into(e, out);
+    }
+    out.push(')');
+}
+
+// a list without a head, the rows of a matrix and the branches of a piecewise definition
+fn row_into<'a>(entries: impl IntoIterator<Item = &'a Expression>, out: &mut String) {
+    out.push('(');
+    for (i, e) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        to_sexpr_into(e, out);
+    }
+    out.push(')');
+}
+
+fn atom(a: &Atom, out: &mut String) {
+    match a {
+        Atom::Numeric(Numeric::Integer(i)) => write!(out, "{}", i).unwrap(),
+        Atom::Numeric(Numeric::Decimal(d)) => decimal(*d, out),
+        Atom::Numeric(Numeric::Fraction(n, d)) => write!(out, "{}/{}", n, d).unwrap(),
+        Atom::Numeric(Numeric::Complex { re, im }) => {
+            out.push_str("(complex ");
+            decimal(*re, out);
+            out.push(' ');
+            decimal(*im, out);
+            out.push(')');
+        }
+        Atom::Variable(v) => out.push(*v),
+        Atom::Escape(e, n) => write!(out, "{}{}", e, n).unwrap(),
+        Atom::Error(e) => write!(out, "(error {:?})", e).unwrap(),
+        Atom::Boolean(b) => write!(out, "{}", b).unwrap(),
+    }
+}
+
+// every digit it takes to read the decimal back, with a point so that it does not read back as an
+// integer
+fn decimal(d: f32, out: &mut String) {
+    out.push_str(&format_decimal_point(d, None));
+}
+
+fn comparison(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Equal => "=",
+        Comparison::NotEqual => "!=",
+        Comparison::Less => "<",
+        Comparison::LessEqual => "<=",
+        Comparison::Greater => ">",
+        Comparison::GreaterEqual => ">=",
+    }
+}
+
+// Node: the untyped tree of an s-expression, every node keeps the input from where it starts so
+// that errors can point at it
+enum Node<'a> {
+    Token(&'a str),
+    List(&'a str, Vec<Node<'a>>),
+}
+
+fn parse_node(input: &str) -> IResult<&str, Node<'_>> {
+    let (input, _) = multispace0(input)?;
+    match input.starts_with('(') {
+        true => {
+            let (rest, nodes) = delimited(
+                char('('),
+                many0(parse_node),
+                preceded(multispace0, char(')')),
+            )(input)?;
+            Ok((rest, Node::List(input, nodes)))
+        }
+        false => {
+            let (rest, token) =
+                take_while1(|c: char| !c.is_whitespace() && c != '(' && c != ')')(input)?;
+            Ok((rest, Node::Token(token)))
+        }
+    }
+}
+
+// the most lists from_sexpr reads inside each other, every list is a level of recursion
+const MAX_DEPTH: usize = 128;
+
+// reads back an expression written by to_sexpr, reporting the byte offset of the token or list
+// which could not be read
+// input with lists nested deeper than MAX_DEPTH is refused before it is read
+pub fn from_sexpr(input: &str) -> Result<Expression, ParseError> {
+    if list_depth(input) > MAX_DEPTH {
+        return Err(ParseError::TooDeep {
+            max_depth: MAX_DEPTH,
+        });
+    }
+
+    // every node is a slice of the input
+    let position = |at: &str| at.as_ptr() as usize - input.as_ptr() as usize;
+    match parse_node(input) {
+        Ok((rest, node)) => match rest.trim_start() {
+            "" => expression(&node, &position),
+            rest => Err(ParseError::InvalidSyntax {
+                position: position(rest),
+            }),
+        },
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(ParseError::InvalidSyntax {
+            position: position(e.input),
+        }),
+        Err(nom::Err::Incomplete(_)) => Err(ParseError::InvalidSyntax {
+            position: input.len(),
+        }),
+    }
+}
+
+// how deeply the lists of the input nest, tokens never contain parentheses
+fn list_depth(input: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut max = 0;
+    for c in input.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            ')' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    max
+}
+
+fn expression(node: &Node, position: &dyn Fn(&str) -> usize) -> Result<Expression, ParseError> {
+    let invalid = |at: &str| ParseError::InvalidSyntax {
+        position: position(at),
+    };
+    let (at, nodes) = match node {
+        Node::Token(token) => return token_atom(token).ok_or_else(|| invalid(token)),
+        Node::List(at, nodes) => (*at, nodes.as_slice()),
+    };
+    let operands = |nodes: &[Node]| {
+        nodes
+            .iter()
+            .map(|node| expression(node, position))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let boxed = |nodes: &[Node]| Ok::<_, ParseError>(operands(nodes)?.into_iter().map(Box::new));
+
+    let (head, rest) = match nodes {
+        [Node::Token(head), rest @ ..] => (*head, rest),
+        _ => return Err(invalid(at)),
+    };
+    let unary = |f: fn(Box<Expression>) -> Expression| match rest {
+        [e] => Ok(f(Box::new(expression(e, position)?))),
+        _ => Err(invalid(at)),
+    };
+    let binary = |f: &dyn Fn(Box<Expression>, Box<Expression>) -> Expression| match rest {
+        [l, r] => Ok(f(
+            Box::new(expression(l, position)?),
+            Box::new(expression(r, position)?),
+        )),
+        _ => Err(invalid(at)),
+    };
+
+    match head {
+        "-" if rest.len() == 1 => unary(Expression::Negate),
+        "!" => unary(Expression::Factorial),
+        "%" => unary(Expression::Percent),
+        "not" => unary(Expression::Not),
+
+        "+" => binary(&Expression::Add),
+        "-" => binary(&Expression::Subtract),
+        "*" => binary(&Expression::Multiply),
+        "/" => binary(&Expression::Divide),
+        "^" => binary(&Expression::Power),
+        "mod" => binary(&Expression::Modulus),
+        "=" | "!=" | "<" | "<=" | ">" | ">=" => {
+            let op = [
+                Comparison::Equal,
+                Comparison::NotEqual,
+                Comparison::Less,
+                Comparison::LessEqual,
+                Comparison::Greater,
+                Comparison::GreaterEqual,
+            ]
+            .into_iter()
+            .find(|op| comparison(*op) == head)
+            .unwrap();
+            binary(&|l, r| Expression::Relation(op, l, r))
+        }
+
+        "and" => Ok(Expression::And(boxed(rest)?.collect())),
+        "or" => Ok(Expression::Or(boxed(rest)?.collect())),
+        "vector" => {
+            let backing = boxed(rest)?.collect::<Vec<_>>();
+            Ok(Expression::Vector {
+                size: u8::try_from(backing.len()).map_err(|_| invalid(at))?,
+                backing,
+            })
+        }
+        "matrix" => {
+            let rows = rest
+                .iter()
+                .map(|row| match row {
+                    Node::List(_, entries) => boxed(entries).map(|e| e.collect::<Vec<_>>()),
+                    Node::Token(token) => Err(invalid(token)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let cs = rows.first().map_or(0, |row| row.len());
+            if rows.iter().any(|row| row.len() != cs) {
+                return Err(invalid(at));
+            }
+            Ok(Expression::Matrix {
+                shape: (
+                    u8::try_from(rows.len()).map_err(|_| invalid(at))?,
+                    u8::try_from(cs).map_err(|_| invalid(at))?,
+                ),
+                backing: rows.into_iter().flatten().collect(),
+            })
+        }
+        "piecewise" => Ok(Expression::Piecewise(
+            rest.iter()
+                .map(|branch| match branch {
+                    Node::List(_, nodes) if nodes.len() == 2 => Ok((
+                        expression(&nodes[0], position)?,
+                        expression(&nodes[1], position)?,
+                    )),
+                    Node::List(at, _) | Node::Token(at) => Err(invalid(at)),
+                })
+                .collect::<Result<_, _>>()?,
+        )),
+        "integral" => {
+            let bound = |node: &Node| match node {
+                Node::Token("nil") => Ok(None),
+                node => expression(node, position).map(|e| Some(Box::new(e))),
+            };
+            let (integrand, var, lower, upper) = match rest {
+                [integrand, var] => (integrand, var, None, None),
+                [integrand, var, lower, upper] => (integrand, var, bound(lower)?, bound(upper)?),
+                _ => return Err(invalid(at)),
+            };
+            match expression(var, position)? {
+                Expression::Atom(Atom::Variable(var)) => Ok(Expression::Integral {
+                    integrand: Box::new(expression(integrand, position)?),
+                    var,
+                    lower,
+                    upper,
+                }),
+                _ => Err(invalid(at)),
+            }
+        }
+        "sum" => match rest {
+            [body, index, lower, upper] => match expression(index, position)? {
+                Expression::Atom(Atom::Variable(index)) => Ok(Expression::Sum {
+                    index,
+                    lower: Box::new(expression(lower, position)?),
+                    upper: Box::new(expression(upper, position)?),
+                    body: Box::new(expression(body, position)?),
+                }),
+                _ => Err(invalid(at)),
+            },
+            _ => Err(invalid(at)),
+        },
+        "complex" => match operands(rest)?.as_slice() {
+            [Expression::Atom(Atom::Numeric(re)), Expression::Atom(Atom::Numeric(im))] => {
+                Ok(Expression::Atom(Atom::Numeric(Numeric::Complex {
+                    re: f32::from(*re),
+                    im: f32::from(*im),
+                })))
+            }
+            _ => Err(invalid(at)),
+        },
+        "error" => match rest {
+            [Node::Token("NotEnoughMemory")] => Ok(crate::Error::NotEnoughMemory),
+            [Node::Token("InvalidSyntax")] => Ok(crate::Error::InvalidSyntax),
+            [Node::Token("UndefinedSymbol")] => Ok(crate::Error::UndefinedSymbol),
+            _ => Err(invalid(at)),
+        }
+        .map(|e| Expression::Atom(Atom::Error(e))),
+
+        "call" => match rest {
+            [Node::Token(name), args @ ..] => Ok(Expression::Function {
+                name: name.to_string(),
+                args: boxed(args)?.collect(),
+            }),
+            _ => Err(invalid(at)),
+        },
+        name => Ok(Expression::Function {
+            name: name.to_string(),
+            args: boxed(rest)?.collect(),
+        }),
+    }
+}
+
+// a number, fraction, boolean, escape or single letter variable
+fn token_atom(token: &str) -> Option<Expression> {
+    let numeric = |n| Some(Expression::Atom(Atom::Numeric(n)));
+    if let Ok(i) = token.parse::<i32>() {
+        return numeric(Numeric::Integer(i));
+    }
+    if let Some((n, d)) = token.split_once('/') {
+        return match (n.parse::<i32>(), d.parse::<i32>()) {
+            (Ok(n), Ok(d)) => numeric(Numeric::Fraction(n, d)),
+            _ => None,
+        };
+    }
+    if token.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') {
+        return numeric(Numeric::Decimal(token.parse().ok()?));
+    }
+
+    let mut chars = token.chars();
+    match (chars.next()?, chars.next(), chars.as_str()) {
+        ('_', Some(kind), n) => {
+            let kind = match kind {
+                'A' => Escape::Atom,
+                'F' => Escape::Function,
+                'V' => Escape::Vector,
+                'M' => Escape::Matrix,
+                '*' => Escape::Everything,
+                _ => return None,
+            };
+            Some(Expression::Atom(Atom::Escape(kind, n.parse().ok()?)))
+        }
+        (v, None, _) if v.is_alphabetic() => Some(Expression::Atom(Atom::Variable(v))),
+        _ => match token {
+            "true" => Some(Expression::Atom(Atom::Boolean(true))),
+            "false" => Some(Expression::Atom(Atom::Boolean(false))),
+            "inf" => numeric(Numeric::Decimal(f32::INFINITY)),
+            "NaN" => numeric(Numeric::Decimal(f32::NAN)),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::{from_sexpr, to_sexpr};
+    use crate::{
+        expression::{expression_tree::Expression, latex},
+        ParseError,
+    };
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_to_sexpr() {
+        assert_eq!(to_sexpr(&parse("5 / 6 * 5 + x")), "(+ (* (/ 5 6) 5) x)");
+        assert_eq!(to_sexpr(&parse("-sin(x)^2.5!")), "(- (! (^ (sin x) 2.5)))");
+        assert_eq!(
+            to_sexpr(&parse("[1, 2; 3, 4] * <a, b>")),
+            "(* (matrix (1 2) (3 4)) (vector a b))"
+        );
+        assert_eq!(
+            to_sexpr(&latex::parse("0 < x \\le 1 \\lor \\lnot y = 2")),
+            "(or (and (< 0 x) (<= x 1)) (not (= y 2)))"
+        );
+        assert_eq!(
+            to_sexpr(&latex::parse("\\int_{0}^{1} 3x \\, dx")),
+            "(integral (* 3 x) x 0 1)"
+        );
+    }
+
+    #[test]
+    fn test_sexpr_roundtrip() {
+        for s in [
+            "5 / 6 * 5 + x",
+            "-(a - b) % 2 + 50%",
+            "f(x, g(y)) - max(1, 2.0)",
+            "[1, 2; 3, 4] * <a, b> + [x]",
+            "_*1 + _A2 * 1.5e-7",
+        ] {
+            assert_eq!(from_sexpr(&to_sexpr(&parse(s))), Ok(parse(s)), "{}", s);
+        }
+        for s in [
+            "\\frac{1}{x} + 2.5i - 3",
+            "\\begin{cases} x & x > 0 \\lor \\top \\\\ -x & x \\neq 0 \\end{cases}",
+            "\\int x^2 \\, dx",
+            "\\sum_{i=1}^{n} i^2",
+        ] {
+            let expr = latex::parse(s);
+            assert_eq!(from_sexpr(&to_sexpr(&expr)), Ok(expr), "{}", s);
+        }
+
+        // functions sharing the name of an operator are called explicitly
+        let not = parse("not(x) + mod(1, 2)");
+        assert_eq!(to_sexpr(&not), "(+ (call not x) (call mod 1 2))");
+        assert_eq!(from_sexpr(&to_sexpr(&not)), Ok(not));
+    }
+
+    #[test]
+    fn test_from_sexpr_errors() {
+        let at = |position| Err(ParseError::InvalidSyntax { position });
+
+        assert_eq!(from_sexpr("(+ 1 2"), at(6));
+        assert_eq!(from_sexpr("(+ 1 2) x"), at(8));
+        assert_eq!(from_sexpr("(+ 1 (^ 2))"), at(5));
+        assert_eq!(from_sexpr("(* 1 xy)"), at(5));
+        assert_eq!(from_sexpr("(matrix (1 2) (3))"), at(0));
+        assert_eq!(from_sexpr("()"), at(0));
+
+        let deep = |depth| "(- ".repeat(depth) + "x" + &")".repeat(depth);
+        assert!(from_sexpr(&deep(128)).is_ok());
+        assert_eq!(
+            from_sexpr(&deep(200_000)),
+            Err(ParseError::TooDeep { max_depth: 128 })
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::expression::expression_tree::{
+    chain_links, precedence, Atom, Comparison, Expression, Link, Numeric,
+};
+
+// renders an expression as plain unicode math, x^2 -> x², sqrt(x+1) -> √(x+1), a*b -> a·b
+// exponents without a superscript form fall back to ascii, x^(a+b)
+pub fn to_unicode(expr: &Expression) -> String {
+    let mut out = String::new();
+    to_unicode_into(expr, &mut out);
+    out
+}
+
+// operands binding looser than their operator are parenthesized, by the shared precedence table
+fn to_unicode_operand(expr: &Expression, min_precedence: u8, out: &mut String) {
+    if precedence(expr) < min_precedence {
+        out.push('(');
+        to_unicode_into(expr, out);
+        out.push(')');
+    } else {
+        to_unicode_into(expr, out);
+    }
+}
+
+fn to_unicode_into(expr: &Expression, out: &mut String) {
+    match expr {
+        Expression::Atom(Atom::Numeric(Numeric::Fraction(n, d))) => {
+            write!(out, "{}/{}", n, d).unwrap()
+        }
+        Expression::Atom(a) => write!(out, "{}", a).unwrap(),
+
+        Expression::Negate(e) => {
+            out.push('-');
+            to_unicode_operand(e, 3, out);
+        }
+        Expression::Factorial(e) => {
+            to_unicode_operand(e, 6, out);
+            out.push('!');
+        }
+        Expression::Percent(e) => {
+            to_unicode_operand(e, 6, out);
+            out.push('%');
+        }
+
+        Expression::Add(l, r) => {
+            to_unicode_operand(l, 1, out);
+            out.push('+');
+            to_unicode_operand(r, 1, out);
+        }
+        Expression::Subtract(l, r) => {
+            to_unicode_operand(l, 1, out);
+            out.push('-');
+            to_unicode_operand(r, 2, out);
+        }
+        Expression::Multiply(l, r) => {
+            to_unicode_operand(l, 2, out);
+            out.push('·');
+            to_unicode_operand(r, 3, out);
+        }
+        Expression::Divide(l, r) => {
+            to_unicode_operand(l, 2, out);
+            out.push('/');
+            to_unicode_operand(r, 3, out);
+        }
+        Expression::Modulus(l, r) => {
+            to_unicode_operand(l, 2, out);
+            out.push('%');
+            to_unicode_operand(r, 3, out);
+        }
+
+        Expression::Power(l, r) => {
+            to_unicode_operand(l, 6, out);
+            match superscript(r) {
+                Some(exponent) => out.push_str(&exponent),
+                None => {
+                    out.push('^');
+                    to_unicode_operand(r, 6, out);
+                }
+            }
+        }
+
+        Expression::Function { name, args } if name == "sqrt" && args.len() == 1 => {
+            out.push('√');
+            to_unicode_operand(&args[0], 6, out);
+        }
+        Expression::Function { name, args } if name == "abs" && args.len() == 1 => {
+            out.push('|');
+            to_unicode_into(&args[0], out);
+            out.push('|');
+        }
+        Expression::Function { name, args }
+            if (name == "floor" || name == "ceil") && args.len() == 1 =>
+        {
+            let (open, close) = match name.as_str() {
+                "floor" => ('⌊', '⌋'),
+                _ => ('⌈', '⌉'),
+            };
+            out.push(open);
+            to_unicode_into(&args[0], out);
+            out.push(close);
+        }
+        Expression::Function { name, args } => {
+            out.push_str(name);
+            out.push('(');
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                to_unicode_into(arg, out);
+            }
+            out.push(')');
+        }
+
+        Expression::Vector {
+            backing: vec,
+            size: _,
+        } => {
+            out.push('⟨');
+            for (i, e) in vec.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                to_unicode_into(e, out);
+            }
+            out.push('⟩');
+        }
+
+        Expression::Matrix {
+            backing: vec,
+            shape: (rs, cs),
+        } => {
+            out.push('[');
+            for r in 0..*rs {
+                if r > 0 {
+                    out.push_str("; ");
+                }
+                for c in 0..*cs {
+                    if c > 0 {
+                        out.push_str(", ");
+                    }
+                    to_unicode_into(&vec[(*cs * r + c) as usize], out);
+                }
+            }
+            out.push(']');
+        }
+
+        // ∫_a^b f dx, with compound bounds in parentheses
+        Expression::Integral {
+            integrand,
+            var,
+            lower,
+            upper,
+        } => {
+            out.push('∫');
+            if let Some(lower) = lower {
+                out.push('_');
+                to_unicode_operand(lower, 6, out);
+            }
+            if let Some(upper) = upper {
+                out.push('^');
+                to_unicode_operand(upper, 6, out);
+            }
+            out.push(' ');
+            to_unicode_operand(integrand, 2, out);
+            write!(out, " d{}", var).unwrap();
+        }
+        // ∑_(i=1)^n f, the body reaches as far as an integrand does
+        Expression::Sum {
+            index,
+            lower,
+            upper,
+            body,
+        } => {
+            write!(out, "∑_({}=", index).unwrap();
+            to_unicode_into(lower, out);
+            out.push_str(")^");
+            to_unicode_operand(upper, 6, out);
+            out.push(' ');
+            to_unicode_operand(body, 2, out);
+        }
+
+        Expression::Relation(op, l, r) => {
+            to_unicode_operand(l, 1, out);
+            out.push(comparison(*op));
+            to_unicode_operand(r, 1, out);
+        }
+        // 0<x<1, members which do not continue a chain are joined by a wedge
+        Expression::And(es) => {
+            for (i, link) in chain_links(es).into_iter().enumerate() {
+                match link {
+                    Link::Chained(op, r) => {
+                        out.push(comparison(op));
+                        to_unicode_operand(r, 1, out);
+                    }
+                    Link::Joined(e) => {
+                        if i > 0 {
+                            out.push_str(" ∧ ");
+                        }
+                        match e {
+                            Expression::Or(_) => to_unicode_operand(e, 1, out),
+                            _ => to_unicode_into(e, out),
+                        }
+                    }
+                }
+            }
+        }
+        Expression::Or(es) => {
+            for (i, e) in es.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" ∨ ");
+                }
+                to_unicode_into(e, out);
+            }
+        }
+        Expression::Not(e) => {
+            out.push('¬');
+            to_unicode_operand(e, 3, out);
+        }
+
+        // {x if x>0; -x if x≤0}
+        Expression::Piecewise(branches) => {
+            out.push('{');
+            for (i, (value, condition)) in branches.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("; ");
+                }
+                to_unicode_into(value, out);
+                out.push_str(" if ");
+                to_unicode_into(condition, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn comparison(op: Comparison) -> char {
+    match op {
+        Comparison::Equal => '=',
+        Comparison::NotEqual => '≠',
+        Comparison::Less => '<',
+        Comparison::LessEqual => '≤',
+        Comparison::Greater => '>',
+        Comparison::GreaterEqual => '≥',
+    }
+}
+
+// the superscript form of an integer exponent, only integers have one
+fn superscript(exponent: &Expression) -> Option<String> {
+    let value = match exponent {
+        Expression::Atom(Atom::Numeric(Numeric::Integer(i))) => *i,
+        Expression::Negate(e) => match **e {
+            Expression::Atom(Atom::Numeric(Numeric::Integer(i))) => i.checked_neg()?,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let mut digits = String::new();
+    write!(digits, "{}", value).unwrap();
+    Some(
+        digits
+            .chars()
+            .map(|c| match c {
+                '-' => '⁻',
+                '0' => '⁰',
+                '1' => '¹',
+                '2' => '²',
+                '3' => '³',
+                '4' => '⁴',
+                '5' => '⁵',
+                '6' => '⁶',
+                '7' => '⁷',
+                '8' => '⁸',
+                _ => '⁹',
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use core::str::FromStr;
+
+    use super::to_unicode;
+    use crate::expression::{expression_tree::Expression, latex};
+
+    fn parse(s: &str) -> Expression {
+        Expression::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_superscript() {
+        assert_eq!(to_unicode(&parse("x^2")), "x²");
+        assert_eq!(to_unicode(&parse("x^10")), "x¹⁰");
+        assert_eq!(to_unicode(&parse("x^(-1)")), "x⁻¹");
+        assert_eq!(to_unicode(&parse("(x+1)^2")), "(x+1)²");
+        assert_eq!(to_unicode(&parse("(x^2)^3")), "(x²)³");
+    }
+
+    #[test]
+    fn test_superscript_fallback() {
+        assert_eq!(to_unicode(&parse("x^(a+b)")), "x^(a+b)");
+        assert_eq!(to_unicode(&parse("x^y")), "x^y");
+        assert_eq!(to_unicode(&parse("x^0.5")), "x^0.5");
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(to_unicode(&parse("sqrt(x)")), "√x");
+        assert_eq!(to_unicode(&parse("sqrt(x+1)")), "√(x+1)");
+        assert_eq!(to_unicode(&parse("sqrt(x^2)")), "√(x²)");
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(to_unicode(&parse("2*x+y/3")), "2·x+y/3");
+        assert_eq!(to_unicode(&parse("(a+b)/(c*d)")), "(a+b)/(c·d)");
+        assert_eq!(to_unicode(&parse("a-(b-c)")), "a-(b-c)");
+        assert_eq!(to_unicode(&parse("-(x+1)")), "-(x+1)");
+        assert_eq!(to_unicode(&parse("f(x,y)")), "f(x, y)");
+    }
+
+    #[test]
+    fn test_relations() {
+        assert_eq!(to_unicode(&latex::parse("0 < x \\le 1")), "0<x≤1");
+        assert_eq!(to_unicode(&latex::parse("a \\neq b+1")), "a≠b+1");
+        assert_eq!(
+            to_unicode(&latex::parse("x > 0 \\land y \\geq 0")),
+            "x>0 ∧ y≥0"
+        );
+        assert_eq!(
+            to_unicode(&latex::parse("\\lnot(x > 0 \\lor y = 1) \\land \\top")),
+            "¬(x>0 ∨ y=1) ∧ true"
+        );
+    }
+}
@@ -7,10 +7,13 @@ use core::fmt;
 
 extern crate alloc;
 
+use alloc::string::String;
+
 pub mod expression;
 pub mod modifier;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     NotEnoughMemory,
     InvalidSyntax,
@@ -26,3 +29,256 @@ impl fmt::Display for Error {
         }
     }
 }
+
+// ParseError: why an input string could not be parsed into an expression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseError {
+    InputTooLong { length: usize, max_length: usize },
+    InvalidSyntax { position: usize },
+    TooDeep { max_depth: usize },
+    Unsupported { construct: String, position: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InputTooLong { length, max_length } => write!(
+                f,
+                "Input too long: {} bytes, at most {} allowed",
+                length, max_length
+            ),
+            ParseError::InvalidSyntax { position } => {
+                write!(f, "Invalid syntax at byte {}", position)
+            }
+            ParseError::TooDeep { max_depth } => {
+                write!(f, "Input nested deeper than {} levels", max_depth)
+            }
+            ParseError::Unsupported {
+                construct,
+                position,
+            } => write!(f, "Unsupported construct {} at byte {}", construct, position),
+        }
+    }
+}
+
+// IntegrateError: why an expression could not be integrated symbolically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntegrateError {
+    Unsupported,
+}
+
+impl fmt::Display for IntegrateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrateError::Unsupported => write!(f, "Unsupported integrand"),
+        }
+    }
+}
+
+// ShapeError: why the operands of a matrix or vector operation do not fit together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShapeError {
+    Mismatch { expected: usize, found: usize },
+    NotAMatrix,
+    NotAVector,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShapeError::Mismatch { expected, found } => {
+                write!(f, "Expected a dimension of {}, found {}", expected, found)
+            }
+            ShapeError::NotAMatrix => write!(f, "Expected a matrix"),
+            ShapeError::NotAVector => write!(f, "Expected a vector"),
+        }
+    }
+}
+
+// MatrixError: why a matrix could not be inverted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MatrixError {
+    NotAMatrix,
+    NotSquare,
+    NotNumeric,
+    Singular,
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatrixError::NotAMatrix => write!(f, "Expected a matrix"),
+            MatrixError::NotSquare => write!(f, "Expected a square matrix"),
+            MatrixError::NotNumeric => write!(f, "Expected a numeric matrix"),
+            MatrixError::Singular => write!(f, "Singular matrix"),
+        }
+    }
+}
+
+// SolveError: why no root of an expression or equation could be found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SolveError {
+    NoConvergence,
+    ZeroDerivative,
+    NotNumeric,
+    NotQuadratic,
+    NotDifferentiable,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::NoConvergence => write!(f, "Did not converge to a root"),
+            SolveError::ZeroDerivative => write!(f, "Derivative vanished before reaching a root"),
+            SolveError::NotNumeric => write!(f, "Expected a numeric expression"),
+            SolveError::NotQuadratic => write!(f, "Expected a quadratic equation"),
+            SolveError::NotDifferentiable => write!(f, "Expression is not differentiable"),
+        }
+    }
+}
+
+// ExpandError: why an expression could not be expanded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExpandError {
+    TooLarge { max_nodes: usize },
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpandError::TooLarge { max_nodes } => {
+                write!(f, "Expansion grew past {} nodes", max_nodes)
+            }
+        }
+    }
+}
+
+// DiffError: why an expression could not be differentiated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiffError {
+    NotDifferentiable,
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffError::NotDifferentiable => write!(f, "Expression is not differentiable"),
+        }
+    }
+}
+
+// LimitError: why the limit of an expression could not be found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LimitError {
+    Unsupported,
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::Unsupported => write!(f, "Limit is not of a supported form"),
+        }
+    }
+}
+
+// EvalError: why an expression could not be evaluated to a number
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EvalError {
+    UndefinedVariable(char),
+    DomainError,
+    DivByZero,
+    ArityMismatch { expected: usize, found: usize },
+    UnknownFunction(String),
+    Shape(ShapeError),
+    Unsupported,
+    StepLimitExceeded,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UndefinedVariable(v) => write!(f, "Undefined variable {}", v),
+            EvalError::DomainError => write!(f, "Argument outside of the domain"),
+            EvalError::DivByZero => write!(f, "Division by zero"),
+            EvalError::ArityMismatch { expected, found } => {
+                write!(f, "Expected {} arguments, found {}", expected, found)
+            }
+            EvalError::UnknownFunction(name) => write!(f, "Unknown function {}", name),
+            EvalError::Shape(e) => write!(f, "{}", e),
+            EvalError::Unsupported => write!(f, "Unsupported expression"),
+            EvalError::StepLimitExceeded => write!(f, "Evaluation took too many steps"),
+        }
+    }
+}
+
+// with std, every error is a std::error::Error, the errors themselves only need core::fmt
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+#[cfg(feature = "std")]
+impl std::error::Error for IntegrateError {}
+#[cfg(feature = "std")]
+impl std::error::Error for ShapeError {}
+#[cfg(feature = "std")]
+impl std::error::Error for MatrixError {}
+#[cfg(feature = "std")]
+impl std::error::Error for SolveError {}
+#[cfg(feature = "std")]
+impl std::error::Error for DiffError {}
+#[cfg(feature = "std")]
+impl std::error::Error for LimitError {}
+#[cfg(feature = "std")]
+impl std::error::Error for ExpandError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EvalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EvalError::Shape(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::{
+        expression::latex::{parse_with_options, ParseOptions},
+        ParseError,
+    };
+
+    #[test]
+    fn test_parse_error_display() {
+        let error = parse_with_options("1 + 2) * 3", &ParseOptions::default()).unwrap_err();
+
+        assert_eq!(error, ParseError::InvalidSyntax { position: 5 });
+        assert!(error.to_string().contains("byte 5"));
+        assert_eq!(
+            ParseError::Unsupported {
+                construct: "align".to_string(),
+                position: 7
+            }
+            .to_string(),
+            "Unsupported construct align at byte 7"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_std_error() {
+        use std::error::Error;
+
+        use crate::{EvalError, ShapeError};
+
+        let error = EvalError::Shape(ShapeError::NotAMatrix);
+        assert_eq!(error.source().unwrap().to_string(), "Expected a matrix");
+        assert!(EvalError::DivByZero.source().is_none());
+
+        let boxed: std::boxed::Box<dyn Error> =
+            std::boxed::Box::new(ParseError::TooDeep { max_depth: 1 });
+        assert_eq!(boxed.to_string(), "Input nested deeper than 1 levels");
+    }
+}
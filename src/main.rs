@@ -0,0 +1,87 @@
+use std::io::{self, BufRead, Write};
+
+use citrus_cas::{
+    expression::{
+        calculus::differentiate,
+        eval::{evaluate, Value},
+        expression_tree::Expression,
+        latex::latexify,
+    },
+    modifier::{adaptable_modifier::AdaptableModifier, default::simplifier},
+};
+
+// how entered expressions are shown, and whether they are differentiated first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Plain,
+    Latex,
+    Diff(char),
+}
+
+fn main() -> io::Result<()> {
+    run(io::stdin().lock(), io::stdout().lock())
+}
+
+// reads one expression or command per line until the input ends or :quit is entered
+// every expression is printed, followed by its simplified form and, when it evaluates to a
+// number, its value
+fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let simplify = simplifier();
+    let mut mode = Mode::Plain;
+
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        match line.strip_prefix(':') {
+            Some(command) => match command.split_whitespace().collect::<Vec<_>>()[..] {
+                ["quit"] | ["q"] => break,
+                ["plain"] => mode = Mode::Plain,
+                ["latex"] => mode = Mode::Latex,
+                ["diff", var] if var.chars().count() == 1 => {
+                    mode = Mode::Diff(var.chars().next().unwrap())
+                }
+                _ => writeln!(
+                    output,
+                    "unknown command, expected :plain, :latex, :diff <var> or :quit"
+                )?,
+            },
+            None if line.is_empty() => (),
+            None => match line.parse::<Expression>() {
+                Ok(expr) => {
+                    let expr = match mode {
+                        Mode::Diff(var) => differentiate(&expr, var),
+                        _ => Ok(expr),
+                    };
+                    match expr {
+                        Ok(expr) => {
+                            let mut simplified = expr.clone();
+                            simplified.simplify_im::<AdaptableModifier, 100>(&simplify);
+
+                            writeln!(output, "{}", show(&expr, mode))?;
+                            writeln!(output, "= {}", show(&simplified, mode))?;
+                            if let Ok(Value::Scalar(value)) = evaluate(&expr) {
+                                writeln!(output, "≈ {}", value)?;
+                            }
+                        }
+                        Err(e) => writeln!(output, "error: {}", e)?,
+                    }
+                }
+                Err(e) => writeln!(output, "error: {}", e)?,
+            },
+        }
+
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+fn show(expr: &Expression, mode: Mode) -> String {
+    match mode {
+        Mode::Latex => latexify(expr),
+        Mode::Plain | Mode::Diff(_) => expr.to_string(),
+    }
+}
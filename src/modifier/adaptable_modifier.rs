@@ -1,6 +1,7 @@
 use core::{
     fmt,
     hash::BuildHasher,
+    iter,
     ops::{Add, AddAssign},
 };
 
@@ -163,26 +164,61 @@ impl ModifierImmutable for AdaptableModifier {
                 }
             }
 
-            Expression::Function { name: _, args: a } => {
+            Expression::Function { name: _, args: a } | Expression::And(a) | Expression::Or(a) => {
                 for expr in a {
                     modified = self.modify_immut(expr) || modified;
                 }
             }
 
-            Expression::Negate(e1) | Expression::Factorial(e1) | Expression::Percent(e1) => {
-                modified = self.modify_immut(e1)
-            }
+            Expression::Negate(e1)
+            | Expression::Factorial(e1)
+            | Expression::Percent(e1)
+            | Expression::Not(e1) => modified = self.modify_immut(e1),
 
             Expression::Add(e1, e2)
             | Expression::Subtract(e1, e2)
             | Expression::Multiply(e1, e2)
             | Expression::Divide(e1, e2)
             | Expression::Power(e1, e2)
-            | Expression::Modulus(e1, e2) => {
+            | Expression::Modulus(e1, e2)
+            | Expression::Relation(_, e1, e2) => {
                 let m1 = self.modify_immut(e1);
                 let m2 = self.modify_immut(e2);
                 modified = m1 || m2;
             }
+
+            Expression::Integral {
+                integrand,
+                var: _,
+                lower,
+                upper,
+            } => {
+                for e in iter::once(integrand)
+                    .chain(lower.iter_mut())
+                    .chain(upper.iter_mut())
+                {
+                    modified = self.modify_immut(e) || modified;
+                }
+            }
+
+            Expression::Sum {
+                index: _,
+                lower,
+                upper,
+                body,
+            } => {
+                for e in [lower, upper, body] {
+                    modified = self.modify_immut(e) || modified;
+                }
+            }
+
+            Expression::Piecewise(branches) => {
+                for (value, condition) in branches {
+                    let m1 = self.modify_immut(value);
+                    let m2 = self.modify_immut(condition);
+                    modified = m1 || m2 || modified;
+                }
+            }
         }
 
         let mut rule_mod;
@@ -223,26 +259,61 @@ impl ModifierMutable for AdaptableModifier {
                 }
             }
 
-            Expression::Function { name: _, args: a } => {
+            Expression::Function { name: _, args: a } | Expression::And(a) | Expression::Or(a) => {
                 for expr in a {
                     modified = self.modify_mut(expr);
                 }
             }
 
-            Expression::Negate(e1) | Expression::Factorial(e1) | Expression::Percent(e1) => {
-                modified = self.modify_mut(e1)
-            }
+            Expression::Negate(e1)
+            | Expression::Factorial(e1)
+            | Expression::Percent(e1)
+            | Expression::Not(e1) => modified = self.modify_mut(e1),
 
             Expression::Add(e1, e2)
             | Expression::Subtract(e1, e2)
             | Expression::Multiply(e1, e2)
             | Expression::Divide(e1, e2)
             | Expression::Power(e1, e2)
-            | Expression::Modulus(e1, e2) => {
+            | Expression::Modulus(e1, e2)
+            | Expression::Relation(_, e1, e2) => {
                 let m1 = self.modify_mut(e1);
                 let m2 = self.modify_mut(e2);
                 modified = m1 || m2;
             }
+
+            Expression::Integral {
+                integrand,
+                var: _,
+                lower,
+                upper,
+            } => {
+                for e in iter::once(integrand)
+                    .chain(lower.iter_mut())
+                    .chain(upper.iter_mut())
+                {
+                    modified = self.modify_mut(e) || modified;
+                }
+            }
+
+            Expression::Sum {
+                index: _,
+                lower,
+                upper,
+                body,
+            } => {
+                for e in [lower, upper, body] {
+                    modified = self.modify_mut(e) || modified;
+                }
+            }
+
+            Expression::Piecewise(branches) => {
+                for (value, condition) in branches {
+                    let m1 = self.modify_mut(value);
+                    let m2 = self.modify_mut(condition);
+                    modified = m1 || m2 || modified;
+                }
+            }
         }
 
         let mut rule_mod;
@@ -457,7 +528,7 @@ mod tests {
 
     #[test]
     fn test_adaptable_order() {
-        let mut modifier2 = AdaptableModifier::from_str_list(vec![
+        let modifier2 = AdaptableModifier::from_str_list(vec![
             ("_A1 + _A2", "3"),
             ("_A1 * _A2", "4"),
             ("_F1 + _A1", "1"),
@@ -465,11 +536,11 @@ mod tests {
         ]);
 
         let mut expr3 = "sin(x) * 5".parse::<Expression>().unwrap();
-        expr3.simplify_im::<AdaptableModifier, 100>(&mut modifier2);
+        expr3.simplify_im::<AdaptableModifier, 100>(&modifier2);
 
         assert_eq!(expr3, "2".parse::<Expression>().unwrap());
 
-        let mut modifier1 = AdaptableModifier::from_str_list(vec![
+        let modifier1 = AdaptableModifier::from_str_list(vec![
             ("_F1 + _A1", "1"),
             ("_F1 * _A1", "2"),
             ("_A1 + _A2", "3"),
@@ -477,12 +548,12 @@ mod tests {
         ]);
 
         let mut expr1 = "x + 5".parse::<Expression>().unwrap();
-        expr1.simplify_im::<AdaptableModifier, 100>(&mut modifier1);
+        expr1.simplify_im::<AdaptableModifier, 100>(&modifier1);
 
         assert_eq!(expr1, "3".parse::<Expression>().unwrap());
 
         let mut expr4 = "sin(x) * 5".parse::<Expression>().unwrap();
-        expr4.simplify_im::<AdaptableModifier, 100>(&mut modifier1);
+        expr4.simplify_im::<AdaptableModifier, 100>(&modifier1);
 
         assert_eq!(expr4, "2".parse::<Expression>().unwrap());
     }
@@ -493,11 +564,11 @@ mod tests {
 
         let mut expr = Expression::from_str("1 - 2").unwrap();
 
-        assert_eq!(modifier.modify_immut(&mut expr), true);
+        assert!(modifier.modify_immut(&mut expr));
 
         let mut expr = Expression::from_str("1 + 2").unwrap();
 
-        assert_eq!(modifier.modify_immut(&mut expr), false);
+        assert!(!modifier.modify_immut(&mut expr));
     }
 
     #[test]
@@ -537,7 +608,7 @@ mod tests {
 
     #[test]
     fn test_adaptable_recursive() {
-        let mut modifier = AdaptableModifier::from_str_list(vec![
+        let modifier = AdaptableModifier::from_str_list(vec![
             ("_*1 * _*2 + _*3", "_*1 / _*3"),
             ("5 * 6", "30"),
             ("_*1 / _*2", "5"),
@@ -546,30 +617,30 @@ mod tests {
 
         let mut expr = Expression::from_str("(8 * 9 + 5) * (2 ^ 10)").unwrap();
         let expected_expr = Expression::from_str("30").unwrap();
-        expr.simplify_im::<AdaptableModifier, 50>(&mut modifier);
+        expr.simplify_im::<AdaptableModifier, 50>(&modifier);
 
         assert_eq!(expr, expected_expr);
     }
 
     #[test]
     fn test_adaptable_atom() {
-        let mut modifier = AdaptableModifier::from_str_list(vec![("_*1 ^ _A1", "log(_A1)")]);
+        let modifier = AdaptableModifier::from_str_list(vec![("_*1 ^ _A1", "log(_A1)")]);
 
         let mut expr1 = Expression::from_str("2 ^ 10").unwrap();
         let expected_expr1 = Expression::from_str("log(10)").unwrap();
-        expr1.simplify_im::<AdaptableModifier, 50>(&mut modifier);
+        expr1.simplify_im::<AdaptableModifier, 50>(&modifier);
 
         assert_eq!(expr1, expected_expr1);
 
         let mut expr2 = Expression::from_str("2 ^ (10 + 5)").unwrap();
         let expected_expr2 = Expression::from_str("2 ^ (10 + 5)").unwrap();
-        expr2.simplify_im::<AdaptableModifier, 50>(&mut modifier);
+        expr2.simplify_im::<AdaptableModifier, 50>(&modifier);
 
         assert_eq!(expr2, expected_expr2);
 
         let mut expr3 = Expression::from_str("2 ^ x").unwrap();
         let expected_expr3 = Expression::from_str("log(x)").unwrap();
-        expr3.simplify_im::<AdaptableModifier, 50>(&mut modifier);
+        expr3.simplify_im::<AdaptableModifier, 50>(&modifier);
 
         assert_eq!(expr3, expected_expr3);
     }
@@ -726,11 +797,11 @@ mod tests {
 
         let mut expr = Expression::from_str("1 - 2").unwrap();
 
-        assert_eq!(modifier.modify_mut(&mut expr), true);
+        assert!(modifier.modify_mut(&mut expr));
 
         let mut expr = Expression::from_str("1 + 2").unwrap();
 
-        assert_eq!(modifier.modify_mut(&mut expr), false);
+        assert!(!modifier.modify_mut(&mut expr));
     }
 
     #[test]
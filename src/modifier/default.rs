@@ -2,10 +2,14 @@ use alloc::{
     boxed::Box,
     string::{String, ToString},
     vec,
+    vec::Vec,
 };
 use heapless::LinearMap;
 
-use crate::expression::expression_tree::{Atom, Escape, Expression, Numeric};
+use crate::expression::{
+    eval::prune_piecewise,
+    expression_tree::{flatten_mul, rebuild_mul, Atom, Escape, Expression, Numeric},
+};
 
 use super::adaptable_modifier::{AdaptableModifier, ModifierFunction};
 
@@ -46,7 +50,10 @@ pub fn reorganize() -> AdaptableModifier {
         ("_*1 - (_*2 - _*3)", "_*1 - _*2 + _*3"),
         ("_*1 * (_*2 * _*3)", "_*1 * _*2 * _*3"),
         ("_*1 * (_*2 / _*3)", "_*1 * _*2 / _*3"),
+        ("(_*1 / _*2) * _*3", "_*1 * _*3 / _*2"),
+        ("(_*1 / _*2) / (_*3 / _*4)", "(_*1 * _*4) / (_*2 * _*3)"),
         ("(_*1 / _*2) / _*3", "_*1 / (_*2 * _*3)"),
+        ("_*1 / (_*2 / _*3)", "_*1 * _*3 / _*2"),
         ("(-_*1) / _*2", "-(_*1/_*2)"),
         ("_*1/(-_*2)", "-(_*1/_*2)"),
     ]);
@@ -236,6 +243,7 @@ fn denegate_internal(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool)
                     )
                 }
             }
+            Numeric::Complex { re: _, im: _ } => (Expression::Atom(Atom::Numeric(*n)), false),
         },
         e => (e.clone(), false),
     }
@@ -243,7 +251,7 @@ fn denegate_internal(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool)
 
 // an AdaptableModifier that reduces identities in an expression tree
 pub fn reduce() -> AdaptableModifier {
-    AdaptableModifier::from_str_list(vec![
+    let identities = AdaptableModifier::from_str_list(vec![
         ("_*1 + _*1", "2 * _*1"),
         ("_A1 * _*1 + _*1", "(1 + _A1) * _*1"),
         ("_*1 + _A1 * _*1", "(1 + _A1) * _*1"),
@@ -262,15 +270,211 @@ pub fn reduce() -> AdaptableModifier {
         ("_*1 + _A1 * _*2 - _A2 * _*2", "_*1 + (_A1 - _A2) * _*2"),
         ("--_*1", "_*1"),
         ("0 + _*1", "_*1"),
+        ("_*1 + 0", "_*1"),
+        ("_*1 - 0", "_*1"),
         ("_*1 + -_*2", "_*1 - _*2"),
+        ("_*1 - -_*2", "_*1 + _*2"),
         ("-_*1 + _*2", "_*2 - _*1"),
         ("1 * _*1", "_*1"),
         ("0 * _*1", "0"),
+        // negations are pulled out in front of products, so signs never sit inside of one
         ("-_*1 * _*2", "-(_*1 * _*2)"),
         ("_*1 * -_*2", "-(_*1 * _*2)"),
         ("-_*1 * -_*2", "_*1 * _*2"),
         ("_*1 / 1", "_*1"),
-        ("0 / _*1", "0"),
+        ("_*1 ^ 1", "_*1"),
+    ]);
+
+    let parity = AdaptableModifier::from_fn_list(vec![(
+        "(-_*1) ^ _A1".parse::<Expression>().unwrap(),
+        Box::new(negated_power),
+    )]);
+
+    let zero = AdaptableModifier::from_fn_list(vec![(
+        "0 / _*1".parse::<Expression>().unwrap(),
+        Box::new(zero_dividend),
+    )]);
+
+    let cancel = AdaptableModifier::from_fn_list(vec![
+        (
+            "(_*1 * _*2) / _*3".parse::<Expression>().unwrap(),
+            cancel_factors(|map| (escape(map, 1) * escape(map, 2), escape(map, 3))),
+        ),
+        (
+            "_*1 / (_*2 * _*3)".parse::<Expression>().unwrap(),
+            cancel_factors(|map| (escape(map, 1), escape(map, 2) * escape(map, 3))),
+        ),
+        (
+            "_*1 / _*1".parse::<Expression>().unwrap(),
+            cancel_factors(|map| (escape(map, 1), escape(map, 1))),
+        ),
+    ]);
+
+    let powers = AdaptableModifier::from_fn_list(vec![
+        (
+            "_*1 ^ _*2 * _*1 ^ _*3".parse::<Expression>().unwrap(),
+            power_law(|x, a, b| power(x, a) * power(x, b), |a, b| a + b, |_| true),
+        ),
+        (
+            "(_*1 ^ _*2) ^ _*3".parse::<Expression>().unwrap(),
+            power_law(
+                |x, a, b| power(&power(x, a), b),
+                |a, b| a * b,
+                |b| matches!(b, Expression::Atom(Atom::Numeric(Numeric::Integer(_)))),
+            ),
+        ),
+        (
+            "_*1 ^ _*2 / _*1 ^ _*3".parse::<Expression>().unwrap(),
+            power_law(|x, a, b| power(x, a) / power(x, b), |a, b| a - b, |_| true),
+        ),
+    ]);
+
+    // a rule for every number of branches the eight escapes can hold, _*1 if _*2, _*3 if _*4, ...,
+    // a piecewise with more branches is left as is
+    let piecewise = AdaptableModifier::from_fn_list(
+        (1..=4)
+            .map(|n| (piecewise_pattern(n), prune_branches(n)))
+            .collect(),
+    );
+
+    identities + parity + zero + cancel + powers + piecewise
+}
+
+// a piecewise of n branches whose values and conditions are all escapes
+fn piecewise_pattern(n: u8) -> Expression {
+    let everything = |i| Expression::Atom(Atom::Escape(Escape::Everything, i));
+    Expression::Piecewise(
+        (0..n)
+            .map(|i| (everything(2 * i + 1), everything(2 * i + 2)))
+            .collect(),
+    )
+}
+
+// drops the branches of a piecewise of n branches whose conditions fold to false, and collapses it
+// to the value of the first branch whose condition folds to true
+fn prune_branches(n: u8) -> ModifierFunction {
+    Box::new(move |map: &LinearMap<Atom, Expression, 8>| {
+        let original = Expression::Piecewise(
+            (0..n)
+                .map(|i| (escape(map, 2 * i + 1), escape(map, 2 * i + 2)))
+                .collect(),
+        );
+        let pruned = prune_piecewise(&original);
+        let modified = pruned != original;
+
+        (pruned, modified)
+    })
+}
+
+// x^a * x^b is x^(a + b), (x^a)^b is x^(a * b) and x^a / x^b is x^(a - b), with the exponents
+// combined symbolically, powers of zero are left alone so 0^a * 0^-a never becomes 0^0
+// the law only applies when applies accepts b, (x^a)^b needs a whole b since (x^2)^(1/2) is |x|
+fn power_law(
+    original: fn(&Expression, &Expression, &Expression) -> Expression,
+    combine: fn(Expression, Expression) -> Expression,
+    applies: fn(&Expression) -> bool,
+) -> ModifierFunction {
+    Box::new(move |map: &LinearMap<Atom, Expression, 8>| {
+        let base = map.get(&Atom::Escape(Escape::Everything, 1)).unwrap();
+        let a = map.get(&Atom::Escape(Escape::Everything, 2)).unwrap();
+        let b = map.get(&Atom::Escape(Escape::Everything, 3)).unwrap();
+
+        match base {
+            Expression::Atom(Atom::Numeric(n)) if f32::from(*n) == 0.0 => {
+                (original(base, a, b), false)
+            }
+            _ if !applies(b) => (original(base, a, b), false),
+            _ => (power(base, &combine(a.clone(), b.clone())), true),
+        }
+    })
+}
+
+fn power(base: &Expression, exponent: &Expression) -> Expression {
+    Expression::Power(Box::new(base.clone()), Box::new(exponent.clone()))
+}
+
+// 0/x is 0, unless x is itself zero
+fn zero_dividend(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool) {
+    match map.get(&Atom::Escape(Escape::Everything, 1)).unwrap() {
+        Expression::Atom(Atom::Numeric(n)) if f32::from(*n) == 0.0 => (
+            Expression::Atom(Atom::Numeric(*n)) / Expression::Atom(Atom::Numeric(*n)),
+            false,
+        ),
+        _ => (Expression::Atom(Atom::Numeric(Numeric::Integer(0))), true),
+    }
+}
+
+// factors shared by the numerator and the denominator cancel, (x * y) / x is y, x / x is 1 and
+// (a * b) / (b * c) is a / c, a factor cancels against one structurally equal to it
+// the cancelled factors are assumed to be nonzero, only a literal zero is never cancelled
+fn cancel_factors(
+    fraction: fn(&LinearMap<Atom, Expression, 8>) -> (Expression, Expression),
+) -> ModifierFunction {
+    Box::new(move |map: &LinearMap<Atom, Expression, 8>| {
+        let (numerator, denominator) = fraction(map);
+        cancel(&numerator, &denominator)
+    })
+}
+
+fn cancel(numerator: &Expression, denominator: &Expression) -> (Expression, bool) {
+    let mut remaining = flatten_mul(numerator);
+    let mut kept = Vec::new();
+    for factor in flatten_mul(denominator) {
+        let zero = matches!(factor, Expression::Atom(Atom::Numeric(n)) if f32::from(n) == 0.0);
+        match remaining.iter().position(|f| *f == factor) {
+            Some(i) if !zero => {
+                remaining.remove(i);
+            }
+            _ => kept.push(factor),
+        }
+    }
+
+    match (kept.len() < flatten_mul(denominator).len(), kept.is_empty()) {
+        (false, _) => (numerator.clone() / denominator.clone(), false),
+        (true, true) => (rebuild_mul(remaining), true),
+        (true, false) => (rebuild_mul(remaining) / rebuild_mul(kept), true),
+    }
+}
+
+fn escape(map: &LinearMap<Atom, Expression, 8>, n: u8) -> Expression {
+    map.get(&Atom::Escape(Escape::Everything, n))
+        .unwrap()
+        .clone()
+}
+
+// (-x)^n is x^n for even integers n and -x^n for odd ones, other exponents keep the negation
+fn negated_power(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool) {
+    let base = map.get(&Atom::Escape(Escape::Everything, 1)).unwrap();
+    let exponent = map.get(&Atom::Escape(Escape::Atom, 1)).unwrap();
+    let power = Expression::Power(Box::new(base.clone()), Box::new(exponent.clone()));
+
+    match exponent {
+        Expression::Atom(Atom::Numeric(Numeric::Integer(n))) if n % 2 == 0 => (power, true),
+        Expression::Atom(Atom::Numeric(Numeric::Integer(_))) => {
+            (Expression::Negate(Box::new(power)), true)
+        }
+        _ => (
+            Expression::Power(
+                Box::new(Expression::Negate(Box::new(base.clone()))),
+                Box::new(exponent.clone()),
+            ),
+            false,
+        ),
+    }
+}
+
+// an AdaptableModifier that applies trigonometric identities, tangents are rewritten in terms of
+// sines and cosines so the pythagorean identity can see them
+// not part of simplifier(), combine the two to have tangents rewritten
+pub fn trig_identities() -> AdaptableModifier {
+    AdaptableModifier::from_str_list(vec![
+        ("sin(_*1) ^ 2 + cos(_*1) ^ 2", "1"),
+        ("cos(_*1) ^ 2 + sin(_*1) ^ 2", "1"),
+        ("_*2 + sin(_*1) ^ 2 + cos(_*1) ^ 2", "_*2 + 1"),
+        ("_*2 + cos(_*1) ^ 2 + sin(_*1) ^ 2", "_*2 + 1"),
+        ("tan(_*1)", "sin(_*1) / cos(_*1)"),
+        ("sin(0)", "0"),
+        ("cos(0)", "1"),
     ])
 }
 
@@ -358,7 +562,8 @@ fn div_numeric(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool) {
         map.get(&Atom::Escape(Escape::Atom, 2)).unwrap(),
     ) {
         (Expression::Atom(a1), Expression::Atom(a2)) => match (a1, a2) {
-            (Atom::Numeric(n1), Atom::Numeric(n2)) => {
+            // a zero divisor is left in place for evaluate to report
+            (Atom::Numeric(n1), Atom::Numeric(n2)) if f32::from(*n2) != 0.0 => {
                 (Expression::Atom(Atom::Numeric(*n1 / *n2)), true)
             }
             _ => (Expression::Atom(*a1) / Expression::Atom(*a2), false),
@@ -499,6 +704,10 @@ pub fn log_approx() -> AdaptableModifier {
             "log(_A1)".parse::<Expression>().unwrap(),
             single_num_approx(libm::logf, "log".to_string()),
         ),
+        (
+            "ln(_A1)".parse::<Expression>().unwrap(),
+            single_num_approx(libm::logf, "ln".to_string()),
+        ),
         (
             "log2(_A1)".parse::<Expression>().unwrap(),
             single_num_approx(libm::log2f, "log2".to_string()),
@@ -631,6 +840,47 @@ fn value_replace(expr: &Expression, var: &Expression, val: &Expression) -> Expre
                     .collect(),
                 shape: *shape,
             },
+            // the variable of an integral and the index of a sum are bound inside the integrand and
+            // the body, only the bounds see the outer value
+            Expression::Integral {
+                integrand,
+                var: v,
+                lower,
+                upper,
+            } if *var == Expression::Atom(Atom::Variable(*v)) => Expression::Integral {
+                integrand: integrand.clone(),
+                var: *v,
+                lower: lower.as_ref().map(|e| Box::new(value_replace(e, var, val))),
+                upper: upper.as_ref().map(|e| Box::new(value_replace(e, var, val))),
+            },
+            e @ Expression::Integral {
+                integrand: _,
+                var: _,
+                lower: _,
+                upper: _,
+            } => e.map_children(|c| value_replace(c, var, val)),
+            Expression::Sum {
+                index,
+                lower,
+                upper,
+                body,
+            } if *var == Expression::Atom(Atom::Variable(*index)) => Expression::Sum {
+                index: *index,
+                lower: Box::new(value_replace(lower, var, val)),
+                upper: Box::new(value_replace(upper, var, val)),
+                body: body.clone(),
+            },
+            e @ Expression::Sum {
+                index: _,
+                lower: _,
+                upper: _,
+                body: _,
+            } => e.map_children(|c| value_replace(c, var, val)),
+            e @ (Expression::Relation(_, _, _)
+            | Expression::And(_)
+            | Expression::Or(_)
+            | Expression::Not(_)
+            | Expression::Piecewise(_)) => e.map_children(|c| value_replace(c, var, val)),
         }
     }
 }
@@ -711,13 +961,16 @@ fn int_approx(map: &LinearMap<Atom, Expression, 8>) -> (Expression, bool) {
 
 #[cfg(test)]
 mod tests {
+    use alloc::{boxed::Box, vec};
+
     use crate::{
-        expression::expression_tree::Expression,
+        expression::expression_tree::{Atom, Comparison, Expression, Numeric},
         modifier::{
             adaptable_modifier::AdaptableModifier,
             default::{
                 approximator, calculus_approx, evaluator, log_approx, num_approx_helper,
                 numeric_fun_approx, numeric_simplify, reduce, reorganize, simplifier, trig_approx,
+                trig_identities, value_replace,
             },
         },
     };
@@ -838,6 +1091,274 @@ mod tests {
         assert_eq!(expr8, "0".parse::<Expression>().unwrap());
     }
 
+    #[test]
+    fn test_div_by_zero() {
+        let simp = simplifier();
+
+        for s in ["5 / 0", "5 % 0", "0 / 0", "x / 0"] {
+            let mut expr = s.parse::<Expression>().unwrap();
+            expr.simplify_im::<AdaptableModifier, 100>(&simp);
+
+            assert_eq!(expr, s.parse::<Expression>().unwrap());
+        }
+
+        let mut expr = "0 / x + 5 / (3 - 3)".parse::<Expression>().unwrap();
+        expr.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr, "5 / 0".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_reduce_negations() {
+        let red = reduce();
+
+        let mut expr1 = -(-Expression::var('x'));
+        expr1.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr1, "x".parse::<Expression>().unwrap());
+
+        let mut expr2 = "a + (-b)".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr2, "a - b".parse::<Expression>().unwrap());
+
+        let mut expr3 = "a - (-b)".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr3, "a + b".parse::<Expression>().unwrap());
+
+        let mut expr4 = "a - (-(-b))".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr4, "a - b".parse::<Expression>().unwrap());
+
+        let mut expr5 = "x - (-(y * 0))".parse::<Expression>().unwrap();
+        expr5.simplify_im::<AdaptableModifier, 100>(&simplifier());
+
+        assert_eq!(expr5, "x".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_reduce_negated_products() {
+        let red = reduce();
+
+        let mut expr1 = "(-a) * b".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr1, "-(a * b)".parse::<Expression>().unwrap());
+
+        let mut expr2 = "a * (-b)".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr2, "-(a * b)".parse::<Expression>().unwrap());
+
+        let mut expr3 = "(-a) * (-b)".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr3, "a * b".parse::<Expression>().unwrap());
+
+        let mut expr4 = "(-a) * (-b) * (-c)".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr4, "-(a * b * c)".parse::<Expression>().unwrap());
+
+        let mut expr5 = "f(a * (-b)) + x * (-y) * z".parse::<Expression>().unwrap();
+        expr5.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(
+            expr5,
+            "f(-(a * b)) - x * y * z".parse::<Expression>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trig_identities() {
+        let trig = simplifier() + trig_identities();
+
+        let mut expr1 = "sin(x) ^ 2 + cos(x) ^ 2".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr1, "1".parse::<Expression>().unwrap());
+
+        let mut expr2 = "y + cos(2 * x) ^ 2 + sin(2 * x) ^ 2"
+            .parse::<Expression>()
+            .unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr2, "1 + y".parse::<Expression>().unwrap());
+
+        let mut expr3 = "sin(x) ^ 2 + cos(y) ^ 2".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(
+            expr3,
+            "sin(x) ^ 2 + cos(y) ^ 2".parse::<Expression>().unwrap()
+        );
+
+        let mut expr4 = "tan(x)".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr4, "sin(x) / cos(x)".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_trig_identities_zero() {
+        let trig = simplifier() + trig_identities();
+
+        let mut expr1 = "sin(0)".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr1, "0".parse::<Expression>().unwrap());
+
+        let mut expr2 = "x * cos(0) + sin(0)".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr2, "x".parse::<Expression>().unwrap());
+
+        let mut expr3 = "tan(0)".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&trig);
+
+        assert_eq!(expr3, "0".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_reduce_negated_power() {
+        let red = reduce();
+
+        let mut expr1 = "(-x) ^ 2".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr1, "x ^ 2".parse::<Expression>().unwrap());
+
+        let mut expr2 = "(-x) ^ 3".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr2, "-(x ^ 3)".parse::<Expression>().unwrap());
+
+        let mut expr3 = "(-(x + y)) ^ 4".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr3, "(x + y) ^ 4".parse::<Expression>().unwrap());
+
+        let mut expr4 = "(-x) ^ 0.5".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr4, "(-x) ^ 0.5".parse::<Expression>().unwrap());
+
+        let mut expr5 = "(-x) ^ n".parse::<Expression>().unwrap();
+        expr5.simplify_im::<AdaptableModifier, 100>(&red);
+
+        assert_eq!(expr5, "(-x) ^ n".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_reduce_power_laws() {
+        let simp = simplifier();
+
+        let mut expr1 = "x ^ 2 * x ^ 3".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr1, "x ^ 5".parse::<Expression>().unwrap());
+
+        let mut expr2 = "(x ^ 2) ^ 3".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr2, "x ^ 6".parse::<Expression>().unwrap());
+
+        let mut expr3 = "(x + 1) ^ n / (x + 1) ^ 2".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr3, "(1 + x) ^ (n - 2)".parse::<Expression>().unwrap());
+
+        let mut expr4 = "y ^ a * y ^ b".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr4, "y ^ (a + b)".parse::<Expression>().unwrap());
+
+        let mut expr5 = "0 ^ n * 0 ^ (-n)".parse::<Expression>().unwrap();
+        expr5.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr5, "0 ^ n * 0 ^ (-n)".parse::<Expression>().unwrap());
+
+        // (x^2)^(1/2) is 3 at x = -3, so it is not x
+        let mut expr6 = "(x ^ 2) ^ (1 / 2)".parse::<Expression>().unwrap();
+        expr6.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(
+            expr6,
+            Expression::Power(
+                Box::new("x ^ 2".parse::<Expression>().unwrap()),
+                Box::new(Expression::Atom(Atom::Numeric(Numeric::Fraction(1, 2)))),
+            )
+        );
+
+        let mut expr7 = "(x ^ (1 / 2)) ^ 2".parse::<Expression>().unwrap();
+        expr7.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr7, "x".parse::<Expression>().unwrap());
+    }
+
+    #[test]
+    fn test_reduce_cancel_factors() {
+        let simp = simplifier();
+        let simplify = |s: &str| {
+            let mut expr = s.parse::<Expression>().unwrap();
+            expr.simplify_im::<AdaptableModifier, 100>(&simp);
+            expr
+        };
+
+        assert_eq!(simplify("(x * y) / x"), "y".parse::<Expression>().unwrap());
+        assert_eq!(
+            simplify("(2 * a) / (2 * b)"),
+            "a / b".parse::<Expression>().unwrap()
+        );
+        assert_eq!(
+            simplify("(a * b) / (b * c)"),
+            "a / c".parse::<Expression>().unwrap()
+        );
+        assert_eq!(
+            simplify("x / (x * y)"),
+            "1 / y".parse::<Expression>().unwrap()
+        );
+        assert_eq!(
+            simplify("(a * b) / c"),
+            "(a * b) / c".parse::<Expression>().unwrap()
+        );
+        assert_eq!(simplify("x / x"), "1".parse::<Expression>().unwrap());
+        assert_eq!(
+            simplify("(x * y) / (x * y)"),
+            "1".parse::<Expression>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reorganize_fractions() {
+        let simp = simplifier();
+
+        let mut expr1 = "(a / b) / (c / d)".parse::<Expression>().unwrap();
+        expr1.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr1, "(a * d) / (b * c)".parse::<Expression>().unwrap());
+
+        let mut expr2 = "a / (b / c)".parse::<Expression>().unwrap();
+        expr2.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr2, "(a * c) / b".parse::<Expression>().unwrap());
+
+        let mut expr3 = "(a / b) * (c / d)".parse::<Expression>().unwrap();
+        expr3.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(expr3, "(a * c) / (b * d)".parse::<Expression>().unwrap());
+
+        let mut expr4 = "((a / b) / c) / (d / e)".parse::<Expression>().unwrap();
+        expr4.simplify_im::<AdaptableModifier, 100>(&simp);
+
+        assert_eq!(
+            expr4,
+            "(a * e) / (b * c * d)".parse::<Expression>().unwrap()
+        );
+    }
+
     #[test]
     fn test_numeric_simplify() {
         let num = numeric_simplify();
@@ -1341,4 +1862,55 @@ mod tests {
 
         assert_eq!(expr9_comp, "3.1780539".parse::<Expression>().unwrap()); // should be ~3.20537, but approx isn't exact
     }
+
+    #[test]
+    fn test_value_replace_bound() {
+        let e = |s: &str| s.parse::<Expression>().unwrap();
+        let sum = |upper| Expression::Sum {
+            index: 'k',
+            lower: Box::new(e("1")),
+            upper: Box::new(e(upper)),
+            body: Box::new(e("k * x")),
+        };
+
+        assert_eq!(value_replace(&sum("k"), &e("k"), &e("3")), sum("3"));
+
+        let integral = |upper| Expression::Integral {
+            integrand: Box::new(e("k ^ 2")),
+            var: 'k',
+            lower: Some(Box::new(e("0"))),
+            upper: Some(Box::new(e(upper))),
+        };
+        assert_eq!(
+            value_replace(&integral("k"), &e("k"), &e("3")),
+            integral("3")
+        );
+    }
+
+    #[test]
+    fn test_simplify_piecewise() {
+        let e = |s: &str| s.parse::<Expression>().unwrap();
+        let relation = |op, l, r| Expression::Relation(op, Box::new(e(l)), Box::new(e(r)));
+
+        let mut expr = Expression::Piecewise(vec![
+            (e("1"), relation(Comparison::Less, "1", "0")),
+            (e("x"), relation(Comparison::Greater, "x", "0")),
+            (e("-x"), relation(Comparison::LessEqual, "x", "0")),
+        ]);
+        expr.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(
+            expr,
+            Expression::Piecewise(vec![
+                (e("x"), relation(Comparison::Greater, "x", "0")),
+                (e("-x"), relation(Comparison::LessEqual, "x", "0")),
+            ])
+        );
+
+        let mut expr = Expression::Piecewise(vec![
+            (e("1"), relation(Comparison::Less, "1", "0")),
+            (e("y + y"), relation(Comparison::Equal, "1 + 1", "2")),
+        ]);
+        expr.simplify_im::<AdaptableModifier, 100>(&simplifier());
+        assert_eq!(expr, e("2 * y"));
+    }
 }
@@ -77,7 +77,7 @@ mod tests {
             )
             .1
             .unwrap(),
-            Expression::Atom(Atom::Numeric(Numeric::Decimal(0.91294525073)))
+            Expression::Atom(Atom::Numeric(Numeric::Decimal(0.9129453)))
         );
     }
 }
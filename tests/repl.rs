@@ -0,0 +1,54 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+// runs the repl on the given lines and returns everything it printed
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_citrus"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_repl_evaluates() {
+    let output = repl("2 + 3 * 4\nx + x\n");
+
+    assert!(output.contains("2 + 3 * 4\n= 14\n≈ 14\n"), "{}", output);
+    assert!(output.contains("x + x\n= 2 * x\n> "), "{}", output);
+}
+
+#[test]
+fn test_repl_modes() {
+    let output = repl(":latex\nx / 2\n:diff x\nx ^ 2\n:plain\n:quit\n1 + 1\n");
+
+    assert!(output.contains("\\frac{x}{2}\n"), "{}", output);
+    assert!(output.contains("= 2 * x\n"), "{}", output);
+    assert!(!output.contains("= 2\n"), "{}", output);
+}
+
+#[test]
+fn test_repl_errors() {
+    let output = repl("1 + )\n:frobnicate\n1.2.3\n3\n");
+
+    assert_eq!(
+        output.matches("error: Invalid syntax\n").count(),
+        2,
+        "{}",
+        output
+    );
+    assert!(output.contains("unknown command"), "{}", output);
+    assert!(output.contains("3\n= 3\n≈ 3\n"), "{}", output);
+}